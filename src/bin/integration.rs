@@ -5,6 +5,8 @@ An integration test that demonstrates the code works from client code external t
 */
 #![allow(unused)]
 
+use std::collections::HashMap;
+
 use ixa_entities::{
     define_entity,
     define_property,
@@ -92,4 +94,14 @@ fn main() {
         );
         assert_eq!(vaccine_status.get(PersonId::new(2)), Some(Vaccinated(true)));
     }
+
+    // Verify the contingency table of infection status against vaccination
+    assert_eq!(
+        context.crosstab::<Person, InfectionStatus, Vaccinated>(),
+        HashMap::from([
+            ((InfectionStatus::Susceptible, Vaccinated(true)), 1),
+            ((InfectionStatus::Susceptible, Vaccinated(false)), 1),
+            ((InfectionStatus::Infected, Vaccinated(true)), 1),
+        ])
+    );
 }
@@ -0,0 +1,192 @@
+/*!
+
+Recording which property values changed since a checkpoint, so that a model that recomputes
+aggregates every tick only has to revisit the entities that changed.
+
+```rust,ignore
+context.enable_change_tracking();
+// ... run a tick ...
+let dirty = context.drain_dirty();
+for person in dirty.entities::<Person, InfectionStatus>() {
+    // ...
+}
+```
+
+While tracking is enabled, every value set with [`Context::set_property`] and the methods built
+on it, e.g. [`Context::set_property_bulk`] and [`Context::modify_property`], and every value
+cleared with [`Context::unset_property`], adds its entity and property to the dirty set, even if
+the new value equals the old one. Values given when an entity is created are not changes.
+
+*/
+
+use std::{any::TypeId, collections::HashSet};
+
+use crate::{
+    Context,
+    entity::{Entity, EntityId, property::Property},
+};
+
+/// The `(entity, property)` pairs whose values were set since change tracking was enabled or the
+/// set was last drained.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DirtySet {
+    /// The property `TypeId` and the entity index of each change.
+    changes: HashSet<(TypeId, usize)>,
+}
+
+impl DirtySet {
+    /// The number of `(entity, property)` pairs.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether the value of property `P` for `entity_id` was set.
+    pub fn contains<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>) -> bool {
+        self.changes.contains(&(P::type_id(), entity_id.0))
+    }
+
+    /// The entities whose value of property `P` was set, in id order.
+    pub fn entities<E: Entity, P: Property<E>>(&self) -> Vec<EntityId<E>> {
+        let mut entity_ids: Vec<EntityId<E>> = self
+            .changes
+            .iter()
+            .filter(|(property_type_id, _)| *property_type_id == P::type_id())
+            .map(|(_, index)| EntityId::new(*index))
+            .collect();
+        entity_ids.sort();
+        entity_ids
+    }
+
+    /// The entities of type `E` with at least one property value set, in id order.
+    pub fn entities_of<E: Entity>(&self) -> Vec<EntityId<E>> {
+        let mut entity_ids: Vec<EntityId<E>> = self
+            .changes
+            .iter()
+            .filter(|(property_type_id, _)| E::property_ids().contains(property_type_id))
+            .map(|(_, index)| EntityId::new(*index))
+            .collect();
+        entity_ids.sort();
+        entity_ids.dedup();
+        entity_ids
+    }
+
+    /// Iterates over the changes as `(entity index, property TypeId)` pairs, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, TypeId)> + '_ {
+        self.changes
+            .iter()
+            .map(|(property_type_id, index)| (*index, *property_type_id))
+    }
+}
+
+impl Context {
+    /// Starts recording the values that are set in a [`DirtySet`]. Does nothing if tracking is
+    /// already enabled.
+    pub fn enable_change_tracking(&mut self) {
+        self.dirty.get_mut().get_or_insert_with(DirtySet::default);
+    }
+
+    /// Stops recording changes and discards the ones recorded.
+    pub fn disable_change_tracking(&mut self) {
+        *self.dirty.get_mut() = None;
+    }
+
+    /// Returns the changes recorded since tracking was enabled or this method was last called,
+    /// and starts a new, empty set. Returns an empty set if tracking isn't enabled.
+    pub fn drain_dirty(&mut self) -> DirtySet {
+        self.dirty
+            .get_mut()
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Empties the dirty set, keeping tracking enabled if it is, for when the entities it names
+    /// are discarded.
+    pub(crate) fn clear_dirty(&mut self) {
+        if let Some(dirty) = self.dirty.get_mut() {
+            dirty.changes.clear();
+        }
+    }
+
+    /// Adds the value of property `P` for `entity_id` to the dirty set, if tracking is enabled.
+    pub(crate) fn record_change<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>) {
+        if let Some(dirty) = self.dirty.borrow_mut().as_mut() {
+            dirty.changes.insert((P::type_id(), entity_id.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{define_entity, define_property};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+    define_property!(struct Weight(u16), Person);
+
+    #[test]
+    fn dirty_set_holds_exactly_the_values_set() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..5).map(|age| context.add_entity((Age(age),))).collect();
+        // Changes before tracking is enabled aren't recorded.
+        context.set_property(people[0], Weight(50));
+        assert!(context.drain_dirty().is_empty());
+
+        context.enable_change_tracking();
+        context.set_property(people[1], InfectionStatus::Infected);
+        context.set_property(people[3], InfectionStatus::Recovered);
+        context.set_property(people[3], Weight(60));
+        context.set_property(people[3], Weight(61));
+        context.modify_property(people[4], |age: &mut Age| age.0 += 1);
+        context.add_entity((Age(40),));
+
+        let dirty = context.drain_dirty();
+        assert_eq!(dirty.len(), 4);
+        assert_eq!(dirty.entities::<Person, InfectionStatus>(), [people[1], people[3]]);
+        assert_eq!(dirty.entities::<Person, Weight>(), [people[3]]);
+        assert!(dirty.contains::<Person, Age>(people[4]));
+        assert!(!dirty.contains::<Person, Age>(people[3]));
+        assert_eq!(dirty.entities_of::<Person>(), [people[1], people[3], people[4]]);
+
+        // Draining starts a new set.
+        assert!(context.drain_dirty().is_empty());
+        context.unset_property::<_, Weight>(people[0]);
+        assert_eq!(context.drain_dirty().entities_of::<Person>(), [people[0]]);
+
+        context.disable_change_tracking();
+        context.set_property(people[2], Weight(70));
+        assert!(context.drain_dirty().is_empty());
+    }
+
+    #[test]
+    fn reset_empties_the_dirty_set() {
+        let mut context = Context::new();
+        context.enable_change_tracking();
+        let person = context.add_entity((Age(30),));
+        context.set_property(person, Weight(60));
+
+        context.reset();
+        assert!(context.drain_dirty().is_empty());
+
+        // Tracking is still enabled.
+        let person = context.add_entity((Age(40),));
+        context.set_property(person, Weight(70));
+        assert_eq!(context.drain_dirty().entities::<Person, Weight>(), [person]);
+    }
+}
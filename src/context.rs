@@ -1,83 +1,916 @@
+use std::{
+    any::TypeId,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
 use crate::entity::{
     Entity, 
     EntityId,
-    entity_store::EntityStore,
+    entity_keyed_map::{EntityKeyedMap, EntityKeyedSparseMap},
+    entity_store::{EntityStore, EntityTypeMeta, get_entity_metadata, get_registered_entities},
+    membership_index::AnyMembershipIndex,
     property_list::PropertyList,
-    property_store::PropertyStore,
-    property::{Property, PropertyInitializationKind}
+    property_store::{
+        PropertyMeta, PropertyStore, RegisteredProperty, get_property_name, get_registered_properties,
+        get_registered_property,
+    },
+    property::{Property, PropertyInitializationKind},
+    relationship_store::AnyRelationshipStore,
 };
+use crate::{change_tracking::DirtySet, events::EventHandlers, value_vec::GrowthStrategy};
+
+/// The reasons `Context::try_add_entity` can refuse to create an entity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AddEntityError {
+    /// The same property appears more than once in the property list.
+    DuplicateProperty(String),
+    /// The property list lacks these required properties of the entity.
+    MissingRequired(Vec<&'static str>),
+    /// A value in the property list was rejected by [`Property::validate`].
+    InvalidValue(InvalidPropertyValueError),
+}
+
+impl Display for AddEntityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddEntityError::DuplicateProperty(msg) => write!(f, "invalid property list: {msg}"),
+            AddEntityError::MissingRequired(names) => write!(
+                f,
+                "initialization list is missing required properties: {}",
+                names.join(", ")
+            ),
+            AddEntityError::InvalidValue(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for AddEntityError {}
+
+/// The error returned when a value may not be stored: [`Property::validate`] rejects it, e.g. a
+/// reference to an entity that doesn't exist, it's a value of a derived property, or the entity
+/// it would be stored for doesn't exist or was removed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidPropertyValueError {
+    pub property_name: &'static str,
+    pub message: String,
+}
+
+impl InvalidPropertyValueError {
+    /// Checks that `value` may be stored. A derived property's values are computed, never
+    /// stored, so any value of one is rejected; otherwise this runs [`Property::validate`].
+    pub fn check<E: Entity, P: Property<E>>(context: &Context, value: &P) -> Result<(), Self> {
+        if P::is_derived() {
+            return Err(Self {
+                property_name: P::name(),
+                message: "the property is derived, so its values can't be set".to_string(),
+            });
+        }
+        P::validate(context, value).map_err(|message| Self {
+            property_name: P::name(),
+            message,
+        })
+    }
+
+    /// Checks that a value of property `P` may be stored for `entity_id`, which must be live: the
+    /// value of an entity that doesn't exist or was removed could never be read.
+    pub(crate) fn check_live<E: Entity, P: Property<E>>(
+        context: &Context,
+        entity_id: EntityId<E>,
+    ) -> Result<(), Self> {
+        if context.entity_store.is_live(entity_id) {
+            return Ok(());
+        }
+        Err(Self {
+            property_name: P::name(),
+            message: format!("{entity_id} does not exist"),
+        })
+    }
+}
+
+impl Display for InvalidPropertyValueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid value for property {}: {}", self.property_name, self.message)
+    }
+}
+
+impl std::error::Error for InvalidPropertyValueError {}
+
+/// The reasons `Context::set_property_by_name` can fail to set a value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SetPropertyByNameError {
+    /// The entity has no property with this name.
+    UnknownProperty {
+        entity_name: &'static str,
+        property_name: String,
+    },
+    /// The string is not a valid value of the property.
+    Parse {
+        property_name: &'static str,
+        message: String,
+    },
+    /// The parsed value was rejected by [`Property::validate`], or the property is derived.
+    InvalidValue(InvalidPropertyValueError),
+}
+
+impl Display for SetPropertyByNameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetPropertyByNameError::UnknownProperty {
+                entity_name,
+                property_name,
+            } => write!(f, "{entity_name} has no property named \"{property_name}\""),
+            SetPropertyByNameError::Parse {
+                property_name,
+                message,
+            } => write!(f, "invalid value for property {property_name}: {message}"),
+            SetPropertyByNameError::InvalidValue(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for SetPropertyByNameError {}
+
+/// The error `Context::try_get_property` returns for an explicit property that was never set,
+/// identifying the property and the entity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertyNotSetError {
+    pub property_name: &'static str,
+    pub entity_name: &'static str,
+    /// The index of the entity's id.
+    pub index: usize,
+}
+
+impl Display for PropertyNotSetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "attempted to get a property value with \"explicit\" initialization that was not set: \
+             property {} of {} {}",
+            self.property_name, self.entity_name, self.index
+        )
+    }
+}
+
+impl std::error::Error for PropertyNotSetError {}
 
 /// A minimalist stand-in for a `Context` object.
+///
+/// A `Context` is neither `Send` nor `Sync`, and wrapping it in a lock wouldn't make it so.
+/// Reading isn't free of writes: looking up a property's storage may create it, and reading a
+/// default function property stores the computed value, both through shared references. And the
+/// property stores and event handlers are type-erased without `Send` bounds, so a handler may
+/// hold an `Rc`. To work with property values on several threads, copy them out first, into a
+/// [`SyncContext`](crate::sync_context::SyncContext) or as the methods of the `parallel` module
+/// do.
 pub struct Context {
     pub entity_store: EntityStore,
     pub property_store: PropertyStore,
+    pub(crate) event_handlers: EventHandlers,
+    /// Maps the `TypeId` of a `RelationshipStore<A, B>` to the store.
+    pub(crate) relationship_stores: HashMap<TypeId, Box<dyn AnyRelationshipStore>>,
+    /// Maps the `TypeId` of a foreign key property to the `MembershipIndex` over it.
+    pub(crate) membership_indexes: HashMap<TypeId, RefCell<Box<dyn AnyMembershipIndex>>>,
+    /// The values set since the last `drain_dirty`, or `None` if change tracking is disabled.
+    pub(crate) dirty: RefCell<Option<DirtySet>>,
+}
+
+/// A deep copy of the population: every entity, property value, relationship, and membership
+/// index is copied, so the clone and the original can be changed independently. Event handlers
+/// can't be copied, so the clone starts without any, and with events dispatched immediately.
+/// Likewise, the clone doesn't track changes.
+impl Clone for Context {
+    fn clone(&self) -> Self {
+        Self {
+            entity_store: self.entity_store.clone(),
+            property_store: self.property_store.clone(),
+            event_handlers: EventHandlers::default(),
+            relationship_stores: self
+                .relationship_stores
+                .iter()
+                .map(|(type_id, store)| (*type_id, store.clone_boxed()))
+                .collect(),
+            membership_indexes: self
+                .membership_indexes
+                .iter()
+                .map(|(type_id, index)| (*type_id, RefCell::new(index.borrow().clone_boxed())))
+                .collect(),
+            dirty: RefCell::new(None),
+        }
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Context::new()
+    }
 }
 
 impl Context {
     pub fn new() -> Self {
+        Self::with_growth_strategy(GrowthStrategy::default())
+    }
+
+    /// Creates a new context whose property value storage grows according to `growth`, e.g.
+    /// [`GrowthStrategy::ExactToLen`] to avoid over-allocating for a population of known size.
+    pub fn with_growth_strategy(growth: GrowthStrategy) -> Self {
+        Self {
+            entity_store: EntityStore::new(),
+            property_store: PropertyStore::with_growth(growth),
+            event_handlers: EventHandlers::default(),
+            relationship_stores: HashMap::new(),
+            membership_indexes: HashMap::new(),
+            dirty: RefCell::new(None),
+        }
+    }
+
+    /// Creates a new context for a population of about `expected_entities` entities of each
+    /// type. Each property's value storage reserves room for that many values when it is first
+    /// accessed, so building the population doesn't reallocate.
+    pub fn with_capacity(expected_entities: usize) -> Self {
         Self {
             entity_store: EntityStore::new(),
-            property_store: PropertyStore::new(),
+            property_store: PropertyStore::with_capacity(expected_entities),
+            event_handlers: EventHandlers::default(),
+            relationship_stores: HashMap::new(),
+            membership_indexes: HashMap::new(),
+            dirty: RefCell::new(None),
         }
     }
 
+    /// Discards every entity, property value, and relationship, returning the context to the
+    /// state of a new one. Queued events and the changes recorded in the dirty set are discarded
+    /// too, since the entities they are about no longer exist. The entity and property
+    /// registries, the subscribed event handlers, and whether change tracking is enabled are
+    /// unaffected.
+    pub fn reset(&mut self) {
+        self.entity_store.reset_entity_counts();
+        self.property_store.clear();
+        self.relationship_stores.clear();
+        self.rebuild_membership_indexes();
+        self.discard_queued_events();
+        self.clear_dirty();
+    }
+
+    /// Creates a new entity with the property values in `property_list`.
+    ///
+    /// Panics if the property list is invalid. Use [`Context::try_add_entity`] to get an error instead.
     pub fn add_entity<E: Entity, PL: PropertyList<E>>(&mut self, property_list: PL) -> EntityId<E> {
-        // Check that the properties in the list are distinct.
-        if let Err(msg) = PL::validate() {
-            panic!("invalid property list: {}", msg);
+        self.try_add_entity(property_list)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Creates a new entity with the property values in `property_list`, or returns an error
+    /// without creating anything if the property list repeats a property or lacks a required one.
+    pub fn try_add_entity<E: Entity, PL: PropertyList<E>>(
+        &mut self,
+        property_list: PL,
+    ) -> Result<EntityId<E>, AddEntityError> {
+        Self::check_property_list::<E, PL>()?;
+
+        // Check that the values may be stored.
+        property_list
+            .validate_values(self)
+            .map_err(AddEntityError::InvalidValue)?;
+
+        // Now that we know we will succeed, we create the entity.
+        Ok(self.create_entity(
+            |property_store, entity_id| property_list.set_values_for_entity(entity_id, property_store),
+            |property_type_id| PL::contains_properties(&[property_type_id]),
+        ))
+    }
+
+    /// Creates one entity per property list in `property_lists`, in order, and returns their ids,
+    /// which are contiguous. Equivalent to calling [`Context::add_entity`] for each list, but
+    /// checks the type `PL` only once, and first reserves room in the value storage of the
+    /// properties every new entity gets a value for, for as many entities as the iterator's
+    /// lower size bound, which for an `ExactSizeIterator` is its length.
+    ///
+    /// Panics if the property list type is invalid, before creating any entity, or if a value is
+    /// rejected by [`Property::validate`], keeping the entities created before it.
+    pub fn add_entities<E: Entity, PL: PropertyList<E>, I: IntoIterator<Item = PL>>(
+        &mut self,
+        property_lists: I,
+    ) -> Vec<EntityId<E>> {
+        Self::check_property_list::<E, PL>().unwrap_or_else(|err| panic!("{err}"));
+
+        let property_lists = property_lists.into_iter();
+        let expected_count = property_lists.size_hint().0;
+        let entity_count = self.get_entity_count::<E>() + expected_count;
+        for property_type_id in E::property_ids() {
+            if let Some(property) = get_registered_property(*property_type_id)
+                && (PL::contains_properties(&[*property_type_id]) || property.init_default.is_some())
+            {
+                (property.reserve_values)(&self.property_store, entity_count);
+            }
         }
 
-        // Check that all required properties are present.
-        if !PL::contains_required_properties() {
-            panic!("initialization list is missing required properties");
+        let mut entity_ids = Vec::with_capacity(expected_count);
+        for property_list in property_lists {
+            property_list
+                .validate_values(self)
+                .unwrap_or_else(|error| panic!("{}", AddEntityError::InvalidValue(error)));
+            entity_ids.push(self.create_entity(
+                |property_store, entity_id| property_list.set_values_for_entity(entity_id, property_store),
+                |property_type_id| PL::contains_properties(&[property_type_id]),
+            ));
         }
+        entity_ids
+    }
 
-        // Now that we know we will succeed, we create the entity.
+    /// Checks that the property list type `PL` has no repeated properties and every required
+    /// property of `E`.
+    fn check_property_list<E: Entity, PL: PropertyList<E>>() -> Result<(), AddEntityError> {
+        // Check that the properties in the list are distinct.
+        PL::validate().map_err(AddEntityError::DuplicateProperty)?;
+
+        // Check that all required properties are present.
+        PL::contains_required_properties().map_err(|missing| {
+            AddEntityError::MissingRequired(
+                missing
+                    .iter()
+                    .map(|id| get_property_name(*id).unwrap_or("<unknown property>"))
+                    .collect(),
+            )
+        })
+    }
+
+    /// Creates a new entity of type `E` whose initial values have already been validated.
+    /// `set_values` assigns them, and `is_assigned` tells whether it assigned a given property.
+    pub(crate) fn create_entity<E: Entity>(
+        &mut self,
+        set_values: impl FnOnce(&PropertyStore, EntityId<E>),
+        is_assigned: impl Fn(TypeId) -> bool,
+    ) -> EntityId<E> {
         let new_entity_id = self.entity_store.new_entity_id::<E>();
+        set_values(&self.property_store, new_entity_id);
 
-        // Assign the properties in the list to the new entity.
-        property_list.set_values_for_entity(new_entity_id.clone(), &self.property_store);
+        // Compute the defaults that depend on the values just assigned.
+        for property_type_id in E::property_ids() {
+            if let Some(init_default) =
+                get_registered_property(*property_type_id).and_then(|property| property.init_default)
+                && !is_assigned(*property_type_id)
+            {
+                init_default(self, new_entity_id.0);
+            }
+        }
+        self.refresh_memberships(new_entity_id);
+        self.emit_entity_created(new_entity_id);
 
         new_entity_id
     }
 
+    /// Removes `entity_id`, clearing all of its property values and deleting every edge to or
+    /// from it in any relationship store. Handlers subscribed with
+    /// [`Context::subscribe_entity_removed`] run first, while the values and edges can still be
+    /// read.
+    /// Returns `false`, doing nothing, if the entity doesn't exist or was already removed.
+    ///
+    /// The ids of removed entities are never reused, and they still count towards
+    /// [`Context::get_entity_count`].
+    pub fn remove_entity<E: Entity>(&mut self, entity_id: EntityId<E>) -> bool {
+        if !self.entity_store.is_live(entity_id) {
+            return false;
+        }
+        self.emit_entity_removed(entity_id);
+
+        self.entity_store.mark_removed(entity_id);
+        for property_type_id in E::property_ids() {
+            if let Some(property) = get_registered_property(*property_type_id) {
+                (property.clear_value)(&self.property_store, entity_id.0);
+            }
+        }
+        for store in self.relationship_stores.values_mut() {
+            store.remove_entity(<E as Entity>::type_id(), entity_id.0);
+        }
+        self.refresh_memberships(entity_id);
+        true
+    }
+
+    /// Renumbers the live entities of type `E` with the contiguous ids `0..n`, in their current
+    /// order, and moves their property values and edges to match, so that the storage no longer
+    /// has holes left by removed entities. Returns a map from each live entity's old id to its
+    /// new id. Property values, of any entity type, that refer to an entity of type `E` are
+    /// renumbered too, and those that refer to a removed one are emptied.
+    ///
+    /// Every `EntityId<E>` held outside the context, including the keys of entity keyed maps,
+    /// refers to the old numbering and must be translated with the returned map.
+    pub fn compact<E: Entity>(&mut self) -> EntityKeyedSparseMap<E, EntityId<E>> {
+        let live_indices: Vec<usize> = self
+            .entity_store
+            .live_entity_ids::<E>()
+            .map(|entity_id| entity_id.0)
+            .collect();
+
+        for property_type_id in E::property_ids() {
+            if let Some(property) = get_registered_property(*property_type_id) {
+                (property.compact_values)(&self.property_store, &live_indices);
+            }
+        }
+        // Values of any entity's properties that refer to a removed entity are emptied.
+        let renumber = |index: usize| live_indices.binary_search(&index).ok();
+        for property in get_registered_properties() {
+            if property.references == Some(<E as Entity>::type_id()) {
+                (property.remap_references)(&self.property_store, &renumber);
+            }
+        }
+        for store in self.relationship_stores.values_mut() {
+            store.remap_entity(<E as Entity>::type_id(), &live_indices);
+        }
+        self.entity_store.set_compacted::<E>(live_indices.len());
+        self.rebuild_membership_indexes();
+
+        let old_count = live_indices.last().map_or(0, |index| index + 1);
+        let mut remap = EntityKeyedSparseMap::with_capacity(old_count);
+        for (new_index, &old_index) in live_indices.iter().enumerate() {
+            remap.insert(EntityId::new(old_index), EntityId::new(new_index));
+        }
+        remap
+    }
+
+    /// Appends the entities of type `E` in `other`, with their property values, after those in
+    /// `self`. Returns a map from each id in `other` to its id in `self`.
+    ///
+    /// Entities removed from `other` are appended as removed entities, so the new ids are the old
+    /// ones shifted by the number of entities of type `E` in `self`. Both contexts share the
+    /// properties registered in the binary, so their schemas always match. Edges between two
+    /// entities of type `E` are appended too, but edges to entities of other types are not,
+    /// since those entities aren't merged. Likewise, property values that refer to an entity of
+    /// type `E` are shifted like the ids. Entity-created handlers run for each appended live
+    /// entity.
+    ///
+    /// Panics, before changing `self`, if `other` holds values of a property of `E` that refer
+    /// to entities of another type.
+    pub fn merge<E: Entity>(&mut self, other: &Context) -> EntityKeyedMap<E, EntityId<E>> {
+        let offset = self.get_entity_count::<E>();
+        let other_count = other.get_entity_count::<E>();
+
+        for property_type_id in E::property_ids() {
+            let Some(property) = get_registered_property(*property_type_id) else {
+                continue;
+            };
+            let refers_elsewhere = property
+                .references
+                .is_some_and(|target| target != <E as Entity>::type_id());
+            if refers_elsewhere && (property.count_values)(&other.property_store) > 0 {
+                panic!(
+                    "cannot merge property {} of {}: its values refer to entities of another type, \
+                     which aren't merged",
+                    property.name,
+                    E::name()
+                );
+            }
+        }
+
+        for property_type_id in E::property_ids() {
+            if let Some(property) = get_registered_property(*property_type_id) {
+                (property.append_values)(&self.property_store, &other.property_store, offset);
+            }
+        }
+
+        let mut remap = EntityKeyedMap::with_capacity(other_count);
+        let mut appended = Vec::with_capacity(other_count);
+        for index in 0..other_count {
+            let entity_id = self.entity_store.new_entity_id::<E>();
+            if other.entity_store.is_live(EntityId::<E>::new(index)) {
+                appended.push(entity_id);
+            } else {
+                self.entity_store.mark_removed(entity_id);
+            }
+            remap.push(entity_id);
+        }
+        // Edges to entities of other types aren't merged, since those entities aren't.
+        let entity_type_id = <E as Entity>::type_id();
+        for (store_type_id, other_store) in &other.relationship_stores {
+            if other_store.end_type_ids() == (entity_type_id, entity_type_id) {
+                self.relationship_stores
+                    .entry(*store_type_id)
+                    .or_insert_with(|| other_store.new_empty())
+                    .append_edges(other_store.as_ref(), entity_type_id, offset);
+            }
+        }
+        self.rebuild_membership_indexes();
+        for entity_id in appended {
+            self.emit_entity_created(entity_id);
+        }
+        remap
+    }
+
+    /// Returns the number of entities of type `E` that have been created.
+    pub fn get_entity_count<E: Entity>(&self) -> usize {
+        self.entity_store.get_entity_count::<E>()
+    }
+
+    /// Calls `f` with each live entity of type `E`, in id order, and the context, without
+    /// collecting the ids first. `f` may set properties, which only needs `&Context`, while the
+    /// iteration is in progress; entities can't be added or removed until it ends.
+    pub fn for_each_entity<E: Entity>(&self, mut f: impl FnMut(EntityId<E>, &Context)) {
+        for entity_id in self.entity_store.live_entity_ids::<E>() {
+            f(entity_id, self);
+        }
+    }
+
+    /// Creates an [`EntityKeyedMap`] with a clone of `fill` for every entity of type `E` that
+    /// currently exists.
+    pub fn new_entity_keyed_map<E: Entity, T: Clone>(&self, fill: T) -> EntityKeyedMap<E, T> {
+        let mut map = EntityKeyedMap::new();
+        map.resize_to(self.get_entity_count::<E>(), fill);
+        map
+    }
+
+    /// Formats every property of `entity_id` as `name=value`, ordered by property name and
+    /// separated by commas, e.g. for debugging. Values are formatted with
+    /// [`Property::get_display`], derived values are computed, and unset explicit values are
+    /// shown as `<unset>`.
+    pub fn display_entity<E: Entity>(&self, entity_id: EntityId<E>) -> String {
+        let mut fields: Vec<(&'static str, String)> = E::property_ids()
+            .iter()
+            .filter_map(|property_type_id| get_registered_property(*property_type_id))
+            .map(|property| {
+                let value = (property.display_value)(self, entity_id.0)
+                    .unwrap_or_else(|| "<unset>".to_string());
+                (property.name, value)
+            })
+            .collect();
+        fields.sort_by_key(|(name, _)| *name);
+        fields
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Lists the properties registered for entity `E`, sorted by name.
+    pub fn properties_of<E: Entity>(&self) -> Vec<PropertyMeta> {
+        let mut properties: Vec<PropertyMeta> = E::property_ids()
+            .iter()
+            .filter_map(|&type_id| self.property_meta(type_id))
+            .collect();
+        properties.sort_by_key(|property| property.name);
+        properties
+    }
+
+    /// Looks up the property with the given `TypeId`, e.g. one from [`Entity::property_ids`].
+    /// Returns `None` if no property with that `TypeId` is registered.
+    pub fn property_meta(&self, type_id: TypeId) -> Option<PropertyMeta> {
+        let property = get_registered_property(type_id)?;
+        Some(PropertyMeta {
+            name: property.name,
+            type_id,
+            is_required: property.is_required,
+            kind: property.initialization_kind,
+        })
+    }
+
+    /// Lists every registered entity type, ordered by entity index.
+    pub fn entity_types(&self) -> Vec<EntityTypeMeta> {
+        get_registered_entities()
+            .into_iter()
+            .map(|entity| EntityTypeMeta {
+                name: entity.name,
+                type_id: entity.type_id,
+                count: self.entity_store.get_live_entity_count_by_index(entity.index),
+                property_count: get_entity_metadata(entity.type_id).property_ids.len(),
+            })
+            .collect()
+    }
+
+    /// Returns the registered property of `E` with the given name, if there is one.
+    pub(crate) fn find_property_by_name<E: Entity>(name: &str) -> Option<RegisteredProperty> {
+        E::property_ids()
+            .iter()
+            .filter_map(|property_type_id| get_registered_property(*property_type_id))
+            .find(|property| property.name == name)
+    }
+
+    /// Formats the value of the property of `E` named `name` for `entity_id` with
+    /// [`Property::get_display`]. Returns `None` if `E` has no property with that name or the
+    /// value is an unset explicit value.
+    pub fn get_property_by_name<E: Entity>(&self, entity_id: EntityId<E>, name: &str) -> Option<String> {
+        let property = Self::find_property_by_name::<E>(name)?;
+        (property.display_value)(self, entity_id.0)
+    }
+
+    /// Sets the property of `E` named `name` for `entity_id` to the value parsed from
+    /// `serialized_value`, e.g. for properties named in a configuration file. The value is parsed
+    /// with `serde` as a single CSV field: `42` for `struct Age(u8)`, `Infectious` for a unit
+    /// enum variant, and the empty string for `None`.
+    pub fn set_property_by_name<E: Entity>(
+        &self,
+        entity_id: EntityId<E>,
+        name: &str,
+        serialized_value: &str,
+    ) -> Result<(), SetPropertyByNameError> {
+        let property = Self::find_property_by_name::<E>(name).ok_or_else(|| {
+            SetPropertyByNameError::UnknownProperty {
+                entity_name: E::name(),
+                property_name: name.to_string(),
+            }
+        })?;
+        (property.set_value_from_str)(self, entity_id.0, serialized_value)?;
+        self.refresh_memberships(entity_id);
+        Ok(())
+    }
+
+    /// Returns the value of property `P` for `entity_id`.
+    ///
+    /// Panics if `P` is an explicit property that was never set for this entity, with a message
+    /// naming the property and the entity. Use [`Context::try_get_property`] to get an error
+    /// instead.
     pub fn get_property<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>) -> P {
-        // ToDo(RobertJacobsonCDC): An alternative to the following is to always assume
-        //       that `None` means "not set" for "explicit" properties, that is, assume
-        //       that `get` is infallible for properties with a default constant. We
-        //       take a more conservative approach here and check for internal errors.
+        self.try_get_property(entity_id).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Returns the value of property `P` for `entity_id`, or an error if `P` is an explicit
+    /// property that was never set for this entity. Constant, default function, and derived
+    /// properties always have a value. A default function property without a value gets one from
+    /// [`Property::default_fn`], which is stored for later reads if the entity is live.
+    pub fn try_get_property<E: Entity, P: Property<E>>(
+        &self,
+        entity_id: EntityId<E>,
+    ) -> Result<P, PropertyNotSetError> {
         match P::initialization_kind() {
-            PropertyInitializationKind::Explicit => {
-                let property_store = self.property_store.get::<E, P>();
-                // A user error can cause this unwrap to fail.
-                property_store.get(entity_id).expect("attempted to get a property value with \"explicit\" initialization that was not set")
+            PropertyInitializationKind::Derived => Ok(P::compute_derived(self, entity_id)),
+            PropertyInitializationKind::Explicit | PropertyInitializationKind::Constant => {
+                // Only explicit properties can be unset; constant ones fall back to the default.
+                let index = entity_id.0;
+                self.property_store.get::<E, P>().get(entity_id).ok_or(PropertyNotSetError {
+                    property_name: P::name(),
+                    entity_name: E::name(),
+                    index,
+                })
             }
+            PropertyInitializationKind::DefaultFn => {
+                if let Some(value) = self.property_store.get::<E, P>().get(entity_id) {
+                    return Ok(value);
+                }
+                // `default_fn` may read other properties, so don't hold on to the store. Only a
+                // live entity's default is stored, so that no value outlives its entity.
+                let value = P::default_fn(self, entity_id);
+                if self.entity_store.is_live(entity_id) {
+                    self.property_store.get::<E, P>().set(entity_id, value.clone());
+                }
+                Ok(value)
+            }
+        }
+    }
+
+    /// Returns the values of the properties in the tuple `PL` for `entity_id`, as a tuple of the
+    /// same type, e.g. `let (age, vaccinated): (Age, Vaccinated) = context.get_properties(person)`.
+    /// Each value is read as by [`Context::get_property`].
+    ///
+    /// Panics if any property in `PL` is an explicit property that was never set for this
+    /// entity. Use [`Context::try_get_properties`] to get an error instead.
+    pub fn get_properties<E: Entity, PL: PropertyList<E>>(&self, entity_id: EntityId<E>) -> PL {
+        self.try_get_properties(entity_id)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Returns the values of the properties in the tuple `PL` for `entity_id` like
+    /// [`Context::get_properties`], or the error for the first property that isn't set.
+    pub fn try_get_properties<E: Entity, PL: PropertyList<E>>(
+        &self,
+        entity_id: EntityId<E>,
+    ) -> Result<PL, PropertyNotSetError> {
+        PL::get_values(self, entity_id)
+    }
 
-            PropertyInitializationKind::Derived => {
-                P::compute_derived(self, entity_id)
+    /// Sets property `P` for many entities at once. Equivalent to calling
+    /// [`Context::set_property`] for each `(entity_id, value)` pair in order, but looks up the
+    /// property's storage only once. (If handlers are subscribed to changes of `P`, the updates
+    /// are applied one at a time so that each handler sees the previous value.)
+    ///
+    /// Panics under the same conditions as [`Context::set_property`], before any value is set
+    /// unless handlers are subscribed.
+    pub fn set_property_bulk<E: Entity, P: Property<E>>(
+        &self,
+        updates: impl IntoIterator<Item = (EntityId<E>, P)>,
+    ) {
+        let property_value_store = self.property_store.get::<E, P>();
+        if self.event_handlers.property_change::<E, P>().is_empty() {
+            let updates: Vec<(EntityId<E>, P)> = updates.into_iter().collect();
+            for (entity_id, value) in &updates {
+                InvalidPropertyValueError::check_live::<E, P>(self, *entity_id)
+                    .and_then(|()| InvalidPropertyValueError::check(self, value))
+                    .unwrap_or_else(|error| panic!("{error}"));
+            }
+            let entity_ids: Vec<EntityId<E>> = updates.iter().map(|(entity_id, _)| *entity_id).collect();
+            property_value_store.set_bulk(updates);
+            for entity_id in entity_ids {
+                self.value_changed::<E, P>(entity_id);
             }
+            return;
+        }
 
-            PropertyInitializationKind::Constant => {
-                let property_store = self.property_store.get::<E, P>();
-                // If this unwrap fails, it is an internal ixa error, not a user error.
-                property_store.get(entity_id).expect("getting a property value with \"constant\" initialization should never fail")
+        for (entity_id, value) in updates {
+            self.set_property(entity_id, value);
+        }
+    }
+
+    /// Replaces every value of property `P` with `f` applied to it, e.g. to age everyone by a year.
+    /// Applied to the stored values through [`Context::set_property_bulk`], so handlers are called
+    /// as for [`Context::set_property`].
+    ///
+    /// An explicit property that was never set stays unset. A constant or default function
+    /// property that was never set or read is materialized first, so every live entity's value,
+    /// including the defaults, is mapped and then stored.
+    ///
+    /// Panics if `P` is a derived property, which has no stored values to map.
+    pub fn map_property<E: Entity, P: Property<E>>(&self, f: impl Fn(P) -> P) {
+        if P::initialization_kind() == PropertyInitializationKind::Derived {
+            panic!("cannot map property {} because it has \"derived\" initialization", P::name());
+        }
+
+        let has_default = P::initialization_kind() != PropertyInitializationKind::Explicit;
+        let updates: Vec<(EntityId<E>, P)> = if has_default {
+            self.entity_store
+                .live_entity_ids::<E>()
+                .map(|entity_id| (entity_id, f(self.get_property::<E, P>(entity_id))))
+                .collect()
+        } else {
+            self.property_store
+                .get::<E, P>()
+                .iter_set()
+                .map(|(entity_id, value)| (entity_id, f(value)))
+                .collect()
+        };
+        self.set_property_bulk(updates);
+    }
+
+    /// Applies `f` to the value of property `P` for `entity_id` in place.
+    ///
+    /// Panics under the same conditions as [`Context::update_property`].
+    pub fn modify_property<E: Entity, P: Property<E>, F: FnOnce(&mut P)>(
+        &self,
+        entity_id: EntityId<E>,
+        f: F,
+    ) {
+        self.update_property(entity_id, |mut value: P| {
+            f(&mut value);
+            value
+        });
+    }
+
+    /// Replaces the value of property `P` for `entity_id` with `f` applied to the current value.
+    /// A constant or default function property that was never set starts from its default value.
+    ///
+    /// Panics if `P` is a derived property, which has no stored value to modify, if `entity_id`
+    /// doesn't exist or was removed, if `P` is an explicit property that was never set for this
+    /// entity, since there is no value to pass to `f`, or if [`Property::validate`] rejects the
+    /// new value. Use [`Context::get_or_init`] to
+    /// supply a value for an unset property.
+    pub fn update_property<E: Entity, P: Property<E>, F: FnOnce(P) -> P>(
+        &self,
+        entity_id: EntityId<E>,
+        f: F,
+    ) {
+        if P::initialization_kind() == PropertyInitializationKind::Derived {
+            panic!("cannot modify property {} because it has \"derived\" initialization", P::name());
+        }
+        InvalidPropertyValueError::check_live::<E, P>(self, entity_id)
+            .unwrap_or_else(|error| panic!("{error}"));
+
+        let value = self.try_get_property::<E, P>(entity_id).unwrap_or_else(|_| {
+            panic!("attempted to modify a property value with \"explicit\" initialization that was not set")
+        });
+        let new_value = f(value.clone());
+        InvalidPropertyValueError::check(self, &new_value).unwrap_or_else(|error| panic!("{error}"));
+        self.property_store.get::<E, P>().set(entity_id, new_value.clone());
+        self.value_changed::<E, P>(entity_id);
+        self.emit_property_change(entity_id, Some(value), new_value);
+    }
+
+    /// Returns the value of property `P` for `entity_id`. If `P` is an explicit property that was
+    /// never set for this entity, first sets it to the value returned by `init`. Constant, default
+    /// function, and derived properties always have a value, so `init` is never called for them.
+    pub fn get_or_init<E: Entity, P: Property<E>>(
+        &self,
+        entity_id: EntityId<E>,
+        init: impl FnOnce() -> P,
+    ) -> P {
+        if let Ok(value) = self.try_get_property(entity_id) {
+            return value;
+        }
+        let value = init();
+        self.set_property(entity_id, value.clone());
+        value
+    }
+
+    /// Returns `true` if property `P` has a value for `entity_id`, that is, if
+    /// [`Context::get_property`] would not panic. Constant, default function, and derived
+    /// properties are always set.
+    pub fn is_property_set<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>) -> bool {
+        match P::initialization_kind() {
+            PropertyInitializationKind::Explicit => {
+                self.property_store.get::<E, P>().is_set(entity_id)
             }
+            PropertyInitializationKind::Derived
+            | PropertyInitializationKind::Constant
+            | PropertyInitializationKind::DefaultFn => true,
         }
     }
 
+    /// Returns `true` if `entity_id` has a value of property `P` to read, for code that branches
+    /// on whether a value was ever assigned. This is another name for
+    /// [`Context::is_property_set`]: only an explicit property can lack a value, while constant,
+    /// default function, and derived properties always have one. To ask whether a value is
+    /// actually stored, ignoring defaults, use [`PropertyValueStore::is_set`].
+    ///
+    /// [`PropertyValueStore::is_set`]: crate::entity::property_value_store::PropertyValueStore::is_set
+    pub fn has_property<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>) -> bool {
+        self.is_property_set::<E, P>(entity_id)
+    }
+
+    /// Clears the value of the explicit property `P` for `entity_id`, so that it reads as never
+    /// having been set: [`Context::is_property_set`] returns `false` and
+    /// [`Context::get_property`] panics until it's set again. Does nothing if it wasn't set.
+    /// Handlers subscribed with [`Context::subscribe_property_change`] are not called, since
+    /// there's no new value to pass them.
+    ///
+    /// Panics if `P` isn't an explicit property: constant and default function properties always
+    /// have a value, and derived ones have no stored value.
+    pub fn unset_property<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>) {
+        if P::initialization_kind() != PropertyInitializationKind::Explicit {
+            panic!(
+                "cannot unset property {} because it doesn't have \"explicit\" initialization",
+                P::name()
+            );
+        }
+        self.property_store.get::<E, P>().clear(entity_id);
+        self.value_changed::<E, P>(entity_id);
+    }
+
+    /// Drops every stored value of property `P` and frees its storage, keeping the property
+    /// registered, so that every entity reads as though it was never set: an explicit property
+    /// is unset, and a default reads as its default again. As with
+    /// [`Context::unset_property`], no handlers are called.
+    pub fn clear_property_column<E: Entity, P: Property<E>>(&self) {
+        if let Some(property_value_store) = self.property_store.try_get::<E, P>() {
+            property_value_store.clear_all();
+        }
+        self.rebuild_membership_indexes();
+    }
+
+    /// Frees the capacity of every property's value storage beyond what its values need, for
+    /// when a population has finished growing, for example after it has been loaded.
+    pub fn shrink_all(&mut self) {
+        for property in get_registered_properties() {
+            (property.shrink_values)(&self.property_store);
+        }
+    }
+
+    /// Sets the value of property `P` for `entity_id`, then calls any handlers subscribed with
+    /// [`Context::subscribe_property_change`].
+    ///
+    /// Panics if `P` is a derived property, `entity_id` doesn't exist or was removed, or
+    /// [`Property::validate`] rejects the value. Use [`Context::try_set_property`] to get an
+    /// error instead.
     pub fn set_property<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>, property_value: P) {
+        self.try_set_property(entity_id, property_value)
+            .unwrap_or_else(|error| panic!("{error}"));
+    }
+
+    /// Sets the value of property `P` for `entity_id` like [`Context::set_property`], or returns
+    /// an error, leaving the value unchanged, if `P` is a derived property, `entity_id` doesn't
+    /// exist or was removed, or [`Property::validate`] rejects the value.
+    pub fn try_set_property<E: Entity, P: Property<E>>(
+        &self,
+        entity_id: EntityId<E>,
+        property_value: P,
+    ) -> Result<(), InvalidPropertyValueError> {
+        InvalidPropertyValueError::check_live::<E, P>(self, entity_id)?;
+        InvalidPropertyValueError::check(self, &property_value)?;
         let property_value_store = self.property_store.get::<E, P>();
-        property_value_store.set(entity_id, property_value);
+        if self.event_handlers.property_change::<E, P>().is_empty() {
+            property_value_store.set(entity_id, property_value);
+            self.value_changed::<E, P>(entity_id);
+            return Ok(());
+        }
+
+        let old_value = property_value_store.get(entity_id);
+        property_value_store.set(entity_id, property_value.clone());
+        self.value_changed::<E, P>(entity_id);
+        self.emit_property_change(entity_id, old_value, property_value);
+        Ok(())
+    }
+
+    /// Brings the membership index and the dirty set up to date after the value of property `P`
+    /// for `entity_id` was set or cleared.
+    fn value_changed<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>) {
+        self.refresh_membership::<E, P>(entity_id);
+        self.record_change::<E, P>(entity_id);
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use crate::{define_entity, define_property, impl_property};
+    use crate::{define_entity, define_property, impl_property, impl_property_with_options};
     use super::*;
 
     define_entity!(Person);
+    define_entity!(Household);
 
     define_property!(struct Age(u8), Person, is_required = true);
 
@@ -97,6 +930,34 @@ mod tests {
         default_const = Vaccinated(false)
     );
 
+    define_property!(struct Weight(u16), Person);
+
+    define_property!(struct Name(String), Person);
+
+    // A per-entity default, standing in for a draw from a distribution seeded by the entity id.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct Susceptibility(u8);
+    impl_property_with_options!(
+        Susceptibility,
+        Person,
+        default_fn = |context: &Context, entity_id: PersonId| {
+            let age: Age = context.get_property(entity_id);
+            Susceptibility((entity_id.0 as u8 * 37 + age.0) % 100)
+        }
+    );
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct IsAdult(bool);
+    impl_property_with_options!(
+        IsAdult,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            IsAdult(age.0 >= 18)
+        }
+    );
+
 
     #[test]
     fn add_an_entity() {
@@ -132,6 +993,63 @@ mod tests {
         println!("{:?}", person1);
     }
 
+    #[test]
+    #[should_panic(expected = "initialization list is missing required properties: Age")]
+    fn missing_required_property_is_named_in_panic() {
+        let mut context = Context::new();
+        context.add_entity((Vaccinated(true),));
+    }
+
+    #[test]
+    fn try_add_entity_with_duplicate_property() {
+        let mut context = Context::new();
+        let result = context.try_add_entity((Age(12), Vaccinated(true), Age(13)));
+        assert!(matches!(result, Err(AddEntityError::DuplicateProperty(_))));
+        assert_eq!(context.get_entity_count::<Person>(), 0);
+    }
+
+    #[test]
+    fn try_add_entity_without_required_properties() {
+        let mut context = Context::new();
+        let result = context.try_add_entity((Vaccinated(true),));
+        assert_eq!(result, Err(AddEntityError::MissingRequired(vec!["Age"])));
+        assert_eq!(context.get_entity_count::<Person>(), 0);
+    }
+
+    #[test]
+    fn try_add_entity_success() {
+        let mut context = Context::new();
+        let person = context.try_add_entity((Age(12),)).unwrap();
+        assert_eq!(context.get_property::<_, Age>(person), Age(12));
+    }
+
+    #[test]
+    fn add_entities_from_an_iterator() {
+        let mut context = Context::new();
+        let first = context.add_entity((Age(99),));
+        let people: Vec<PersonId> = context.add_entities(
+            (0..1000).map(|index| (Age((index % 90) as u8), Vaccinated(index % 2 == 0))),
+        );
+        assert!(context.property_store.get::<Person, Age>().capacity() >= 1001);
+
+        assert_eq!(people.len(), 1000);
+        for (index, person) in people.iter().enumerate() {
+            assert_eq!(*person, PersonId::new(first.0 + 1 + index));
+            assert_eq!(
+                context.get_properties::<_, (Age, Vaccinated)>(*person),
+                (Age((index % 90) as u8), Vaccinated(index % 2 == 0))
+            );
+        }
+        assert_eq!(context.get_entity_count::<Person>(), 1001);
+    }
+
+    #[test]
+    #[should_panic(expected = "initialization list is missing required properties")]
+    fn add_entities_checks_the_list_type_first() {
+        let mut context = Context::new();
+        context.add_entities(std::iter::empty::<(Weight,)>());
+    }
+
     #[test]
     fn get_and_set_property_explicit() {
         let mut context = Context::new();
@@ -178,4 +1096,719 @@ mod tests {
         assert_eq!(status, InfectionStatus::Susceptible);
     }
 
+    #[test]
+    fn try_get_property_explicit() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+
+        assert_eq!(
+            context.try_get_property::<_, Weight>(person),
+            Err(PropertyNotSetError {
+                property_name: "Weight",
+                entity_name: "Person",
+                index: 0,
+            })
+        );
+        context.set_property(person, Weight(70));
+        assert_eq!(context.try_get_property::<_, Weight>(person), Ok(Weight(70)));
+    }
+
+    #[test]
+    fn try_get_property_constant() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+
+        assert_eq!(context.try_get_property::<_, Vaccinated>(person), Ok(Vaccinated(false)));
+        context.set_property(person, Vaccinated(true));
+        assert_eq!(context.try_get_property::<_, Vaccinated>(person), Ok(Vaccinated(true)));
+    }
+
+    #[test]
+    fn try_get_property_derived() {
+        let mut context = Context::new();
+        let child = context.add_entity((Age(12),));
+        let adult = context.add_entity((Age(40),));
+
+        assert_eq!(context.try_get_property::<_, IsAdult>(child), Ok(IsAdult(false)));
+        assert_eq!(context.try_get_property::<_, IsAdult>(adult), Ok(IsAdult(true)));
+    }
+
+    #[test]
+    #[should_panic(expected = "attempted to get a property value with \"explicit\" initialization that was not set")]
+    fn get_unset_explicit_property() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+        let _: Weight = context.get_property(person);
+    }
+
+    #[test]
+    #[should_panic(expected = "that was not set: property Weight of Person 1")]
+    fn get_unset_explicit_property_names_the_entity() {
+        let mut context = Context::new();
+        let _ = context.add_entity((Age(25), Weight(70)));
+        let person = context.add_entity((Age(25),));
+        let _: Weight = context.get_property(person);
+    }
+
+    #[test]
+    fn get_properties_in_one_call() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(42), Vaccinated(true)));
+        let (age, vaccinated): (Age, Vaccinated) = context.get_properties(person);
+        assert_eq!((age, vaccinated), (Age(42), Vaccinated(true)));
+
+        // Each property is read according to its initialization kind.
+        assert_eq!(
+            context.get_properties::<_, (InfectionStatus, IsAdult, Age)>(person),
+            (InfectionStatus::Susceptible, IsAdult(true), Age(42))
+        );
+        assert_eq!(
+            context
+                .try_get_properties::<_, (Age, Weight)>(person)
+                .unwrap_err()
+                .property_name,
+            "Weight"
+        );
+    }
+
+    #[test]
+    fn is_property_set_explicit() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+
+        assert!(context.is_property_set::<_, Age>(person));
+        assert!(!context.is_property_set::<_, Weight>(person));
+        context.set_property(person, Weight(70));
+        assert!(context.is_property_set::<_, Weight>(person));
+    }
+
+    #[test]
+    fn is_property_set_constant_and_derived() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+
+        assert!(context.is_property_set::<_, Vaccinated>(person));
+        assert!(context.is_property_set::<_, IsAdult>(person));
+    }
+
+    #[test]
+    fn has_property_for_each_initialization_kind() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+
+        // Explicit
+        assert!(context.has_property::<_, Age>(person));
+        assert!(!context.has_property::<_, Weight>(person));
+        context.set_property(person, Weight(70));
+        assert!(context.has_property::<_, Weight>(person));
+        context.unset_property::<_, Weight>(person);
+        assert!(!context.has_property::<_, Weight>(person));
+
+        // Constant and default function, set or not
+        assert!(context.has_property::<_, Vaccinated>(person));
+        assert!(!context.property_store.get::<_, Vaccinated>().is_set(person));
+        // Setting a later entity's value doesn't store the default for this one.
+        let other = context.add_entity((Age(30),));
+        context.set_property(other, InfectionStatus::Infected);
+        assert!(context.has_property::<_, InfectionStatus>(person));
+        assert!(!context.property_store.get::<_, InfectionStatus>().is_set(person));
+        assert!(context.has_property::<_, Susceptibility>(person));
+
+        // Derived
+        assert!(context.has_property::<_, IsAdult>(person));
+    }
+
+    #[test]
+    fn for_each_entity_tallies_and_sets() {
+        let mut context = Context::new();
+        for age in [10, 25, 40, 70] {
+            context.add_entity((Age(age),));
+        }
+        let removed = context.add_entity((Age(99),));
+        assert!(context.remove_entity(removed));
+
+        let mut total_age = 0;
+        let mut visited = Vec::new();
+        context.for_each_entity::<Person>(|person, context| {
+            let age: Age = context.get_property(person);
+            total_age += u32::from(age.0);
+            visited.push(person);
+            // Setting a property from the callback is allowed.
+            if age.0 >= 18 {
+                context.set_property(person, Weight(70));
+            }
+        });
+        assert_eq!(total_age, 145);
+        assert_eq!(visited, (0..4).map(PersonId::new).collect::<Vec<_>>());
+        assert!(!context.is_property_set::<_, Weight>(PersonId::new(0)));
+        assert_eq!(context.get_property::<_, Weight>(PersonId::new(3)), Weight(70));
+    }
+
+    #[test]
+    fn unset_explicit_property() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25), Weight(70)));
+        let other = context.add_entity((Age(30), Weight(80)));
+
+        context.unset_property::<_, Weight>(person);
+        assert!(!context.is_property_set::<_, Weight>(person));
+        assert!(context.try_get_property::<_, Weight>(person).is_err());
+        assert_eq!(context.get_property::<_, Weight>(other), Weight(80));
+        // Unsetting twice, or an entity beyond the end of the storage, is harmless.
+        context.unset_property::<_, Weight>(person);
+        let newest = context.add_entity((Age(35),));
+        context.unset_property::<_, Weight>(newest);
+
+        context.set_property(person, Weight(60));
+        assert_eq!(context.get_property::<_, Weight>(person), Weight(60));
+    }
+
+    #[test]
+    fn clear_property_column() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..100)
+            .map(|age| context.add_entity((Age(age), Weight(50 + u16::from(age)), Vaccinated(true))))
+            .collect();
+        assert!(context.property_store.get::<Person, Weight>().heap_bytes() > 0);
+
+        context.clear_property_column::<Person, Weight>();
+        context.clear_property_column::<Person, Vaccinated>();
+        assert_eq!(context.property_store.get::<Person, Weight>().heap_bytes(), 0);
+        for person in &people {
+            assert!(!context.is_property_set::<_, Weight>(*person));
+            assert!(context.try_get_property::<_, Weight>(*person).is_err());
+            assert_eq!(context.get_property::<_, Vaccinated>(*person), Vaccinated(false));
+        }
+        assert_eq!(context.get_property::<_, Age>(people[42]), Age(42));
+
+        // The column can be filled again.
+        context.set_property(people[7], Weight(65));
+        assert_eq!(context.get_property::<_, Weight>(people[7]), Weight(65));
+        assert!(!context.is_property_set::<_, Weight>(people[6]));
+    }
+
+    #[test]
+    #[should_panic(expected = "that was not set: property Weight of Person 0")]
+    fn get_property_after_unset() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25), Weight(70)));
+        context.unset_property::<_, Weight>(person);
+        let _: Weight = context.get_property(person);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot unset property Vaccinated because it doesn't have \"explicit\" initialization")]
+    fn unset_constant_property() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25), Vaccinated(true)));
+        context.unset_property::<_, Vaccinated>(person);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value for property IsAdult: the property is derived, so its values can't be set")]
+    fn set_derived_property() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(12),));
+        context.set_property(person, IsAdult(true));
+    }
+
+    #[test]
+    fn derived_property_values_are_rejected() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(12),));
+        let error = context.try_set_property(person, IsAdult(true)).unwrap_err();
+        assert_eq!(error.property_name, "IsAdult");
+        assert_eq!(context.get_property::<_, IsAdult>(person), IsAdult(false));
+        assert!(!context.property_store.get::<Person, IsAdult>().is_set(person));
+
+        assert_eq!(
+            context.try_add_entity((Age(40), IsAdult(false))),
+            Err(AddEntityError::InvalidValue(error))
+        );
+        assert_eq!(context.get_entity_count::<Person>(), 1);
+    }
+
+    #[test]
+    fn values_of_removed_and_nonexistent_entities_are_rejected() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(12),));
+        let removed = context.add_entity((Age(30),));
+        assert!(context.remove_entity(removed));
+        let future = PersonId::new(5);
+
+        for entity_id in [removed, future] {
+            let error = context.try_set_property(entity_id, Weight(60)).unwrap_err();
+            assert_eq!(error.property_name, "Weight");
+            assert_eq!(error.message, format!("{entity_id} does not exist"));
+        }
+        let weights = context.property_store.get::<Person, Weight>();
+        assert!(!weights.is_set(removed) && !weights.is_set(future));
+
+        context.set_property(person, Weight(40));
+        assert_eq!(context.get_property::<_, Weight>(person), Weight(40));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value for property Weight: Person#1 does not exist")]
+    fn set_property_bulk_on_removed_entity() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(12),));
+        let removed = context.add_entity((Age(30),));
+        context.remove_entity(removed);
+        context.set_property_bulk([(person, Weight(40)), (removed, Weight(50))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value for property Vaccinated: Person#1 does not exist")]
+    fn update_property_on_removed_entity() {
+        let mut context = Context::new();
+        context.add_entity((Age(12),));
+        let removed = context.add_entity((Age(30),));
+        context.remove_entity(removed);
+        context.update_property(removed, |vaccinated: Vaccinated| Vaccinated(!vaccinated.0));
+    }
+
+    #[test]
+    fn reset_context() {
+        let mut context = Context::default();
+        context.add_entity((Age(25), Vaccinated(true)));
+        context.add_entity((Age(30), Weight(70)));
+        assert_eq!(context.get_entity_count::<Person>(), 2);
+
+        context.reset();
+        assert_eq!(context.get_entity_count::<Person>(), 0);
+
+        let new_person = context.add_entity((Age(40),));
+        assert_eq!(new_person, EntityId::new(0));
+        assert_eq!(context.get_property::<_, Age>(new_person), Age(40));
+        assert_eq!(context.get_property::<_, Vaccinated>(new_person), Vaccinated(false));
+        assert!(!context.is_property_set::<_, Weight>(new_person));
+    }
+
+    #[test]
+    fn modify_property_in_place() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+
+        let mut calls = 0;
+        context.modify_property(person, |age: &mut Age| {
+            age.0 += 1;
+            calls += 1;
+        });
+        assert_eq!(calls, 1);
+        assert_eq!(context.get_property::<_, Age>(person), Age(26));
+
+        context.modify_property(person, |vaccinated: &mut Vaccinated| vaccinated.0 = !vaccinated.0);
+        assert_eq!(context.get_property::<_, Vaccinated>(person), Vaccinated(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot modify property IsAdult")]
+    fn modify_derived_property() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+        context.modify_property(person, |is_adult: &mut IsAdult| is_adult.0 = false);
+    }
+
+    #[test]
+    fn update_property_from_stored_and_default_values() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+
+        context.update_property(person, |age: Age| Age(age.0 * 2));
+        assert_eq!(context.get_property::<_, Age>(person), Age(50));
+
+        // `Vaccinated` was never set, so `f` receives the default.
+        context.update_property(person, |vaccinated: Vaccinated| {
+            assert_eq!(vaccinated, Vaccinated(false));
+            Vaccinated(true)
+        });
+        assert_eq!(context.get_property::<_, Vaccinated>(person), Vaccinated(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "attempted to modify a property value with \"explicit\" initialization that was not set")]
+    fn update_unset_explicit_property() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+        context.update_property(person, |weight: Weight| Weight(weight.0 + 1));
+    }
+
+    #[test]
+    fn set_property_bulk_matches_single_sets() {
+        let mut bulk_context = Context::new();
+        let mut single_context = Context::new();
+        let mut people = Vec::new();
+        for age in 0..100 {
+            people.push(bulk_context.add_entity((Age(age),)));
+            single_context.add_entity((Age(age),));
+        }
+
+        // Leave some entities unset and set one entity twice; the last write wins.
+        let updates: Vec<(EntityId<Person>, Weight)> = people
+            .iter()
+            .filter(|person| person.0 % 3 != 0)
+            .map(|person| (*person, Weight(person.0 as u16)))
+            .chain([(people[50], Weight(500))])
+            .collect();
+
+        bulk_context.set_property_bulk(updates.clone());
+        for (person, weight) in updates {
+            single_context.set_property(person, weight);
+        }
+
+        for person in people {
+            assert_eq!(
+                bulk_context.try_get_property::<_, Weight>(person),
+                single_context.try_get_property::<_, Weight>(person)
+            );
+        }
+        assert_eq!(bulk_context.get_property::<_, Weight>(EntityId::new(50)), Weight(500));
+    }
+
+    #[test]
+    fn map_property_over_every_value() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..50).map(|age| context.add_entity((Age(age),))).collect();
+        context.set_property(people[1], Weight(60));
+        context.set_property(people[2], Vaccinated(true));
+
+        context.map_property::<Person, Age>(|age| Age(age.0 + 1));
+        context.map_property::<Person, Weight>(|weight| Weight(weight.0 * 2));
+        context.map_property::<Person, Vaccinated>(|vaccinated| Vaccinated(!vaccinated.0));
+        for (index, person) in people.iter().enumerate() {
+            assert_eq!(context.get_property::<_, Age>(*person), Age(index as u8 + 1));
+            // Constant defaults are mapped too.
+            assert_eq!(context.get_property::<_, Vaccinated>(*person), Vaccinated(index != 2));
+        }
+        assert_eq!(context.get_property::<_, Weight>(people[1]), Weight(120));
+        assert!(!context.is_property_set::<_, Weight>(people[0]));
+    }
+
+    #[test]
+    fn map_property_maps_default_fn_values_that_were_never_read() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..6).map(|age| context.add_entity((Age(age),))).collect();
+        let expected: Vec<Susceptibility> = people
+            .iter()
+            .map(|person| Susceptibility(Susceptibility::default_fn(&context, *person).0 / 2))
+            .collect();
+        // Only one value was read, and so stored, before mapping.
+        context.get_property::<_, Susceptibility>(people[3]);
+
+        context.map_property::<Person, Susceptibility>(|value| Susceptibility(value.0 / 2));
+        for (person, expected) in people.iter().zip(expected) {
+            assert!(context.is_property_set::<_, Susceptibility>(*person));
+            assert_eq!(context.get_property::<_, Susceptibility>(*person), expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot map property IsAdult")]
+    fn map_derived_property() {
+        let mut context = Context::new();
+        context.add_entity((Age(25),));
+        context.map_property::<Person, IsAdult>(|is_adult| is_adult);
+    }
+
+    #[test]
+    fn get_or_init_miss_then_hit() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25),));
+
+        let mut calls = 0;
+        let weight = context.get_or_init(person, || {
+            calls += 1;
+            Weight(70)
+        });
+        assert_eq!(weight, Weight(70));
+        assert_eq!(calls, 1);
+        assert_eq!(context.get_property::<_, Weight>(person), Weight(70));
+
+        let weight = context.get_or_init(person, || -> Weight { panic!("init should not run") });
+        assert_eq!(weight, Weight(70));
+    }
+
+    #[test]
+    fn get_or_init_set_value() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25), Weight(80)));
+
+        let weight = context.get_or_init(person, || -> Weight { panic!("init should not run") });
+        assert_eq!(weight, Weight(80));
+    }
+
+    #[test]
+    fn non_copy_property_round_trip() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(25), Name("Alice".to_string())));
+        assert_eq!(context.get_property::<_, Name>(person), Name("Alice".to_string()));
+
+        context.modify_property(person, |name: &mut Name| name.0.push_str(" Smith"));
+        assert_eq!(context.get_property::<_, Name>(person), Name("Alice Smith".to_string()));
+
+        let other = context.add_entity((Age(30),));
+        assert!(context.try_get_property::<_, Name>(other).is_err());
+        context.set_property(other, Name("Bob".to_string()));
+        assert_eq!(context.get_property::<_, Name>(other), Name("Bob".to_string()));
+    }
+
+    #[test]
+    fn default_fn_property_is_computed_once_per_entity() {
+        let mut context = Context::new();
+        let first = context.add_entity((Age(20),));
+        let second = context.add_entity((Age(20),));
+        assert_eq!(
+            Susceptibility::initialization_kind(),
+            PropertyInitializationKind::DefaultFn
+        );
+        assert!(!context.property_store.get::<Person, Susceptibility>().is_set(first));
+
+        let first_value: Susceptibility = context.get_property(first);
+        let second_value: Susceptibility = context.get_property(second);
+        assert_eq!(first_value, Susceptibility(20));
+        assert_eq!(second_value, Susceptibility(57));
+
+        // The value is stored, so changing what the default depends on doesn't change it.
+        assert!(context.property_store.get::<Person, Susceptibility>().is_set(first));
+        context.set_property(first, Age(50));
+        assert_eq!(context.get_property::<_, Susceptibility>(first), first_value);
+
+        // An explicitly set value takes precedence over the default.
+        let third = context.add_entity((Age(20), Susceptibility(1)));
+        assert_eq!(context.get_property::<_, Susceptibility>(third), Susceptibility(1));
+    }
+
+    #[test]
+    fn display_entity_shows_every_property() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(12), Vaccinated(true), Susceptibility(5)));
+        assert_eq!(
+            context.display_entity(person),
+            "Age=Age(12), InfectionStatus=Susceptible, IsAdult=IsAdult(false), Name=<unset>, \
+             Susceptibility=Susceptibility(5), Vaccinated=Vaccinated(true), Weight=<unset>"
+        );
+    }
+
+    #[test]
+    fn properties_of_lists_registered_properties() {
+        let context = Context::new();
+        let properties = context.properties_of::<Person>();
+        let names: Vec<&str> = properties.iter().map(|property| property.name).collect();
+        assert_eq!(
+            names,
+            [
+                "Age",
+                "InfectionStatus",
+                "IsAdult",
+                "Name",
+                "Susceptibility",
+                "Vaccinated",
+                "Weight"
+            ]
+        );
+
+        let age = &properties[0];
+        assert_eq!(age.type_id, TypeId::of::<Age>());
+        assert!(age.is_required);
+        assert_eq!(age.kind, PropertyInitializationKind::Explicit);
+        let infection_status = &properties[1];
+        assert!(!infection_status.is_required);
+        assert_eq!(infection_status.kind, PropertyInitializationKind::Constant);
+        assert_eq!(properties[2].kind, PropertyInitializationKind::Derived);
+    }
+
+    #[test]
+    fn property_meta_by_type_id() {
+        let context = Context::new();
+        let vaccinated = context.property_meta(TypeId::of::<Vaccinated>()).unwrap();
+        assert_eq!(vaccinated.name, "Vaccinated");
+        assert_eq!(vaccinated.type_id, TypeId::of::<Vaccinated>());
+        assert!(!vaccinated.is_required);
+        assert_eq!(vaccinated.kind, PropertyInitializationKind::Constant);
+
+        assert_eq!(context.property_meta(TypeId::of::<String>()), None);
+    }
+
+    #[test]
+    fn get_and_set_property_by_name() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(30),));
+
+        context.set_property_by_name(person, "Age", "42").unwrap();
+        assert_eq!(context.get_property::<Person, Age>(person), Age(42));
+        assert_eq!(context.get_property_by_name(person, "Age").as_deref(), Some("Age(42)"));
+
+        context
+            .set_property_by_name(person, "InfectionStatus", "Recovered")
+            .unwrap();
+        assert_eq!(
+            context.get_property::<Person, InfectionStatus>(person),
+            InfectionStatus::Recovered
+        );
+        assert_eq!(context.get_property_by_name(person, "Weight"), None);
+        assert_eq!(context.get_property_by_name(person, "Height"), None);
+
+        assert_eq!(
+            context.set_property_by_name(person, "Height", "180"),
+            Err(SetPropertyByNameError::UnknownProperty {
+                entity_name: "Person",
+                property_name: "Height".to_string(),
+            })
+        );
+        let error = context.set_property_by_name(person, "Age", "old").unwrap_err();
+        assert!(matches!(
+            error,
+            SetPropertyByNameError::Parse {
+                property_name: "Age",
+                ..
+            }
+        ));
+        assert_eq!(context.get_property::<Person, Age>(person), Age(42));
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        let mut baseline = Context::new();
+        let people: Vec<PersonId> = (0..3)
+            .map(|age| baseline.add_entity((Age(age), Vaccinated(age == 1))))
+            .collect();
+        baseline.set_property(people[2], Name("Ada".to_string()));
+
+        let mut fork = baseline.clone();
+        fork.set_property(people[0], Age(50));
+        fork.set_property(people[1], Vaccinated(false));
+        fork.set_property(people[2], Name("Grace".to_string()));
+        fork.set_property(people[2], Weight(60));
+        fork.add_entity((Age(9),));
+
+        assert_eq!(baseline.get_entity_count::<Person>(), 3);
+        assert_eq!(baseline.get_property::<Person, Age>(people[0]), Age(0));
+        assert_eq!(baseline.get_property::<Person, Vaccinated>(people[1]), Vaccinated(true));
+        assert_eq!(baseline.get_property::<Person, Name>(people[2]), Name("Ada".to_string()));
+        assert!(baseline.try_get_property::<Person, Weight>(people[2]).is_err());
+
+        assert_eq!(fork.get_entity_count::<Person>(), 4);
+        assert_eq!(fork.get_property::<Person, Age>(people[0]), Age(50));
+        assert_eq!(fork.get_property::<Person, Age>(people[1]), Age(1));
+        assert_eq!(fork.get_property::<Person, Name>(people[2]), Name("Grace".to_string()));
+    }
+
+    #[test]
+    fn merge_appends_entities() {
+        let mut context = Context::new();
+        let ada = context.add_entity((Age(36), Name("Ada".to_string())));
+        context.add_entity((Age(40), Vaccinated(true)));
+
+        let mut other = Context::new();
+        let grace = other.add_entity((Age(85), Name("Grace".to_string()), Weight(55)));
+        let removed = other.add_entity((Age(1),));
+        other.remove_entity(removed);
+        let alan = other.add_entity((Age(41), Vaccinated(true)));
+
+        let remap = context.merge::<Person>(&other);
+        assert_eq!(remap.as_slice(), [PersonId::new(2), PersonId::new(3), PersonId::new(4)]);
+        assert_eq!(context.get_entity_count::<Person>(), 5);
+        assert_eq!(context.entity_store.get_live_entity_count::<Person>(), 4);
+
+        let grace = *remap.get(grace).unwrap();
+        assert_eq!(context.get_property::<Person, Name>(grace), Name("Grace".to_string()));
+        assert_eq!(context.get_property::<Person, Weight>(grace), Weight(55));
+        assert_eq!(context.get_property::<Person, Vaccinated>(grace), Vaccinated(false));
+        let alan = *remap.get(alan).unwrap();
+        assert_eq!(context.get_property::<Person, Age>(alan), Age(41));
+        assert_eq!(context.get_property::<Person, Vaccinated>(alan), Vaccinated(true));
+        assert_eq!(context.get_property::<Person, Name>(ada), Name("Ada".to_string()));
+    }
+
+    #[test]
+    fn entity_types_lists_registered_entities() {
+        let mut context = Context::new();
+        context.add_entity((Age(30),));
+        context.add_entity((Age(40),));
+        context.add_entity::<Household, _>(());
+
+        let entity_types = context.entity_types();
+        let person = entity_types
+            .iter()
+            .find(|entity| entity.type_id == TypeId::of::<Person>())
+            .unwrap();
+        assert_eq!(person.name, "Person");
+        assert_eq!(person.count, 2);
+        assert_eq!(person.property_count, 7);
+        let household = entity_types
+            .iter()
+            .find(|entity| entity.type_id == TypeId::of::<Household>())
+            .unwrap();
+        assert_eq!(household.name, "Household");
+        assert_eq!(household.count, 1);
+        assert_eq!(household.property_count, 0);
+    }
+
+    #[test]
+    fn with_capacity_preallocates_property_stores() {
+        let mut context = Context::with_capacity(10_000);
+        let person = context.add_entity((Age(30), Vaccinated(true)));
+        context.set_property(person, Weight(70));
+
+        let age_capacity = context.property_store.get::<Person, Age>().capacity();
+        assert!(age_capacity >= 10_000);
+        assert!(context.property_store.get::<Person, Weight>().capacity() >= 10_000);
+        assert!(context.property_store.get::<Person, Vaccinated>().capacity() >= 10_000);
+
+        // Filling the population doesn't reallocate.
+        for _ in 1..10_000 {
+            context.add_entity((Age(30),));
+        }
+        assert_eq!(context.property_store.get::<Person, Age>().capacity(), age_capacity);
+    }
+
+    #[test]
+    fn shrink_all_drops_capacity_to_length() {
+        let mut context = Context::with_capacity(10_000);
+        for age in 0..100 {
+            context.add_entity((Age(age),));
+        }
+        context.set_property(PersonId::new(3), Weight(70));
+        assert!(context.property_store.get::<Person, Age>().capacity() >= 10_000);
+
+        context.shrink_all();
+        assert_eq!(context.property_store.get::<Person, Age>().capacity(), 100);
+        assert_eq!(context.property_store.get::<Person, Weight>().capacity(), 4);
+        assert_eq!(context.get_property::<_, Age>(PersonId::new(99)), Age(99));
+        assert_eq!(context.get_property::<_, Weight>(PersonId::new(3)), Weight(70));
+    }
+
+    #[test]
+    fn compact_after_removing_every_other_entity() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..10)
+            .map(|index| context.add_entity((Age(index), Vaccinated(index % 4 == 1))))
+            .collect();
+        context.set_property(people[5], Weight(55));
+        for person in people.iter().step_by(2) {
+            assert!(context.remove_entity(*person));
+        }
+
+        let remap = context.compact::<Person>();
+        assert_eq!(context.get_entity_count::<Person>(), 5);
+        assert_eq!(remap.len(), 5);
+        assert_eq!(remap.get(people[0]), None);
+        for (new_index, old_id) in people.iter().skip(1).step_by(2).enumerate() {
+            let new_id = *remap.get(*old_id).unwrap();
+            assert_eq!(new_id, PersonId::new(new_index));
+            assert_eq!(context.get_property::<_, Age>(new_id), Age(old_id.0 as u8));
+            assert_eq!(
+                context.get_property::<_, Vaccinated>(new_id),
+                Vaccinated(old_id.0 % 4 == 1)
+            );
+        }
+        assert_eq!(context.try_get_property::<_, Weight>(PersonId::new(2)), Ok(Weight(55)));
+        assert!(context.try_get_property::<_, Weight>(PersonId::new(1)).is_err());
+
+        // New entities continue after the compacted ones.
+        let person = context.add_entity((Age(99),));
+        assert_eq!(person, PersonId::new(5));
+        assert!(context.try_get_property::<_, Weight>(person).is_err());
+    }
 }
@@ -0,0 +1,286 @@
+/*!
+
+Comparing the populations of two contexts, e.g. to find where a run diverged from a reference
+run.
+
+```rust,ignore
+for difference in context.diff::<Person>(&reference)? {
+    println!("{difference}");
+}
+```
+
+Entities are matched by id. A property value differs if it is set in only one of the contexts or
+if the two values serialize differently, so values are compared exactly even when their display
+forms are rounded. Derived properties are skipped, since their values follow from the others.
+
+Comparing two contexts doesn't change either: a default function value that was never read is
+computed to be compared, but not stored, so the result doesn't depend on which values were read
+before.
+
+*/
+
+use std::fmt::{Display, Formatter};
+
+use serde_json::Value;
+
+use crate::{
+    Context,
+    entity::{
+        Entity, EntityId,
+        property::{Property, PropertyInitializationKind},
+        property_store::{RegisteredProperty, get_registered_property},
+    },
+};
+
+/// One difference between the populations of two contexts, reported by [`Context::diff`]. The
+/// context `diff` is called on is the left one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PropertyDiff<E: Entity> {
+    /// The entity is live in the left context only.
+    LeftOnly(EntityId<E>),
+    /// The entity is live in the right context only.
+    RightOnly(EntityId<E>),
+    /// The entity's values of a property differ. Each value is formatted with `get_display`, or
+    /// `None` if it isn't set.
+    Value {
+        entity_id: EntityId<E>,
+        property_name: &'static str,
+        left: Option<String>,
+        right: Option<String>,
+    },
+}
+
+impl<E: Entity> Display for PropertyDiff<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropertyDiff::LeftOnly(entity_id) => write!(f, "{entity_id} is only in the left context"),
+            PropertyDiff::RightOnly(entity_id) => write!(f, "{entity_id} is only in the right context"),
+            PropertyDiff::Value {
+                entity_id,
+                property_name,
+                left,
+                right,
+            } => write!(
+                f,
+                "{property_name} of {entity_id}: {} != {}",
+                left.as_deref().unwrap_or("<unset>"),
+                right.as_deref().unwrap_or("<unset>")
+            ),
+        }
+    }
+}
+
+impl Context {
+    /// Compares the entities of type `E` in `self` with those in `other`, returning every
+    /// difference in id order and, for each entity, in the order of the entity's properties. An
+    /// entity live in only one of the contexts is reported once, without its values. See the
+    /// [module documentation](self).
+    ///
+    /// Returns an error if a value can't be serialized to be compared.
+    pub fn diff<E: Entity>(&self, other: &Context) -> serde_json::Result<Vec<PropertyDiff<E>>> {
+        let properties: Vec<RegisteredProperty> = E::property_ids()
+            .iter()
+            .filter_map(|property_type_id| get_registered_property(*property_type_id))
+            .filter(|property| property.initialization_kind != PropertyInitializationKind::Derived)
+            .collect();
+        let entity_count = self.get_entity_count::<E>().max(other.get_entity_count::<E>());
+
+        let mut differences = Vec::new();
+        for index in 0..entity_count {
+            let entity_id = EntityId::<E>::new(index);
+            match (self.entity_store.is_live(entity_id), other.entity_store.is_live(entity_id)) {
+                (true, true) => {}
+                (true, false) => {
+                    differences.push(PropertyDiff::LeftOnly(entity_id));
+                    continue;
+                }
+                (false, true) => {
+                    differences.push(PropertyDiff::RightOnly(entity_id));
+                    continue;
+                }
+                (false, false) => continue,
+            }
+
+            for property in &properties {
+                let left = (property.peek_value)(self, index).transpose()?;
+                let right = (property.peek_value)(other, index).transpose()?;
+                if left.as_ref().map(|(json, _)| json) != right.as_ref().map(|(json, _)| json) {
+                    differences.push(PropertyDiff::Value {
+                        entity_id,
+                        property_name: property.name,
+                        left: left.map(|(_, display)| display),
+                        right: right.map(|(_, display)| display),
+                    });
+                }
+            }
+        }
+        Ok(differences)
+    }
+}
+
+/// A property value, serialized as JSON to be compared and formatted with `get_display` to be
+/// reported.
+pub(crate) type PeekedValue = (Value, String);
+
+/// Serializes the value of property `P` that the live entity with the given index reads as,
+/// paired with its `get_display` form, or returns `None` if it isn't set. Unlike reading it with
+/// `try_get_property`, this stores nothing: a default function value that was never read is
+/// computed but not stored, and the store isn't instantiated. Registered as a hook for each
+/// property.
+pub(crate) fn peek_property_value<E: Entity, P: Property<E>>(
+    context: &Context,
+    index: usize,
+) -> Option<serde_json::Result<PeekedValue>> {
+    let entity_id = EntityId::new(index);
+    let stored = context
+        .property_store
+        .try_get::<E, P>()
+        .and_then(|property_value_store| property_value_store.get(entity_id));
+    let value = match (stored, P::initialization_kind()) {
+        (Some(value), _) => value,
+        (None, PropertyInitializationKind::Constant) => P::default_const(),
+        (None, PropertyInitializationKind::DefaultFn) => P::default_fn(context, entity_id),
+        (None, _) => return None,
+    };
+    Some(serde_json::to_value(&value).map(|json| (json, value.get_display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{define_entity, define_property, impl_property_with_options};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+    define_property!(struct Weight(u16), Person);
+
+    // A per-entity default, computed the first time the value is read.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct Susceptibility(u8);
+    impl_property_with_options!(
+        Susceptibility,
+        Person,
+        default_fn = |_context: &Context, entity_id: PersonId| Susceptibility(entity_id.0 as u8 % 10)
+    );
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, crate::serde::Deserialize)]
+    struct Opaque(u8);
+    impl crate::serde::Serialize for Opaque {
+        fn serialize<S: crate::serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(crate::serde::ser::Error::custom("opaque values can't be serialized"))
+        }
+    }
+    impl_property_with_options!(Opaque, Person);
+
+    fn population() -> Context {
+        let mut context = Context::new();
+        for age in 0..20 {
+            let person = context.add_entity((Age(age),));
+            if age % 2 == 0 {
+                context.set_property(person, Weight(u16::from(age) * 3));
+            }
+        }
+        context
+    }
+
+    #[test]
+    fn diff_against_clone_with_one_change() {
+        let context = population();
+        let changed = context.clone();
+        assert!(context.diff::<Person>(&changed).unwrap().is_empty());
+
+        changed.set_property(PersonId::new(7), InfectionStatus::Infected);
+        let differences = context.diff::<Person>(&changed).unwrap();
+        assert_eq!(
+            differences,
+            [PropertyDiff::Value {
+                entity_id: PersonId::new(7),
+                property_name: "InfectionStatus",
+                left: Some("Susceptible".to_string()),
+                right: Some("Infected".to_string()),
+            }]
+        );
+        assert_eq!(
+            differences[0].to_string(),
+            "InfectionStatus of Person#7: Susceptible != Infected"
+        );
+    }
+
+    #[test]
+    fn diff_reports_unset_values_and_extra_entities() {
+        let mut context = population();
+        let mut other = context.clone();
+        other.unset_property::<_, Weight>(PersonId::new(4));
+        assert!(other.remove_entity(PersonId::new(5)));
+        let extra = other.add_entity((Age(50),));
+        context.set_property(PersonId::new(9), Age(90));
+
+        assert_eq!(
+            context.diff::<Person>(&other).unwrap(),
+            [
+                PropertyDiff::Value {
+                    entity_id: PersonId::new(4),
+                    property_name: "Weight",
+                    left: Some("Weight(12)".to_string()),
+                    right: None,
+                },
+                PropertyDiff::LeftOnly(PersonId::new(5)),
+                PropertyDiff::Value {
+                    entity_id: PersonId::new(9),
+                    property_name: "Age",
+                    left: Some("Age(90)".to_string()),
+                    right: Some("Age(9)".to_string()),
+                },
+                PropertyDiff::RightOnly(extra),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_does_not_depend_on_which_defaults_were_read() {
+        let context = population();
+        let other = context.clone();
+        let _: Susceptibility = context.get_property(PersonId::new(3));
+
+        // Both contexts read the same value, so there is no difference.
+        assert!(context.diff::<Person>(&other).unwrap().is_empty());
+        // Neither context stored a default while being compared.
+        let stored = |context: &Context, index| {
+            context.property_store.get::<Person, Susceptibility>().is_set(PersonId::new(index))
+        };
+        assert!(stored(&context, 3) && !stored(&context, 4));
+        assert!(!stored(&other, 3));
+
+        other.set_property(PersonId::new(3), Susceptibility(8));
+        assert_eq!(
+            other.diff::<Person>(&context).unwrap(),
+            [PropertyDiff::Value {
+                entity_id: PersonId::new(3),
+                property_name: "Susceptibility",
+                left: Some("Susceptibility(8)".to_string()),
+                right: Some("Susceptibility(3)".to_string()),
+            }]
+        );
+        // Comparing the values no one read computed them on both sides without storing them.
+        assert!(!stored(&context, 4) && !stored(&other, 4));
+    }
+
+    #[test]
+    fn diff_reports_serialization_errors() {
+        let context = population();
+        let other = context.clone();
+        context.set_property(PersonId::new(2), Opaque(1));
+        let error = context.diff::<Person>(&other).unwrap_err();
+        assert_eq!(error.to_string(), "opaque values can't be serialized");
+    }
+}
@@ -8,35 +8,83 @@ Right now an `Entity` type is just a zero-sized marker type. The static data ass
 
 use std::{
     any::{Any, TypeId},
+    cmp::Ordering,
+    hash::{Hash, Hasher},
     marker::PhantomData,
 };
-use std::fmt::{Debug, Formatter};
-use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display, Formatter};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use super::entity_store::get_entity_metadata_static;
+use super::entity_store::get_entity_metadata;
 
 /// A type that can be named and used (copied, cloned) but not created outside of this crate.
 /// In the `define_entity!` macro we define the alias `pub type MyEntityId = EntityId<MyEntity>`.
-#[derive(Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// An id serializes as its bare index. A deserialized id refers to whichever entity has that
+/// index in the context it's used with, so it is only meaningful against a context holding the
+/// same population as the one it was taken from (e.g. one restored from a snapshot).
 pub struct EntityId<E: Entity>(pub(crate) usize, PhantomData<E>);
 
-// The derive version of the `Clone` implementation introduces unnecessary trait bounds on `E: Entity`.
+// The derive versions of `Clone` and `Copy` introduce unnecessary trait bounds on `E: Entity`, so
+// that the id of an entity with fields, which isn't `Copy`, wouldn't be `Copy` either.
 impl<E: Entity> Clone for EntityId<E> {
     #[inline]
     fn clone(&self) -> Self {
-        Self(
-            self.0,
-            PhantomData,
-        )
+        *self
     }
 }
 
+impl<E: Entity> Copy for EntityId<E> {}
+
+// Likewise, comparisons and hashing depend only on the index, with no bounds on `E`.
+impl<E: Entity> PartialEq for EntityId<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<E: Entity> Eq for EntityId<E> {}
+
+impl<E: Entity> PartialOrd for EntityId<E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<E: Entity> Ord for EntityId<E> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<E: Entity> Hash for EntityId<E> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<E: Entity> Serialize for EntityId<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, E: Entity> Deserialize<'de> for EntityId<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        usize::deserialize(deserializer).map(Self::new)
+    }
+}
+
+// Both `Debug` and `Display` print the entity name and the index, e.g. `Person#3`.
 impl<E: Entity> Debug for EntityId<E> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let name = format!("{}Id", E::name());
-        f.debug_tuple(name.as_str())
-         .field(&self.0)
-         .finish()
+        Display::fmt(self, f)
+    }
+}
+
+impl<E: Entity> Display for EntityId<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}#{}", E::name(), self.0)
     }
 }
 
@@ -46,6 +94,12 @@ impl<E: Entity> EntityId<E> {
     pub fn new(index: usize) -> Self {
         Self(index, PhantomData)
     }
+
+    /// The index of this id, for the code generated by `define_property!`.
+    #[doc(hidden)]
+    pub fn index(self) -> usize {
+        self.0
+    }
 }
 
 /// All entities must implement this trait using the `define_entity!` macro.
@@ -65,17 +119,14 @@ pub trait Entity: Any + Default {
     where
         Self: Sized,
     {
-        let (property_ids, _) = unsafe { get_entity_metadata_static(<Self as Entity>::type_id()) };
-        property_ids
+        &get_entity_metadata(<Self as Entity>::type_id()).property_ids
     }
 
     fn required_property_ids() -> &'static [TypeId]
     where
         Self: Sized,
     {
-        let (_, required_property_ids) =
-            unsafe { get_entity_metadata_static(<Self as Entity>::type_id()) };
-        required_property_ids
+        &get_entity_metadata(<Self as Entity>::type_id()).required_property_ids
     }
 
     /// The index of this item in the owner, which is initialized globally per type
@@ -96,3 +147,58 @@ pub trait Entity: Any + Default {
 }
 
 pub type BxEntity = Box<dyn Entity>;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use crate::define_entity;
+
+    define_entity!(Person);
+
+    #[test]
+    fn entity_id_formatting() {
+        let person = PersonId::new(3);
+        assert_eq!(format!("{person:?}"), "Person#3");
+        assert_eq!(format!("{person}"), "Person#3");
+        assert_eq!(format!("{:?}", Some(person)), "Some(Person#3)");
+    }
+
+    #[test]
+    fn entity_ids_are_copy() {
+        // `School` has a field that isn't `Copy`, so `School` isn't either, but its ids are.
+        define_entity!(School { name: String });
+
+        let school = SchoolId::new(4);
+        let copy = school;
+        assert_eq!(school, copy);
+        let pair = [school, copy];
+        assert_eq!(pair.map(|id| id.0), [4, 4]);
+        #[allow(clippy::clone_on_copy)]
+        let clone = school.clone();
+        assert_eq!(format!("{clone} {copy:?}"), "School#4 School#4");
+    }
+
+    #[test]
+    fn entity_ids_as_map_keys() {
+        let ids = [PersonId::new(7), PersonId::new(2), PersonId::new(5)];
+
+        let hash_map: HashMap<PersonId, usize> = ids.iter().map(|id| (*id, id.0 * 10)).collect();
+        assert_eq!(hash_map.len(), 3);
+        assert_eq!(hash_map[&PersonId::new(5)], 50);
+
+        let btree_map: BTreeMap<PersonId, usize> = ids.iter().map(|id| (*id, id.0 * 10)).collect();
+        let keys: Vec<PersonId> = btree_map.keys().copied().collect();
+        assert_eq!(keys, [PersonId::new(2), PersonId::new(5), PersonId::new(7)]);
+        assert!(PersonId::new(2) < PersonId::new(5));
+    }
+
+    #[test]
+    fn entity_id_serializes_as_its_index() {
+        let people = vec![PersonId::new(3), PersonId::new(0)];
+        let json = serde_json::to_string(&people).unwrap();
+        assert_eq!(json, "[3,0]");
+        let deserialized: Vec<PersonId> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, people);
+    }
+}
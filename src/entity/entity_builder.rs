@@ -0,0 +1,182 @@
+/*!
+
+An [`EntityBuilder`] creates an entity from property values supplied one at a time, for code
+that decides which properties to set at runtime, where writing the tuple for
+[`Context::add_entity`] would be awkward.
+
+```rust,ignore
+let mut builder = context.entity_builder::<Person>();
+builder.set(Age(25));
+if vaccinated {
+    builder.set(Vaccinated(true));
+}
+let person = builder.build()?;
+```
+
+Nothing is checked until [`EntityBuilder::build`], which applies the same checks as
+[`Context::try_add_entity`], in the same order, and creates nothing if any fails.
+
+*/
+
+use std::any::{Any, TypeId};
+
+use super::{
+    Entity, EntityId,
+    property::Property,
+    property_store::{PropertyStore, get_property_name},
+};
+use crate::{AddEntityError, Context, InvalidPropertyValueError};
+
+/// A property value waiting to be assigned, with functions monomorphized for its concrete type
+/// when it was added, like the hooks of a `RegisteredProperty`.
+struct PendingValue<E: Entity> {
+    property_type_id: TypeId,
+    property_name: &'static str,
+    value: Box<dyn Any>,
+    /// Checks the value with `Property::validate`.
+    validate: fn(&Context, &dyn Any) -> Result<(), InvalidPropertyValueError>,
+    /// Sets the value for the given entity in the given store.
+    assign: fn(&PropertyStore, EntityId<E>, Box<dyn Any>),
+}
+
+fn validate_value<E: Entity, P: Property<E>>(
+    context: &Context,
+    value: &dyn Any,
+) -> Result<(), InvalidPropertyValueError> {
+    let value: &P = value.downcast_ref().expect("pending value has the wrong type");
+    InvalidPropertyValueError::check(context, value)
+}
+
+fn assign_value<E: Entity, P: Property<E>>(
+    property_store: &PropertyStore,
+    entity_id: EntityId<E>,
+    value: Box<dyn Any>,
+) {
+    let value: Box<P> = value.downcast().expect("pending value has the wrong type");
+    property_store.get::<E, P>().set(entity_id, *value);
+}
+
+/// Collects the initial property values of a new entity of type `E`. Created with
+/// [`Context::entity_builder`].
+pub struct EntityBuilder<'a, E: Entity> {
+    context: &'a mut Context,
+    values: Vec<PendingValue<E>>,
+}
+
+impl<'a, E: Entity> EntityBuilder<'a, E> {
+    /// Adds `value` to the new entity's initial values.
+    pub fn set<P: Property<E>>(&mut self, value: P) -> &mut Self {
+        self.values.push(PendingValue {
+            property_type_id: <P as Property<E>>::type_id(),
+            property_name: P::name(),
+            value: Box::new(value),
+            validate: validate_value::<E, P>,
+            assign: assign_value::<E, P>,
+        });
+        self
+    }
+
+    /// Creates the entity with the values added with [`EntityBuilder::set`], or returns an error
+    /// without creating anything if a property was added more than once, a required property is
+    /// missing, or [`Property::validate`] rejects a value.
+    pub fn build(self) -> Result<EntityId<E>, AddEntityError> {
+        for (i, pending) in self.values.iter().enumerate() {
+            if self.values[i + 1..]
+                .iter()
+                .any(|other| other.property_type_id == pending.property_type_id)
+            {
+                return Err(AddEntityError::DuplicateProperty(format!(
+                    "the property {} is set more than once",
+                    pending.property_name
+                )));
+            }
+        }
+
+        let assigned: Vec<TypeId> = self
+            .values
+            .iter()
+            .map(|pending| pending.property_type_id)
+            .collect();
+        let missing: Vec<&'static str> = E::required_property_ids()
+            .iter()
+            .filter(|property_type_id| !assigned.contains(property_type_id))
+            .map(|property_type_id| get_property_name(*property_type_id).unwrap_or("<unknown property>"))
+            .collect();
+        if !missing.is_empty() {
+            return Err(AddEntityError::MissingRequired(missing));
+        }
+
+        for pending in &self.values {
+            (pending.validate)(self.context, pending.value.as_ref())
+                .map_err(AddEntityError::InvalidValue)?;
+        }
+
+        let values = self.values;
+        Ok(self.context.create_entity(
+            |property_store, entity_id| {
+                for pending in values {
+                    (pending.assign)(property_store, entity_id, pending.value);
+                }
+            },
+            |property_type_id| assigned.contains(&property_type_id),
+        ))
+    }
+}
+
+impl Context {
+    /// Starts building a new entity of type `E` from values supplied one at a time. See
+    /// [`EntityBuilder`].
+    pub fn entity_builder<E: Entity>(&mut self) -> EntityBuilder<'_, E> {
+        EntityBuilder {
+            context: self,
+            values: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AddEntityError, Context, define_entity, define_property};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+    define_property!(struct Vaccinated(bool), Person, default_const = Vaccinated(false));
+    define_property!(struct Weight(u16), Person);
+
+    #[test]
+    fn build_an_entity() {
+        let mut context = Context::new();
+        let mut builder = context.entity_builder::<Person>();
+        builder.set(Age(25));
+        builder.set(Vaccinated(true));
+        let person = builder.build().unwrap();
+
+        assert_eq!(context.get_property::<Person, Age>(person), Age(25));
+        assert_eq!(context.get_property::<Person, Vaccinated>(person), Vaccinated(true));
+        assert!(!context.is_property_set::<Person, Weight>(person));
+
+        let mut builder = context.entity_builder::<Person>();
+        builder.set(Age(30)).set(Weight(80));
+        assert_eq!(builder.build(), Ok(PersonId::new(1)));
+        assert_eq!(context.get_property::<Person, Vaccinated>(PersonId::new(1)), Vaccinated(false));
+    }
+
+    #[test]
+    fn build_checks_the_values() {
+        let mut context = Context::new();
+        let mut builder = context.entity_builder::<Person>();
+        builder.set(Vaccinated(true));
+        assert_eq!(builder.build(), Err(AddEntityError::MissingRequired(vec!["Age"])));
+
+        let mut builder = context.entity_builder::<Person>();
+        builder.set(Age(25)).set(Weight(70)).set(Age(26));
+        assert_eq!(
+            builder.build(),
+            Err(AddEntityError::DuplicateProperty(
+                "the property Age is set more than once".to_string()
+            ))
+        );
+        assert_eq!(context.get_entity_count::<Person>(), 0);
+    }
+}
@@ -2,8 +2,41 @@
 
 /// Defines a zero-sized struct with the right derived traits and implements the `Entity` trait. If you already
 /// have a type defined (struct, enum, etc.), you can use the `impl_entity!` macro instead.
+///
+/// The struct can also carry data about the entity type as a whole, such as the capacity of a
+/// school, given as named fields. The fields are public, and `new` takes their values in order:
+///
+/// ```rust,ignore
+/// define_entity!(School { name: &'static str, capacity: usize });
+///
+/// context.entity_store.set(School::new("Central", 500));
+/// assert_eq!(context.entity_store.get::<School>().capacity, 500);
+/// ```
+///
+/// Each `Context` has its own instance, which is `Default::default()` until it is set.
 #[macro_export]
 macro_rules! define_entity {
+    ($entity_name:ident { $($field_name:ident : $field_ty:ty),* $(,)? }) => {
+        #[derive(Default, Debug, PartialEq, Clone)]
+        pub struct $entity_name {
+            $(pub $field_name: $field_ty,)*
+            // Field holds the total count of all entities of this type. Equivalently,
+            // this is the index of the next entity to be created.
+            entity_count: usize,
+        }
+
+        impl $entity_name {
+            pub fn new($($field_name: $field_ty),*) -> Self {
+                Self {
+                    $($field_name,)*
+                    entity_count: 0,
+                }
+            }
+        }
+
+        $crate::impl_entity!($entity_name);
+    };
+
     ($entity_name:ident) => {
         #[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
         pub struct $entity_name {
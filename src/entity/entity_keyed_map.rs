@@ -0,0 +1,538 @@
+/*!
+
+Maps keyed by `EntityId<E>`. Because entity ids are dense indices starting at zero, a map from
+ids to values can be stored as a vector indexed by `EntityId<E>` instead of a `HashMap`.
+
+An `EntityKeyedMap<E, T>` is backed by a `Vec<T>` and holds a value for every id below its
+length, with no holes. It suits data that exists for every entity, such as a value computed
+for the whole population, where the value at position `i` belongs to `EntityId::<E>::new(i)`.
+
+An `EntityKeyedSparseMap<E, T>` is backed by a `Vec<Option<T>>`, so it can hold values for an
+arbitrary subset of entities. The vector grows on `insert` to fit the largest id seen, so memory
+use is proportional to the largest key, not to the number of entries.
+
+*/
+
+use std::{iter::Enumerate, marker::PhantomData};
+
+use super::entity::{Entity, EntityId};
+
+/// A map from `EntityId<E>` to `T` that holds a value for every id below its length.
+pub struct EntityKeyedMap<E: Entity, T> {
+    data: Vec<T>,
+    _phantom: PhantomData<E>,
+}
+
+// The derive version of `Default` introduces unnecessary trait bounds on `T`.
+impl<E: Entity, T> Default for EntityKeyedMap<E, T> {
+    fn default() -> Self {
+        Self {
+            data: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: Entity, T> From<Vec<T>> for EntityKeyedMap<E, T> {
+    /// The value at position `i` of `data` becomes the value for `EntityId::<E>::new(i)`.
+    fn from(data: Vec<T>) -> Self {
+        Self {
+            data,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: Entity, T> EntityKeyedMap<E, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::from(Vec::with_capacity(capacity))
+    }
+
+    /// The number of entities that have a value in the map, which is one more than the largest id.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Resizes the map to hold values for exactly `len` entities. New slots are filled with
+    /// clones of `value`; if `len` is smaller than the current length, the excess is dropped.
+    pub fn resize_to(&mut self, len: usize, value: T)
+    where
+        T: Clone,
+    {
+        self.data.resize(len, value);
+    }
+
+    /// Appends a value for the next id, returning that id.
+    pub fn push(&mut self, value: T) -> EntityId<E> {
+        self.data.push(value);
+        EntityId::new(self.data.len() - 1)
+    }
+
+    pub fn get(&self, entity_id: EntityId<E>) -> Option<&T> {
+        self.data.get(entity_id.0)
+    }
+
+    pub fn get_mut(&mut self, entity_id: EntityId<E>) -> Option<&mut T> {
+        self.data.get_mut(entity_id.0)
+    }
+
+    /// Returns mutable references to the values of several distinct entities at once, in the
+    /// order of `entity_ids`. Returns `None` if any id is out of range or appears more than once.
+    pub fn get_disjoint_mut<const N: usize>(
+        &mut self,
+        entity_ids: [EntityId<E>; N],
+    ) -> Option<[&mut T; N]> {
+        self.data
+            .get_disjoint_mut(entity_ids.map(|entity_id| entity_id.0))
+            .ok()
+    }
+
+    /// Returns the values as a slice. The value at position `i` belongs to the entity with raw
+    /// index `i`, so reordering the slice (e.g. sorting it) reassigns values to different
+    /// entities.
+    pub fn as_slice(&self) -> &[T] {
+        self.data.as_slice()
+    }
+
+    /// Returns the values as a mutable slice. See [`EntityKeyedMap::as_slice`].
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self.data.as_mut_slice()
+    }
+
+    /// Returns a raw pointer to the values, for example to pass them across an FFI boundary.
+    /// The pointee at offset `i` belongs to the entity with raw index `i`. The pointer is
+    /// invalidated by any operation that may reallocate, such as `push`.
+    pub fn as_ptr(&self) -> *const T {
+        self.data.as_ptr()
+    }
+
+    /// Returns a raw mutable pointer to the values. See [`EntityKeyedMap::as_ptr`].
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.data.as_mut_ptr()
+    }
+
+    /// Iterates over the values in id order.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    /// Iterates over the entries of the map in id order.
+    pub fn iter_with_ids(&self) -> impl Iterator<Item = (EntityId<E>, &T)> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (EntityId::new(index), value))
+    }
+}
+
+/// Places each value at the position of its id. Because an `EntityKeyedMap` has no holes, any
+/// id below the largest one that the iterator doesn't supply gets `T::default()`. If an id
+/// appears more than once, the last value wins.
+impl<E: Entity, T: Default> FromIterator<(EntityId<E>, T)> for EntityKeyedMap<E, T> {
+    fn from_iter<I: IntoIterator<Item = (EntityId<E>, T)>>(iter: I) -> Self {
+        let mut data = Vec::new();
+        for (entity_id, value) in iter {
+            let index = entity_id.0;
+            if index >= data.len() {
+                data.resize_with(index + 1, T::default);
+            }
+            data[index] = value;
+        }
+        Self::from(data)
+    }
+}
+
+/// An owning iterator over the `(EntityId<E>, T)` entries of an [`EntityKeyedMap`] in id order.
+pub struct IntoIter<E: Entity, T> {
+    inner: Enumerate<std::vec::IntoIter<T>>,
+    _phantom: PhantomData<E>,
+}
+
+impl<E: Entity, T> Iterator for IntoIter<E, T> {
+    type Item = (EntityId<E>, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(index, value)| (EntityId::new(index), value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<E: Entity, T> IntoIterator for EntityKeyedMap<E, T> {
+    type Item = (EntityId<E>, T);
+    type IntoIter = IntoIter<E, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            inner: self.data.into_iter().enumerate(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// A map from `EntityId<E>` to `T` that may hold values for any subset of entities.
+pub struct EntityKeyedSparseMap<E: Entity, T> {
+    data: Vec<Option<T>>,
+    /// The number of occupied slots in `data`.
+    len: usize,
+    _phantom: PhantomData<E>,
+}
+
+// The derive version of `Default` introduces unnecessary trait bounds on `T`.
+impl<E: Entity, T> Default for EntityKeyedSparseMap<E, T> {
+    fn default() -> Self {
+        Self {
+            data: Vec::new(),
+            len: 0,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: Entity, T> EntityKeyedSparseMap<E, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty map with room for ids below `capacity` without reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(capacity),
+            len: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The number of entities that have a value in the map.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Sets the value for `entity_id`, returning the previous value if there was one.
+    pub fn insert(&mut self, entity_id: EntityId<E>, value: T) -> Option<T> {
+        let index = entity_id.0;
+        if index >= self.data.len() {
+            self.data.resize_with(index + 1, || None);
+        }
+        let old_value = self.data[index].replace(value);
+        if old_value.is_none() {
+            self.len += 1;
+        }
+        old_value
+    }
+
+    pub fn get(&self, entity_id: EntityId<E>) -> Option<&T> {
+        self.data.get(entity_id.0).and_then(Option::as_ref)
+    }
+
+    pub fn get_mut(&mut self, entity_id: EntityId<E>) -> Option<&mut T> {
+        self.data.get_mut(entity_id.0).and_then(Option::as_mut)
+    }
+
+    pub fn contains_key(&self, entity_id: EntityId<E>) -> bool {
+        self.get(entity_id).is_some()
+    }
+
+    /// Removes and returns the value for `entity_id`, if there is one.
+    pub fn remove(&mut self, entity_id: EntityId<E>) -> Option<T> {
+        let old_value = self.data.get_mut(entity_id.0).and_then(Option::take);
+        if old_value.is_some() {
+            self.len -= 1;
+        }
+        old_value
+    }
+
+    /// Removes every value, keeping the allocated storage.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.len = 0;
+    }
+
+    /// Removes every entry for which `keep` returns `false`. Entries are visited in id order, and
+    /// the surviving entries keep their ids.
+    pub fn retain(&mut self, mut keep: impl FnMut(EntityId<E>, &mut T) -> bool) {
+        for (index, slot) in self.data.iter_mut().enumerate() {
+            if let Some(value) = slot
+                && !keep(EntityId::new(index), value)
+            {
+                *slot = None;
+                self.len -= 1;
+            }
+        }
+    }
+
+    /// Gets the entry for `entity_id` for in-place manipulation, growing the map to fit
+    /// `entity_id` if necessary.
+    pub fn entry(&mut self, entity_id: EntityId<E>) -> Entry<'_, T> {
+        let index = entity_id.0;
+        if index >= self.data.len() {
+            self.data.resize_with(index + 1, || None);
+        }
+        Entry {
+            slot: &mut self.data[index],
+            len: &mut self.len,
+        }
+    }
+
+    /// Iterates over the entries of the map in id order.
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId<E>, &T)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| value.as_ref().map(|value| (EntityId::new(index), value)))
+    }
+}
+
+/// A view into a single slot of an [`EntityKeyedSparseMap`], which may or may not hold a value.
+pub struct Entry<'a, T> {
+    slot: &'a mut Option<T>,
+    /// The occupied slot count of the map, kept up to date when the entry is filled.
+    len: &'a mut usize,
+}
+
+impl<'a, T> Entry<'a, T> {
+    /// Returns a mutable reference to the value, first inserting `default` if the slot is empty.
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns a mutable reference to the value, first inserting the result of `default` if the
+    /// slot is empty.
+    pub fn or_insert_with(self, default: impl FnOnce() -> T) -> &'a mut T {
+        if self.slot.is_none() {
+            *self.len += 1;
+        }
+        self.slot.get_or_insert_with(default)
+    }
+
+    /// Applies `f` to the value if there is one.
+    pub fn and_modify(self, f: impl FnOnce(&mut T)) -> Self {
+        if let Some(value) = self.slot.as_mut() {
+            f(value);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Context, define_entity, define_property};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person);
+
+    #[test]
+    fn dense_map_ids_line_up_with_positions() {
+        let mut map = EntityKeyedMap::<Person, &str>::new();
+        assert_eq!(map.push("zero"), PersonId::new(0));
+        assert_eq!(map.push("one"), PersonId::new(1));
+        map.push("two");
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(PersonId::new(1)), Some(&"one"));
+        assert_eq!(map.get(PersonId::new(3)), None);
+
+        assert_eq!(map.iter().copied().collect::<Vec<_>>(), vec!["zero", "one", "two"]);
+        assert_eq!(
+            map.iter_with_ids().collect::<Vec<_>>(),
+            vec![
+                (PersonId::new(0), &"zero"),
+                (PersonId::new(1), &"one"),
+                (PersonId::new(2), &"two"),
+            ]
+        );
+        for (person, value) in map {
+            assert_eq!(value, ["zero", "one", "two"][person.0]);
+        }
+    }
+
+    #[test]
+    fn sum_a_property_column_through_a_slice() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = [12, 34, 56]
+            .into_iter()
+            .map(|age| context.add_entity((Age(age),)))
+            .collect();
+
+        let mut ages: EntityKeyedMap<Person, u32> = people
+            .iter()
+            .map(|person| (*person, u32::from(context.get_property::<_, Age>(*person).0)))
+            .collect();
+        assert_eq!(ages.as_slice().iter().sum::<u32>(), 102);
+
+        ages.as_mut_slice()[1] = 0;
+        assert_eq!(ages.get(people[1]), Some(&0));
+        assert_eq!(ages.as_ptr(), ages.as_slice().as_ptr());
+    }
+
+    #[test]
+    fn get_disjoint_mut_swaps_values() {
+        let mut map = EntityKeyedMap::<Person, u32>::from(vec![1, 2, 3]);
+        let [a, b] = map
+            .get_disjoint_mut([PersonId::new(0), PersonId::new(2)])
+            .unwrap();
+        std::mem::swap(a, b);
+        assert_eq!(map.as_slice(), &[3, 2, 1]);
+    }
+
+    #[test]
+    fn get_disjoint_mut_duplicate_id() {
+        let mut map = EntityKeyedMap::<Person, u32>::from(vec![1, 2, 3]);
+        assert!(map.get_disjoint_mut([PersonId::new(1), PersonId::new(1)]).is_none());
+    }
+
+    #[test]
+    fn get_disjoint_mut_out_of_range() {
+        let mut map = EntityKeyedMap::<Person, u32>::from(vec![1, 2, 3]);
+        assert!(map.get_disjoint_mut([PersonId::new(0), PersonId::new(3)]).is_none());
+    }
+
+    #[test]
+    fn new_entity_keyed_map_covers_population() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..5).map(|age| context.add_entity((Age(age),))).collect();
+
+        let mut counts = context.new_entity_keyed_map::<Person, u32>(0);
+        assert_eq!(counts.len(), people.len());
+        for person in &people {
+            *counts.get_mut(*person).unwrap() += 1;
+        }
+        assert!(counts.iter().all(|count| *count == 1));
+
+        context.add_entity((Age(9),));
+        counts.resize_to(context.get_entity_count::<Person>(), 7);
+        assert_eq!(counts.get(PersonId::new(5)), Some(&7));
+        counts.resize_to(2, 0);
+        assert_eq!(counts.as_slice(), &[1, 1]);
+    }
+
+    #[test]
+    fn collect_contiguous_ids() {
+        let map: EntityKeyedMap<Person, u32> = [2, 1, 0]
+            .into_iter()
+            .map(|index| (PersonId::new(index), index as u32 * 10))
+            .collect();
+        assert_eq!(map.iter().copied().collect::<Vec<_>>(), vec![0, 10, 20]);
+    }
+
+    #[test]
+    fn collect_ids_with_a_gap() {
+        let map: EntityKeyedMap<Person, u32> =
+            [(PersonId::new(0), 5), (PersonId::new(3), 7)].into_iter().collect();
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.iter().copied().collect::<Vec<_>>(), vec![5, 0, 0, 7]);
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut map = EntityKeyedSparseMap::<Person, &str>::new();
+        assert_eq!(map.insert(PersonId::new(5), "five"), None);
+        assert_eq!(map.insert(PersonId::new(2), "two"), None);
+
+        assert_eq!(map.get(PersonId::new(5)), Some(&"five"));
+        assert_eq!(map.get(PersonId::new(2)), Some(&"two"));
+        assert_eq!(map.get(PersonId::new(3)), None);
+        assert_eq!(map.get(PersonId::new(100)), None);
+
+        assert_eq!(map.insert(PersonId::new(5), "FIVE"), Some("five"));
+        *map.get_mut(PersonId::new(2)).unwrap() = "TWO";
+        assert_eq!(map.get(PersonId::new(2)), Some(&"TWO"));
+
+        assert_eq!(map.remove(PersonId::new(5)), Some("FIVE"));
+        assert_eq!(map.remove(PersonId::new(5)), None);
+        assert_eq!(map.remove(PersonId::new(100)), None);
+        assert!(!map.contains_key(PersonId::new(5)));
+        assert!(map.contains_key(PersonId::new(2)));
+    }
+
+    #[test]
+    fn len_counts_occupied_slots() {
+        let mut map = EntityKeyedSparseMap::<Person, u32>::new();
+        assert!(map.is_empty());
+
+        map.insert(PersonId::new(10), 1);
+        map.insert(PersonId::new(0), 2);
+        assert_eq!(map.len(), 2);
+
+        // Overwriting doesn't add an entry, and removing a missing key doesn't remove one.
+        map.insert(PersonId::new(10), 3);
+        map.remove(PersonId::new(4));
+        assert_eq!(map.len(), 2);
+
+        map.remove(PersonId::new(0));
+        assert_eq!(map.len(), 1);
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(PersonId::new(10), &3)]
+        );
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.get(PersonId::new(10)), None);
+    }
+
+    #[test]
+    fn entry_accumulates_counts() {
+        let mut contacts = EntityKeyedSparseMap::<Person, u32>::new();
+
+        *contacts.entry(PersonId::new(3)).or_insert_with(|| 0) += 1;
+        assert_eq!(contacts.get(PersonId::new(3)), Some(&1));
+        assert_eq!(contacts.len(), 1);
+
+        contacts
+            .entry(PersonId::new(3))
+            .and_modify(|count| *count += 1)
+            .or_insert(100);
+        assert_eq!(contacts.get(PersonId::new(3)), Some(&2));
+        assert_eq!(contacts.len(), 1);
+
+        // `and_modify` does nothing for an empty slot.
+        contacts
+            .entry(PersonId::new(7))
+            .and_modify(|count| *count += 1)
+            .or_insert(100);
+        assert_eq!(contacts.get(PersonId::new(7)), Some(&100));
+        assert_eq!(contacts.len(), 2);
+    }
+
+    #[test]
+    fn retain_keeps_ids() {
+        let mut map = EntityKeyedSparseMap::<Person, u32>::new();
+        for (index, value) in [(0, 1), (2, 2), (3, 3), (5, 4), (8, 6)] {
+            map.insert(PersonId::new(index), value);
+        }
+
+        let mut visited = Vec::new();
+        map.retain(|person, value| {
+            visited.push(person);
+            *value % 2 == 0
+        });
+        assert_eq!(visited, [0, 2, 3, 5, 8].map(PersonId::new).to_vec());
+        assert_eq!(map.len(), 3);
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![
+                (PersonId::new(2), &2),
+                (PersonId::new(5), &4),
+                (PersonId::new(8), &6),
+            ]
+        );
+    }
+}
@@ -12,9 +12,9 @@ create or destructure `EntityId<Entity>` values directly. Instead,
 use std::{
     any::{Any, TypeId},
     cell::OnceCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{
-        LazyLock, Mutex,
+        LazyLock, Mutex, OnceLock,
         atomic::{AtomicUsize, Ordering},
     },
 };
@@ -27,53 +27,89 @@ use crate::entity::{Entity, EntityId};
 /// requests an index. Equivalently, holds a *count* of the number of entities currently registered.
 static NEXT_ENTITY_INDEX: Mutex<usize> = Mutex::new(0);
 
-/// For each entity we keep track of the properties associated with it. This maps
-/// `entity_type_id` to `(vec_of_all_property_type_ids, vec_of_required_property_type_ids)`.
-/// This data is actually written by the property ctors with a call to
-/// [`register_property_with_entity()`].
-static ENTITY_METADATA: LazyLock<Mutex<HashMap<TypeId, (Vec<TypeId>, Vec<TypeId>)>>> =
-    LazyLock::new(|| Mutex::new(HashMap::default()));
+/// The properties associated with an entity type.
+#[derive(Debug, Default)]
+pub struct EntityMetadata {
+    /// The `TypeId`s of all of the entity's properties, in registration order.
+    pub property_ids: Vec<TypeId>,
+    /// The `TypeId`s of the entity's required properties, in registration order.
+    pub required_property_ids: Vec<TypeId>,
+}
+
+/// The metadata of entity types without registered properties.
+static EMPTY_ENTITY_METADATA: EntityMetadata = EntityMetadata {
+    property_ids: Vec::new(),
+    required_property_ids: Vec::new(),
+};
+
+/// For each entity we keep track of the properties associated with it, keyed by
+/// `entity_type_id`. This data is written by the property ctors with a call to
+/// [`register_property_with_entity()`]. The first lookup moves the map into
+/// `FROZEN_ENTITY_METADATA` and leaves `None` behind, after which no more properties can be
+/// registered.
+static ENTITY_METADATA: LazyLock<Mutex<Option<HashMap<TypeId, EntityMetadata>>>> =
+    LazyLock::new(|| Mutex::new(Some(HashMap::default())));
+
+/// The metadata of every entity type, fixed at the first lookup so that we can hand out
+/// `'static` references to it.
+static FROZEN_ENTITY_METADATA: OnceLock<HashMap<TypeId, EntityMetadata>> = OnceLock::new();
 
 /// The public interface to `ENTITY_METADATA`.
+///
+/// Panics if called after the metadata has been looked up, which can only happen if a
+/// property is registered after `main` starts.
 pub fn register_property_with_entity(
     entity_type_id: TypeId,
     property_type_id: TypeId,
     required: bool,
 ) {
     let mut entity_metadata = ENTITY_METADATA.lock().unwrap();
-    let (property_type_ids, required_property_type_ids) = entity_metadata
+    let metadata = entity_metadata
+        .as_mut()
+        .expect("properties must be registered before entity metadata is first looked up")
         .entry(entity_type_id)
-        .or_insert_with(|| (Vec::new(), Vec::new()));
-    property_type_ids.push(property_type_id);
+        .or_default();
+    metadata.property_ids.push(property_type_id);
     if required {
-        required_property_type_ids.push(property_type_id);
+        metadata.required_property_ids.push(property_type_id);
     }
 }
 
-/// The public getter to `ENTITY_METADATA`.
-/// # Safety
-/// This function assumes that `ENTITY_METADATA` will never again be mutated
-/// after the first call.  Mutating the map or the `Vec`s after taking these
-/// references would cause undefined behavior.
-pub unsafe fn get_entity_metadata_static(
-    entity_type_id: TypeId,
-) -> (&'static [TypeId], &'static [TypeId]) {
-    let mut map = ENTITY_METADATA.lock().unwrap();
-
-    // Insert empty vectors if not already registered
-    let (props, reqs) = map
-        .entry(entity_type_id)
-        .or_insert_with(|| (Vec::new(), Vec::new()));
+/// The public getter to `ENTITY_METADATA`. Returns empty metadata for an entity type without
+/// registered properties.
+pub fn get_entity_metadata(entity_type_id: TypeId) -> &'static EntityMetadata {
+    FROZEN_ENTITY_METADATA
+        .get_or_init(|| ENTITY_METADATA.lock().unwrap().take().unwrap_or_default())
+        .get(&entity_type_id)
+        .unwrap_or(&EMPTY_ENTITY_METADATA)
+}
 
-    // Transmute to `'static` slices. This assumes these `Vec`s will never move or deallocate.
-    let props_static: &'static [TypeId] =
-        unsafe { std::mem::transmute::<&[TypeId], &'static [TypeId]>(props.as_slice()) };
-    let reqs_static: &'static [TypeId] =
-        unsafe { std::mem::transmute::<&[TypeId], &'static [TypeId]>(reqs.as_slice()) };
+/// The name of a registered entity alongside its index.
+#[derive(Copy, Clone, Debug)]
+pub struct RegisteredEntity {
+    pub name: &'static str,
+    /// The fully qualified type name, which (unlike the `TypeId`) is stable across builds.
+    pub type_name: &'static str,
+    pub type_id: TypeId,
+    pub index: usize,
+    /// Returns a boxed copy of the given boxed instance of the entity type.
+    pub(crate) clone_entity: fn(&dyn Any) -> Box<dyn Any>,
+}
 
-    (props_static, reqs_static)
+/// A description of an entity type, for code that enumerates the entity types at runtime (see
+/// [`Context::entity_types`](crate::Context::entity_types)).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EntityTypeMeta {
+    pub name: &'static str,
+    pub type_id: TypeId,
+    /// The number of entities of this type that have been created and not removed.
+    pub count: usize,
+    pub property_count: usize,
 }
 
+/// The [`RegisteredEntity`] record of every entity registered with [`add_to_entity_registry()`].
+static REGISTERED_ENTITIES: Mutex<Vec<RegisteredEntity>> = Mutex::new(Vec::new());
+
 /// Adds a new item to the registry. The job of this method is to create whatever
 /// "singleton" data/metadata is associated with the [`Entity`] if it doesn't already
 /// exist.
@@ -83,8 +119,35 @@ pub unsafe fn get_entity_metadata_static(
 /// construct in the constructor of `EntityStore`, so that we never have to mutate
 /// `EntityStore` itself when an `Entity` is accessed for the first time. (The
 /// `OnceCell` itself handles the interior mutability required for initialization.)
-pub fn add_to_entity_registry<R: Entity>() {
-    let _ = R::index();
+pub fn add_to_entity_registry<R: Entity + Clone>() {
+    let index = R::index();
+    let mut registered_entities = REGISTERED_ENTITIES.lock().unwrap();
+    if !registered_entities.iter().any(|entity| entity.index == index) {
+        registered_entities.push(RegisteredEntity {
+            name: R::name(),
+            type_name: std::any::type_name::<R>(),
+            type_id: <R as Entity>::type_id(),
+            index,
+            clone_entity: clone_entity::<R>,
+        });
+    }
+}
+
+/// Returns a boxed copy of `entity`, which must be an `R`.
+fn clone_entity<R: Entity + Clone>(entity: &dyn Any) -> Box<dyn Any> {
+    Box::new(
+        entity
+            .downcast_ref::<R>()
+            .expect("TypeID does not match registered item type")
+            .clone(),
+    )
+}
+
+/// Returns the records of all registered entities ordered by entity index.
+pub fn get_registered_entities() -> Vec<RegisteredEntity> {
+    let mut registered_entities = REGISTERED_ENTITIES.lock().unwrap().clone();
+    registered_entities.sort_by_key(|entity| entity.index);
+    registered_entities
 }
 
 /// A convenience getter for `NEXT_ENTITY_INDEX`.
@@ -134,6 +197,8 @@ pub fn initialize_entity_index(plugin_index: &AtomicUsize) -> usize {
 pub struct EntityRecord {
     /// The total count of all entities of this type (i.e., the next index to assign).
     pub(crate) entity_count: usize,
+    /// The indices of removed entities. Their ids are never reused.
+    pub(crate) removed: HashSet<usize>,
     /// Lazily initialized `Entity` instance.
     pub(crate) entity: OnceCell<Box<dyn Any>>,
 }
@@ -142,6 +207,7 @@ impl EntityRecord {
     pub(crate) fn new() -> Self {
         Self {
             entity_count: 0,
+            removed: HashSet::new(),
             entity: OnceCell::new(),
         }
     }
@@ -152,6 +218,29 @@ pub struct EntityStore {
     items: Vec<EntityRecord>,
 }
 
+// The entity counts are copied, and each instantiated entity is copied through its registry hook.
+impl Clone for EntityStore {
+    fn clone(&self) -> Self {
+        let mut items: Vec<EntityRecord> = self
+            .items
+            .iter()
+            .map(|record| EntityRecord {
+                entity_count: record.entity_count,
+                removed: record.removed.clone(),
+                entity: OnceCell::new(),
+            })
+            .collect();
+        for entity in get_registered_entities() {
+            if let Some(instance) = self.items[entity.index].entity.get() {
+                let _ = items[entity.index]
+                    .entity
+                    .set((entity.clone_entity)(instance.as_ref()));
+            }
+        }
+        Self { items }
+    }
+}
+
 impl Default for EntityStore {
     fn default() -> Self {
         EntityStore::new()
@@ -224,6 +313,81 @@ impl EntityStore {
             .expect("TypeID does not match the registered item type. You must use the `define_registered_item!` macro to create a registered item.")
     }
 
+    /// Replaces the instance of the item `E`, e.g. to set data that an entity defined with fields
+    /// carries about the entity type as a whole.
+    pub fn set<E: Entity>(&mut self, entity: E) {
+        let record = &mut self.items[E::index()];
+        record.entity = OnceCell::from(Box::new(entity) as Box<dyn Any>);
+    }
+
+    /// Returns the number of entities of type `E` that have been created.
+    #[must_use]
+    pub fn get_entity_count<E: Entity>(&self) -> usize {
+        self.items[E::index()].entity_count
+    }
+
+    /// Returns the number of entities of the entity type with the given index.
+    pub(crate) fn get_entity_count_by_index(&self, index: usize) -> usize {
+        self.items[index].entity_count
+    }
+
+    /// Overwrites the number of entities of the entity type with the given index, e.g. when
+    /// restoring a saved population.
+    pub(crate) fn set_entity_count_by_index(&mut self, index: usize, entity_count: usize) {
+        self.items[index].entity_count = entity_count;
+    }
+
+    /// Resets the count of every entity type to zero, so that ids are handed out from 0 again.
+    pub(crate) fn reset_entity_counts(&mut self) {
+        for record in &mut self.items {
+            record.entity_count = 0;
+            record.removed.clear();
+        }
+    }
+
+    /// Returns the number of entities of type `E` that have been created and not removed.
+    #[must_use]
+    pub fn get_live_entity_count<E: Entity>(&self) -> usize {
+        let record = &self.items[E::index()];
+        record.entity_count - record.removed.len()
+    }
+
+    /// Returns the number of live entities of the entity type with the given index.
+    pub(crate) fn get_live_entity_count_by_index(&self, index: usize) -> usize {
+        let record = &self.items[index];
+        record.entity_count - record.removed.len()
+    }
+
+    /// Iterates over the ids of the entities of type `E` that have been created and not removed,
+    /// in id order.
+    pub(crate) fn live_entity_ids<E: Entity>(&self) -> impl Iterator<Item = EntityId<E>> + '_ {
+        let record = &self.items[E::index()];
+        (0..record.entity_count)
+            .filter(|index| !record.removed.contains(index))
+            .map(EntityId::new)
+    }
+
+    /// Returns `true` if `entity_id` has been created and not removed.
+    #[must_use]
+    pub fn is_live<E: Entity>(&self, entity_id: EntityId<E>) -> bool {
+        let record = &self.items[E::index()];
+        entity_id.0 < record.entity_count && !record.removed.contains(&entity_id.0)
+    }
+
+    /// Sets the number of entities of type `E` to `entity_count` and forgets which were removed,
+    /// after the live entities have been renumbered `0..entity_count`.
+    pub(crate) fn set_compacted<E: Entity>(&mut self, entity_count: usize) {
+        let record = &mut self.items[E::index()];
+        record.entity_count = entity_count;
+        record.removed.clear();
+    }
+
+    /// Records that `entity_id` has been removed. Returns `false` if it wasn't live.
+    pub(crate) fn mark_removed<E: Entity>(&mut self, entity_id: EntityId<E>) -> bool {
+        let record = &mut self.items[E::index()];
+        entity_id.0 < record.entity_count && record.removed.insert(entity_id.0)
+    }
+
     /// Creates a new `EntityId` for the given `Entity` type `E`.
     /// Increments the entity counter and returns the next valid ID.
     pub(crate) fn new_entity_id<E: Entity>(&mut self) -> EntityId<E> {
@@ -238,7 +402,7 @@ impl EntityStore {
 #[cfg(test)]
 mod tests {
     use std::{
-        any::Any,
+        any::{Any, TypeId},
         sync::{
             Arc, Barrier,
             atomic::{AtomicUsize, Ordering},
@@ -246,13 +410,16 @@ mod tests {
         thread,
     };
 
-    use crate::entity::{
-        Entity,
-        entity_store::{
-            EntityStore, add_to_entity_registry, get_registered_entity_count,
-            initialize_entity_index,
+    use crate::{
+        define_entity, define_property,
+        entity::{
+            Entity,
+            entity_store::{
+                EntityStore, add_to_entity_registry, get_entity_metadata,
+                get_registered_entity_count, initialize_entity_index,
+            },
+            impl_entity,
         },
-        impl_entity,
     };
 
     // Test item types
@@ -295,6 +462,12 @@ mod tests {
     impl_entity!(TestItem2);
     impl_entity!(TestItem3);
 
+    define_entity!(Household);
+    define_entity!(School { name: &'static str, capacity: usize });
+    define_property!(struct HouseholdSize(u8), Household, is_required = true);
+    define_property!(struct Income(u32), Household);
+    define_property!(struct Enrollment(u32), School);
+
     // Test the internal synchronization mechanisms of `initialize_entity_index()`.
     //
     // It is convenient to only have a single test that mutates `NEXT_ENTITY_INDEX`,
@@ -649,4 +822,43 @@ mod tests {
             42
         );
     }
+
+    #[test]
+    fn entity_metadata_per_entity_type() {
+        let household = get_entity_metadata(TypeId::of::<Household>());
+        assert_eq!(household.property_ids.len(), 2);
+        assert!(household.property_ids.contains(&TypeId::of::<HouseholdSize>()));
+        assert!(household.property_ids.contains(&TypeId::of::<Income>()));
+        assert_eq!(household.required_property_ids, vec![TypeId::of::<HouseholdSize>()]);
+
+        let school = get_entity_metadata(TypeId::of::<School>());
+        assert_eq!(school.property_ids, vec![TypeId::of::<Enrollment>()]);
+        assert!(school.required_property_ids.is_empty());
+
+        // Entity types without properties have empty metadata.
+        let item = get_entity_metadata(TypeId::of::<TestItem1>());
+        assert!(item.property_ids.is_empty());
+
+        // `Entity::property_ids` returns references into the same metadata.
+        assert_eq!(Household::property_ids(), household.property_ids.as_slice());
+        assert_eq!(School::required_property_ids(), school.required_property_ids.as_slice());
+    }
+
+    #[test]
+    fn entity_with_fields() {
+        let mut entity_store = EntityStore::new();
+        // Until it is set, the instance has default field values.
+        assert_eq!(entity_store.get::<School>().capacity, 0);
+
+        entity_store.set(School::new("Central", 500));
+        let school = entity_store.get::<School>();
+        assert_eq!(school.name, "Central");
+        assert_eq!(school.capacity, 500);
+
+        // Setting the instance doesn't affect the entity count.
+        let _ = entity_store.new_entity_id::<School>();
+        entity_store.set(School::new("Central", 450));
+        assert_eq!(entity_store.get_entity_count::<School>(), 1);
+        assert_eq!(entity_store.get::<School>().capacity, 450);
+    }
 }
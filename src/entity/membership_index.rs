@@ -0,0 +1,322 @@
+/*!
+
+A reverse index over a property that refers to another entity, answering "who belongs to this
+setting?" for settings like households and schools.
+
+```rust,ignore
+define_property!(struct HouseholdOf(EntityId<Household>), Person);
+
+context.index_membership::<Person, HouseholdOf>();
+context.add_entity(HouseholdOf(household));
+for person in context.members_of::<Person, HouseholdOf>(household) {
+    // ...
+}
+```
+
+A [`MembershipIndex<M, S>`] maps each setting, an `EntityId<S>`, to the members, the
+`EntityId<M>`s whose [`ForeignKey`] property refers to it. Once a property is indexed with
+[`Context::index_membership`], the context keeps the index in step with the property's values:
+creating a member, setting, unsetting, or clearing its value, and removing the member each move
+it to the right list. Unlike a query, looking up the members of a setting takes time
+proportional to the number of members, not to the population.
+
+The index mirrors the property's values, so removing a setting doesn't empty its list: members
+whose value still refers to it are still listed.
+
+*/
+
+use std::{
+    any::{Any, TypeId},
+    cell::{Ref, RefCell},
+    collections::HashMap,
+};
+
+use super::{
+    Entity, EntityId,
+    property::{ForeignKey, Property},
+    relationship_store::remove_from_list,
+};
+use crate::Context;
+
+/// The members of each setting, according to one foreign key property of the members.
+pub struct MembershipIndex<M: Entity, S: Entity> {
+    /// Reads the setting a live member refers to, or `None` if the member was removed or its
+    /// value isn't set.
+    read_setting: fn(&Context, EntityId<M>) -> Option<EntityId<S>>,
+    /// The setting each member is listed under.
+    setting_of: HashMap<EntityId<M>, EntityId<S>>,
+    /// The members of each setting, in the order they joined.
+    members: HashMap<EntityId<S>, Vec<EntityId<M>>>,
+}
+
+// The derive version of the `Clone` implementation introduces unnecessary trait bounds on `M` and `S`.
+impl<M: Entity, S: Entity> Clone for MembershipIndex<M, S> {
+    fn clone(&self) -> Self {
+        Self {
+            read_setting: self.read_setting,
+            setting_of: self.setting_of.clone(),
+            members: self.members.clone(),
+        }
+    }
+}
+
+impl<M: Entity, S: Entity> MembershipIndex<M, S> {
+    /// The members of `setting`, in the order they joined it.
+    pub fn members_of(&self, setting: EntityId<S>) -> &[EntityId<M>] {
+        self.members.get(&setting).map_or(&[], Vec::as_slice)
+    }
+
+    /// The setting `member` is listed under, or `None` if it isn't listed.
+    pub fn setting_of(&self, member: EntityId<M>) -> Option<EntityId<S>> {
+        self.setting_of.get(&member).copied()
+    }
+
+    /// The number of settings with at least one member.
+    pub fn setting_count(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Lists `member` under `setting`, or under no setting if `None`, taking it off the list it
+    /// was on.
+    fn assign(&mut self, member: EntityId<M>, setting: Option<EntityId<S>>) {
+        let previous = match setting {
+            Some(setting) => self.setting_of.insert(member, setting),
+            None => self.setting_of.remove(&member),
+        };
+        if previous == setting {
+            return;
+        }
+        if let Some(previous) = previous {
+            remove_from_list(&mut self.members, &previous, &member);
+        }
+        if let Some(setting) = setting {
+            self.members.entry(setting).or_default().push(member);
+        }
+    }
+}
+
+/// Reads the setting that the value of property `P` of a live `member` refers to.
+fn read_setting<M: Entity, P: ForeignKey<M>>(
+    context: &Context,
+    member: EntityId<M>,
+) -> Option<EntityId<P::Target>> {
+    if !context.entity_store.is_live(member) {
+        return None;
+    }
+    context
+        .try_get_property::<M, P>(member)
+        .ok()
+        .map(|value| value.target())
+}
+
+/// The type-erased interface through which a `Context` keeps its membership indexes up to date.
+pub(crate) trait AnyMembershipIndex {
+    fn as_any(&self) -> &dyn Any;
+    fn clone_boxed(&self) -> Box<dyn AnyMembershipIndex>;
+    /// The `TypeId` of the member entity type.
+    fn member_type_id(&self) -> TypeId;
+    /// Moves the member with the given index to the list of the setting its value now refers to.
+    fn refresh(&mut self, context: &Context, index: usize);
+    /// Rebuilds the index from the values of every member.
+    fn rebuild(&mut self, context: &Context);
+}
+
+impl<M: Entity, S: Entity> AnyMembershipIndex for MembershipIndex<M, S> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn clone_boxed(&self) -> Box<dyn AnyMembershipIndex> {
+        Box::new(self.clone())
+    }
+
+    fn member_type_id(&self) -> TypeId {
+        <M as Entity>::type_id()
+    }
+
+    fn refresh(&mut self, context: &Context, index: usize) {
+        let member = EntityId::new(index);
+        self.assign(member, (self.read_setting)(context, member));
+    }
+
+    fn rebuild(&mut self, context: &Context) {
+        self.setting_of.clear();
+        self.members.clear();
+        for member in context.entity_store.live_entity_ids::<M>() {
+            if let Some(setting) = (self.read_setting)(context, member) {
+                self.setting_of.insert(member, setting);
+                self.members.entry(setting).or_default().push(member);
+            }
+        }
+    }
+}
+
+impl Context {
+    /// Starts maintaining a [`MembershipIndex`] over property `P`, listing each entity of type
+    /// `M` under the entity its value of `P` refers to. Does nothing if `P` is already indexed.
+    pub fn index_membership<M: Entity, P: ForeignKey<M>>(&mut self) {
+        if self.membership_indexes.contains_key(&P::type_id()) {
+            return;
+        }
+        let mut index = MembershipIndex::<M, P::Target> {
+            read_setting: read_setting::<M, P>,
+            setting_of: HashMap::new(),
+            members: HashMap::new(),
+        };
+        index.rebuild(self);
+        self.membership_indexes
+            .insert(P::type_id(), RefCell::new(Box::new(index)));
+    }
+
+    /// The index over property `P`, or `None` if [`Context::index_membership`] wasn't called for
+    /// `P`. The index is borrowed from the context, so setting a value of `P` while holding it
+    /// panics.
+    pub fn membership_index<M: Entity, P: ForeignKey<M>>(
+        &self,
+    ) -> Option<Ref<'_, MembershipIndex<M, P::Target>>> {
+        let index = self.membership_indexes.get(&P::type_id())?;
+        Some(Ref::map(index.borrow(), |index| {
+            index
+                .as_any()
+                .downcast_ref()
+                .expect("membership index has the wrong type")
+        }))
+    }
+
+    /// The entities of type `M` whose value of property `P` refers to `setting`, in the order
+    /// they joined it. The list is a copy, so members can be moved to other settings while
+    /// iterating over it.
+    ///
+    /// Panics if [`Context::index_membership`] wasn't called for `P`.
+    pub fn members_of<M: Entity, P: ForeignKey<M>>(
+        &self,
+        setting: EntityId<P::Target>,
+    ) -> Vec<EntityId<M>> {
+        let index = self.membership_index::<M, P>().unwrap_or_else(|| {
+            panic!("property {} has no membership index; call `index_membership` first", P::name())
+        });
+        index.members_of(setting).to_vec()
+    }
+
+    /// Updates the index over property `P`, if there is one, after the value of `P` for
+    /// `entity_id` was changed.
+    pub(crate) fn refresh_membership<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>) {
+        if let Some(index) = self.membership_indexes.get(&P::type_id()) {
+            index.borrow_mut().refresh(self, entity_id.0);
+        }
+    }
+
+    /// Updates every index over a property of `E` after `entity_id` was created or removed, or
+    /// after values of its properties were changed without naming the property's type.
+    pub(crate) fn refresh_memberships<E: Entity>(&self, entity_id: EntityId<E>) {
+        for index in self.membership_indexes.values() {
+            if index.borrow().member_type_id() == <E as Entity>::type_id() {
+                index.borrow_mut().refresh(self, entity_id.0);
+            }
+        }
+    }
+
+    /// Rebuilds every index from the property values, after an operation that rewrote many of
+    /// them at once.
+    pub(crate) fn rebuild_membership_indexes(&self) {
+        for index in self.membership_indexes.values() {
+            index.borrow_mut().rebuild(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, define_entity, define_property, entity::EntityId};
+
+    define_entity!(Person);
+    define_entity!(Household);
+
+    define_property!(struct HouseholdOf(EntityId<Household>), Person);
+    define_property!(struct Age(u8), Person);
+
+    #[test]
+    fn assign_and_reassign_people_to_households() {
+        let mut context = Context::new();
+        let households: Vec<HouseholdId> = (0..3).map(|_| context.add_entity(())).collect();
+        context.index_membership::<Person, HouseholdOf>();
+
+        let ada = context.add_entity(HouseholdOf(households[0]));
+        let bo = context.add_entity(HouseholdOf(households[0]));
+        let cy = context.add_entity((Age(3),));
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[0]), [ada, bo]);
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[1]), []);
+
+        context.set_property(cy, HouseholdOf(households[1]));
+        context.set_property(ada, HouseholdOf(households[1]));
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[0]), [bo]);
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[1]), [cy, ada]);
+        {
+            let index = context.membership_index::<Person, HouseholdOf>().unwrap();
+            assert_eq!(index.setting_of(ada), Some(households[1]));
+            assert_eq!(index.setting_count(), 2);
+        }
+
+        // Setting the same household again doesn't reorder its members.
+        context.set_property(cy, HouseholdOf(households[1]));
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[1]), [cy, ada]);
+
+        context.unset_property::<_, HouseholdOf>(bo);
+        assert!(context.remove_entity(cy));
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[0]), []);
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[1]), [ada]);
+        assert_eq!(
+            context.membership_index::<Person, HouseholdOf>().unwrap().setting_of(bo),
+            None
+        );
+    }
+
+    #[test]
+    fn index_reflects_values_set_before_and_rewritten_in_bulk() {
+        let mut context = Context::new();
+        let households: Vec<HouseholdId> = (0..2).map(|_| context.add_entity(())).collect();
+        let people: Vec<PersonId> = (0..6)
+            .map(|index| context.add_entity(HouseholdOf(households[index % 2])))
+            .collect();
+        context.index_membership::<Person, HouseholdOf>();
+        assert_eq!(
+            context.members_of::<_, HouseholdOf>(households[1]),
+            [people[1], people[3], people[5]]
+        );
+
+        context.remove_entity(people[1]);
+        let remap = context.compact::<Person>();
+        assert_eq!(
+            context.members_of::<_, HouseholdOf>(households[1]),
+            [*remap.get(people[3]).unwrap(), *remap.get(people[5]).unwrap()]
+        );
+
+        context.clear_property_column::<Person, HouseholdOf>();
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[0]), []);
+        assert_eq!(context.membership_index::<Person, HouseholdOf>().unwrap().setting_count(), 0);
+    }
+
+    #[test]
+    fn move_members_while_iterating() {
+        let mut context = Context::new();
+        let households: Vec<HouseholdId> = (0..2).map(|_| context.add_entity(())).collect();
+        let people: Vec<PersonId> = (0..4)
+            .map(|_| context.add_entity(HouseholdOf(households[0])))
+            .collect();
+        context.index_membership::<Person, HouseholdOf>();
+
+        for person in context.members_of::<Person, HouseholdOf>(households[0]) {
+            context.set_property(person, HouseholdOf(households[1]));
+        }
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[0]), []);
+        assert_eq!(context.members_of::<_, HouseholdOf>(households[1]), people);
+    }
+
+    #[test]
+    #[should_panic(expected = "property HouseholdOf has no membership index")]
+    fn members_of_without_index() {
+        let mut context = Context::new();
+        let household: HouseholdId = context.add_entity(());
+        let _ = context.members_of::<Person, HouseholdOf>(household);
+    }
+}
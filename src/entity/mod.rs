@@ -33,12 +33,16 @@ static variable `index`. Each concrete `Entity` type is thus assigned a unique i
 
 mod entity;
 mod entity_impl;
+pub mod entity_builder;
+pub mod entity_keyed_map;
 pub mod entity_store;
+pub mod membership_index;
 pub mod property;
 pub mod property_impl;
 pub mod property_list;
 pub mod property_store;
 pub mod property_value_store;
+pub mod relationship_store;
 
 // Flatten the module hierarchy.
 pub use entity::*;
@@ -6,11 +6,12 @@ A `Property` is the newtype value type for properties associated to an `Entity`.
 
 use std::{any::TypeId, fmt::Debug};
 
-use serde::Serialize;
+use serde::{Serialize, de::DeserializeOwned};
 
 use crate::{
     Context,
     entity::{Entity, EntityId},
+    value_vec::OptionSlots,
 };
 
 /// The kind of initialization that a property has.
@@ -30,11 +31,50 @@ pub enum PropertyInitializationKind {
     /// The property is given a constant initial value. Its initialization does not
     /// trigger a change event.
     Constant,
+
+    /// The property's initial value is computed per entity by [`Property::default_fn`] the first
+    /// time it is read, and then stored. Like `Constant`, its initialization does not trigger a
+    /// change event.
+    DefaultFn,
+}
+
+/// Converts the values of a two-valued property to and from `bool`. A property opts in to
+/// bit-packed storage, two bits per entity, by providing one of these as [`Property::BIT_CODEC`].
+pub struct BitCodec<P> {
+    pub to_bit: fn(P) -> bool,
+    pub from_bit: fn(bool) -> P,
+}
+
+// The derive versions of `Clone` and `Copy` introduce unnecessary trait bounds on `P`.
+impl<P> Clone for BitCodec<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
+impl<P> Copy for BitCodec<P> {}
+
+/// Converts the values of a property that refers to another entity to and from the index of
+/// the entity's id, so that the values can be rewritten when entities are renumbered. Provided as
+/// [`Property::ENTITY_REFERENCE`] by the `define_property!` macro for tuple structs wrapping an
+/// `EntityId`.
+pub struct EntityReference<P> {
+    /// The `TypeId` of the entity type the values refer to.
+    pub target_type_id: fn() -> TypeId,
+    pub to_index: fn(&P) -> usize,
+    pub from_index: fn(usize) -> P,
+}
+
+// The derive versions of `Clone` and `Copy` introduce unnecessary trait bounds on `P`.
+impl<P> Clone for EntityReference<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<P> Copy for EntityReference<P> {}
 
 // A type-erased interface for properties.
-pub trait AnyProperty: Copy + Debug + PartialEq + Serialize + 'static {}
-impl<T> AnyProperty for T where T: Copy + Debug + PartialEq + Serialize + 'static {}
+pub trait AnyProperty: Clone + Debug + PartialEq + Serialize + DeserializeOwned + 'static {}
+impl<T> AnyProperty for T where T: Clone + Debug + PartialEq + Serialize + DeserializeOwned + 'static {}
 
 /// All properties must implement this trait using one of the `define_property` macros.
 pub trait Property<E: Entity>: AnyProperty {
@@ -42,6 +82,26 @@ pub trait Property<E: Entity>: AnyProperty {
     /// type of the transformed value. For simple properties this will be the same as `Self`.
     type CanonicalValue: AnyProperty;
 
+    /// The vector that stores the values of this property, one `Option<Self>` per entity, unless
+    /// they are stored bit-packed. `impl_property_with_options!` uses a `ValueVec<Option<Self>>`,
+    /// which requires `Self: Copy`, unless its `value_storage` option names another, e.g. a
+    /// [`ClonedValueVec<Self>`](crate::value_vec::ClonedValueVec) for a type that isn't `Copy`.
+    type ValueStorage: OptionSlots<Self>;
+
+    /// If `Some`, values of this property are stored bit-packed instead of one `Option<Self>`
+    /// per entity. The `define_property!` macro sets this for tuple structs wrapping a `bool`.
+    const BIT_CODEC: Option<BitCodec<Self>> = None;
+
+    /// If `Some`, the values of this property refer to entities, and are rewritten when those
+    /// entities are renumbered by [`Context::compact`] or [`Context::merge`].
+    const ENTITY_REFERENCE: Option<EntityReference<Self>> = None;
+
+    /// If `true`, a property with `DefaultFn` initialization gets its default when an entity is
+    /// created without a value for it, so the default depends on the entity's initial values
+    /// rather than on the values when it's first read. Set by the `default_from` option of
+    /// `impl_property_with_options!`.
+    const DEFAULT_AT_CREATION: bool = false;
+
     /// The kind of initialization this property has.
     #[must_use]
     fn initialization_kind() -> PropertyInitializationKind;
@@ -66,6 +126,11 @@ pub trait Property<E: Entity>: AnyProperty {
     #[must_use]
     fn default_const() -> Self;
 
+    /// Compute the initial value of a property with `DefaultFn` initialization for an entity
+    /// that doesn't have a value yet.
+    #[must_use]
+    fn default_fn(context: &Context, entity_id: EntityId<E>) -> Self;
+
     /// This transforms a `Self` into a `Self::CanonicalValue`, e.g., for storage in an index.
     /// For simple properties, this is the identity function.
     #[must_use]
@@ -83,11 +148,22 @@ pub trait Property<E: Entity>: AnyProperty {
     #[must_use]
     fn get_display(&self) -> String;
 
-    // /// For cases when the property's hash needs to be computed in a special way.
-    // #[must_use]
-    // fn hash_property_value(value: &Self::CanonicalValue) -> u128 {
-    //   hash_serialized_128(value)
-    // }
+    /// A 128-bit hash of a canonical value, e.g. for building indices or for checking that two
+    /// runs produced the same population. Equal values must have equal hashes. Defaults to
+    /// [`hash_serialized_128`](crate::hashing::hash_serialized_128); override it with the `hash_fn` option
+    /// of `impl_property_with_options!`.
+    #[must_use]
+    fn hash_value(value: &Self::CanonicalValue) -> u128 {
+        crate::hashing::hash_serialized_128(value)
+    }
+
+    /// Checks that `value` may be stored, e.g. that an entity it refers to exists, before it is
+    /// set or used to initialize an entity. Returns a description of the problem if not. Accepts
+    /// every value by default; override it with the `validate_fn` option of
+    /// `impl_property_with_options!`.
+    fn validate(context: &Context, value: &Self) -> Result<(), String> {
+        Ok(())
+    }
 
     /// Overridden by multi-properties, which use the `TypeId` of the ordered tuple so that tuples
     /// with the same component types in a different order will have the same type ID.
@@ -100,6 +176,16 @@ pub trait Property<E: Entity>: AnyProperty {
     fn index() -> usize;
 }
 
+/// A property whose value refers to an entity of type `Target`, e.g. the household a person
+/// belongs to. The `define_property!` macro implements it for a tuple struct wrapping an
+/// `EntityId<Target>`.
+pub trait ForeignKey<E: Entity>: Property<E> {
+    type Target: Entity;
+
+    /// The entity this value refers to.
+    fn target(&self) -> EntityId<Self::Target>;
+}
+
 #[cfg(feature = "disabled")]
 mod tests {
     use super::*;
@@ -40,22 +40,22 @@ You might want to implement your own property type yourself if
 
 You can implement `Property` for existing types using the `impl_property!` macro. This macro
 defines the `Property` trait implementation for you but doesn't take care of the `#[derive(..)]`
-boilerplate, so you have to remember to `derive` all of `Copy, Clone, Debug, PartialEq, Serialize`.
+boilerplate, so you have to remember to `derive` all of `Copy, Clone, Debug, PartialEq, Serialize, Deserialize`.
 
 ```rust,ignore
 define_entity!(Person);
 
 // The `define_property!` automatically adds `pub` visibility. If we want to restrict the
 // visibility of our `Property` type, we can use the `impl_property!` macro instead. The only
-// catch is, we have to remember to `derive` all of `Copy, Clone, Debug, PartialEq, Serialize`. (Note that we don't have a default value in this case.)
-#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+// catch is, we have to remember to `derive` all of `Copy, Clone, Debug, PartialEq, Serialize, Deserialize`. (Note that we don't have a default value in this case.)
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 struct Age(u8);
 impl_property!(Age, Person);
 
 // Here we derive `Default`, which also requires an attribute on one
 // of the variants. (`Property` has its own independent mechanism for
 // assigning default values for entities unrelated to the `Default` trait.)
-#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 enum InfectionStatus {
     #[default]
     Susceptible,
@@ -67,7 +67,7 @@ impl_property!(InfectionStatus, Person, default_const = InfectionStatus::Suscept
 
 // Exactly equivalent to
 //    `define_property!(struct Vaccinated(bool) = Vaccinated(false), Person);`
-#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Vaccinated(bool);
 impl_property!(Vaccinated, Person, default_const = Vaccinated(false));
 ```
@@ -104,10 +104,10 @@ you also must provide a conversion function to and from the canonical type.
 ```rust,ignore
 define_entity!(WeatherStation);
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct DegreesFahrenheit(pub f64);
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct DegreesCelsius(pub f64);
 
 // Custom canonical type
@@ -137,9 +137,9 @@ impl_property_with_options!(
 /// ```
 /// Expands to:
 /// ```rust
-/// # use ixa::{impl_property, define_entity, serde::Serialize};
+/// # use ixa::{impl_property, define_entity, serde::{Deserialize, Serialize}};
 /// # define_entity!(Person);
-/// #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+/// #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 /// pub struct Age(u8);
 /// impl_property!(Age, Person);
 /// ```
@@ -157,9 +157,9 @@ impl_property_with_options!(
 /// ```
 /// Expands to:
 /// ```rust
-/// # use ixa::{impl_property, define_entity, serde::Serialize};
+/// # use ixa::{impl_property, define_entity, serde::{Deserialize, Serialize}};
 /// # define_entity!(Person);
-/// #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+/// #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 /// pub struct Coordinates { x: i32, y: i32 }
 /// impl_property!(Coordinates, Person);
 /// ```
@@ -179,9 +179,9 @@ impl_property_with_options!(
 /// ```
 /// Expands to:
 /// ```rust
-/// # use ixa::{impl_property, define_entity, serde::Serialize};
+/// # use ixa::{impl_property, define_entity, serde::{Deserialize, Serialize}};
 /// # define_entity!(Person);
-/// #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+/// #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 /// pub enum InfectionStatus {
 ///     Susceptible,
 ///     Infectious,
@@ -193,12 +193,18 @@ impl_property_with_options!(
 /// ### Notes
 ///
 /// - The generated type always derives the following traits:
-///   `Default`, `Debug`, `PartialEq`, `Eq`, `Clone`, `Copy`, and `Serialize`.
+///   `Default`, `Debug`, `PartialEq`, `Eq`, `Hash`, `Clone`, `Copy`, `Serialize`, and `Deserialize`,
+///   except that a tuple struct wrapping a single `String`, e.g. `struct Name(String)`, is not `Copy`.
+/// - A tuple struct wrapping a single `EntityId<Target>`, e.g. `struct Home(EntityId<Household>)`,
+///   refers to another entity. Setting a value that refers to an entity that doesn't exist is an
+///   error (see [`Property::validate`](crate::entity::property::Property::validate)).
 /// - Use the optional `default_const = <default_value>` argument to define a compile-time constant
 ///   default for the property.
+/// - A tuple struct wrapping a single `bool` stores its values bit-packed (see
+///   [`Property::BIT_CODEC`](crate::entity::property::Property::BIT_CODEC)).
 /// - Trailing commas in field or variant lists are allowed.
 /// - If you need a more complex type definition (e.g., generics, attributes, or
-///   non-`Copy` fields), define the type manually and then call
+///   other non-`Copy` fields), define the type manually and then call
 ///   [`impl_property!`] or
 ///   [`impl_property_with_options!`]
 ///   directly.
@@ -210,7 +216,7 @@ macro_rules! define_property {
         $entity:ident
         $(, $($extra:tt)+),*
     ) => {
-        #[derive(Debug, PartialEq, Eq, Clone, Copy, $crate::serde::Serialize)]
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, $crate::serde::Serialize, $crate::serde::Deserialize)]
         pub struct $name(Option<$inner_ty>);
 
         // Use impl_property_with_options! to provide a custom display implementation
@@ -227,13 +233,90 @@ macro_rules! define_property {
         );
     };
 
+    // Struct (tuple) with single bool field (special case)
+    (
+        struct $name:ident ( bool ),
+        $entity:ident
+        $(, $($extra:tt)+),*
+    ) => {
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, $crate::serde::Serialize, $crate::serde::Deserialize)]
+        pub struct $name(bool);
+
+        // Store the values bit-packed.
+        $crate::impl_property_with_options!(
+            $name,
+            $entity
+            $(, $($extra)+)*
+            , bit_codec = $crate::entity::property::BitCodec {
+                to_bit: |value: $name| value.0,
+                from_bit: $name,
+            }
+        );
+    };
+
+    // Struct (tuple) with single String field (special case), which can't be `Copy`
+    (
+        struct $name:ident ( String ),
+        $entity:ident
+        $(, $($extra:tt)+),*
+    ) => {
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, $crate::serde::Serialize, $crate::serde::Deserialize)]
+        pub struct $name(String);
+
+        // A `ValueVec` requires `Copy` values.
+        $crate::impl_property_with_options!(
+            $name,
+            $entity
+            $(, $($extra)+)*
+            , value_storage = $crate::value_vec::ClonedValueVec<$name>
+        );
+    };
+
+    // Struct (tuple) with single `EntityId` field (special case), a reference to an entity of
+    // type `$target`. Setting a value that refers to an entity that doesn't exist is an error.
+    // (Write `EntityId<Household>` rather than `HouseholdId` to get this case.)
+    (
+        struct $name:ident ( EntityId<$target:ident> ),
+        $entity:ident
+        $(, $($extra:tt)+),*
+    ) => {
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, $crate::serde::Serialize, $crate::serde::Deserialize)]
+        pub struct $name($crate::entity::EntityId<$target>);
+
+        $crate::impl_property_with_options!(
+            $name,
+            $entity
+            $(, $($extra)+)*
+            , validate_fn = |context: &$crate::Context, value: &$name| {
+                if context.entity_store.is_live(value.0) {
+                    Ok(())
+                } else {
+                    Err(format!("{} does not exist", value.0))
+                }
+            }
+            , entity_reference = $crate::entity::property::EntityReference {
+                target_type_id: <$target as $crate::entity::Entity>::type_id,
+                to_index: |value: &$name| value.0.index(),
+                from_index: |index| $name($crate::entity::EntityId::new(index)),
+            }
+        );
+
+        impl $crate::entity::property::ForeignKey<$entity> for $name {
+            type Target = $target;
+
+            fn target(&self) -> $crate::entity::EntityId<$target> {
+                self.0
+            }
+        }
+    };
+
     // Struct (tuple)
     (
         struct $name:ident ( $($field_ty:ty),* $(,)? ),
         $entity:ident
         $(, $($extra:tt)+),*
     ) => {
-        #[derive(Debug, PartialEq, Eq, Clone, Copy, $crate::serde::Serialize)]
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, $crate::serde::Serialize, $crate::serde::Deserialize)]
         pub struct $name($($field_ty),*);
         $crate::impl_property!($name, $entity $(, $($extra)+)*);
     };
@@ -244,7 +327,7 @@ macro_rules! define_property {
         $entity:ident
         $(, $($extra:tt)+),*
     ) => {
-        #[derive(Debug, PartialEq, Eq, Clone, Copy, $crate::serde::Serialize)]
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, $crate::serde::Serialize, $crate::serde::Deserialize)]
         pub struct $name { $($field_name : $field_ty),* }
         $crate::impl_property!($name, $entity $(, $($extra)+)*);
     };
@@ -257,7 +340,7 @@ macro_rules! define_property {
         $entity:ident
         $(, $($extra:tt)+),*
     ) => {
-        #[derive(Debug, PartialEq, Eq, Clone, Copy, $crate::serde::Serialize)]
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, $crate::serde::Serialize, $crate::serde::Deserialize)]
         pub enum $name {
             $($variant),*
         }
@@ -266,6 +349,66 @@ macro_rules! define_property {
 }
 pub use define_property;
 
+/// Defines several properties of the same entity at once. Each `;`-terminated entry is a
+/// [`define_property!`] declaration without the entity, followed by any of its options (in the
+/// order [`impl_property_with_options!`] expects them).
+///
+/// ```rust
+/// # use ixa::{define_entity, define_properties};
+/// # define_entity!(Person);
+/// define_properties!(Person {
+///     struct Age(u8), is_required = true;
+///     struct Vaccinated(bool), default_const = Vaccinated(false);
+///     enum InfectionStatus {
+///         Susceptible,
+///         Infectious,
+///         Recovered,
+///     }, default_const = InfectionStatus::Susceptible;
+/// });
+/// ```
+#[macro_export]
+macro_rules! define_properties {
+    (
+        $entity:ident {
+            $(
+                $kind:ident $name:ident $body:tt
+                $(, initialization_kind = $initialization_kind:expr)?
+                $(, is_required = $is_required:expr)?
+                $(, compute_derived_fn = $compute_derived_fn:expr)?
+                $(, default_const = $default_const:expr)?
+                $(, default_fn = $default_fn:expr)?
+                $(, default_from = $default_from:expr)?
+                $(, display_impl = $display_impl:expr)?
+                $(, canonical_value = $canonical_value:ty)?
+                $(, make_canonical = $make_canonical:expr)?
+                $(, make_uncanonical = $make_uncanonical:expr)?
+                $(, bit_codec = $bit_codec:expr)?
+                $(, hash_fn = $hash_fn:expr)?
+            );+ $(;)?
+        }
+    ) => {
+        $(
+            $crate::define_property!(
+                $kind $name $body,
+                $entity
+                $(, initialization_kind = $initialization_kind)?
+                $(, is_required = $is_required)?
+                $(, compute_derived_fn = $compute_derived_fn)?
+                $(, default_const = $default_const)?
+                $(, default_fn = $default_fn)?
+                $(, default_from = $default_from)?
+                $(, display_impl = $display_impl)?
+                $(, canonical_value = $canonical_value)?
+                $(, make_canonical = $make_canonical)?
+                $(, make_uncanonical = $make_uncanonical)?
+                $(, bit_codec = $bit_codec)?
+                $(, hash_fn = $hash_fn)?
+            );
+        )+
+    };
+}
+pub use define_properties;
+
 /// Defines a property with the following parameters:
 /// * `$property`: A name for the identifier type of the property
 /// * `$entity`: The entity type this property is associated with
@@ -291,10 +434,38 @@ pub use impl_property;
 ///   - `is_required = <bool>` — Whether new entities must explicitly set this property; defaults to `false`.
 ///   - `compute_derived_fn = <expr>` — Function used to compute derived properties; defaults to `None`.
 ///   - `default_const = <expr>` — Constant default value if the property has one; defaults to `None`.
+///   - `default_fn = <expr>` — Function computing a per-entity default from `(&Context, EntityId)` the first time the
+///     value is read; implies `PropertyInitializationKind::DefaultFn`.
+///   - `default_from = <expr>` — Like `default_fn`, but the default is computed when the entity is created, from the
+///     values in its property list, unless the list sets the property. Use one or the other.
 ///   - `display_impl = <expr>` — Function converting the canonical value to a string; defaults to `|v| format!("{v:?}")`.
 ///   - `canonical_value = <type>` — If the type stored in the index differs from the property's value type.
-///   - `make_canonical = <expr>` — Function converting from `Self` to `CanonicalValue`; defaults to `|s: &Self| *s`.
-///   - `make_uncanonical = <expr>` — Function converting from `CanonicalValue` to `Self`; defaults to `|v| v`.
+///   - `make_canonical = <expr>` — Function converting from `Self` to `CanonicalValue`; defaults to the identity.
+///   - `make_uncanonical = <expr>` — Function converting from `CanonicalValue` to `Self`; defaults to the identity.
+///   - `bit_codec = <expr>` — A `BitCodec<Self>` to store the values bit-packed; defaults to unpacked storage.
+///   - `hash_fn = <expr>` — Function hashing a `&CanonicalValue` to a `u128`; defaults to [`hash_serialized_128`](crate::hashing::hash_serialized_128).
+///   - `validate_fn = <expr>` — Function checking a value from `(&Context, &Self)` before it is stored; defaults to accepting every value.
+///   - `value_storage = <type>` — The [`OptionSlots`](crate::value_vec::OptionSlots) vector storing the values; defaults to a `ValueVec<Option<$property>>`, which requires a `Copy` type. Use a [`ClonedValueVec`](crate::value_vec::ClonedValueVec) for a type that isn't `Copy`, or an [`OptionValueVec`](crate::value_vec::OptionValueVec) to keep which values are set in a bitset.
+///   - `entity_reference = <expr>` — An [`EntityReference<Self>`](crate::entity::property::EntityReference) if the values refer to entities; defaults to `None`.
+///
+/// A derived property has no stored value for a new entity to supply, so it can't be required.
+/// Combining `initialization_kind = PropertyInitializationKind::Derived` with `is_required = true`
+/// fails to compile:
+///
+/// ```rust,compile_fail
+/// # use ixa_entities::{Context, define_entity, impl_property_with_options, entity::property::PropertyInitializationKind};
+/// # define_entity!(Person);
+/// #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, ixa_entities::serde::Serialize, ixa_entities::serde::Deserialize)]
+/// struct IsAdult(bool);
+/// impl_property_with_options!(
+///     IsAdult,
+///     Person,
+///     initialization_kind = PropertyInitializationKind::Derived,
+///     is_required = true,
+///     compute_derived_fn = |_context: &Context, _entity_id| IsAdult(true)
+/// );
+/// # fn main() {}
+/// ```
 #[macro_export]
 macro_rules! impl_property_with_options {
     (
@@ -304,29 +475,51 @@ macro_rules! impl_property_with_options {
         $(, is_required = $is_required:expr)?
         $(, compute_derived_fn = $compute_derived_fn:expr)?
         $(, default_const = $default_const:expr)?
+        $(, default_fn = $default_fn:expr)?
+        $(, default_from = $default_from:expr)?
         $(, display_impl = $display_impl:expr)?
         $(, canonical_value = $canonical_value:ty)?
         $(, make_canonical = $make_canonical:expr)?
         $(, make_uncanonical = $make_uncanonical:expr)?
+        $(, bit_codec = $bit_codec:expr)?
+        $(, hash_fn = $hash_fn:expr)?
+        $(, validate_fn = $validate_fn:expr)?
+        $(, value_storage = $value_storage:ty)?
+        $(, entity_reference = $entity_reference:expr)?
     ) => {
         $crate::__impl_property_common!(
             $property,
             $entity,
             $crate::impl_property_with_options!(@unwrap_or_ty $($canonical_value)?, $property),
 
-            // If `initialization_kind` is not specified, use `Constant` if a constant is given, or `Explicit` otherwise.
+            // If `initialization_kind` is not specified, use `DefaultFn` if a default function is given,
+            // `Constant` if a constant is given, or `Explicit` otherwise.
             // (The `Derived` case always supplies an explicit `$initialization_kind` parameter.)
             $crate::impl_property_with_options!(@unwrap_or
                 $($initialization_kind)?,
-                $crate::impl_property_with_options!(@unwrap_or_default_kind $($default_const)?)
+                $crate::impl_property_with_options!(@unwrap_or
+                    $($crate::impl_property_with_options!(@default_fn_kind $default_fn))?
+                    $($crate::impl_property_with_options!(@default_fn_kind $default_from))?,
+                    $crate::impl_property_with_options!(@unwrap_or_default_kind $($default_const)?)
+                )
             ),
 
             $crate::impl_property_with_options!(@unwrap_or $($is_required)?, false),
             $crate::impl_property_with_options!(@unwrap_or $($compute_derived_fn)?, |_, _| panic!("property {} is not derived", stringify!($property)) ),
             $crate::impl_property_with_options!(@unwrap_or $($default_const)?, panic!("property {} has no default value", stringify!($property))),
+            $crate::impl_property_with_options!(@unwrap_or $($default_fn)? $($default_from)?, |_, _| panic!("property {} has no default function", stringify!($property))),
+            $crate::impl_property_with_options!(@unwrap_or $($crate::impl_property_with_options!(@true $default_from))?, false),
             $crate::impl_property_with_options!(@unwrap_or $($display_impl)?, |v| format!("{v:?}")),
             $crate::impl_property_with_options!(@unwrap_or $($make_canonical)?, std::convert::identity),
-            $crate::impl_property_with_options!(@unwrap_or $($make_uncanonical)?, std::convert::identity)
+            $crate::impl_property_with_options!(@unwrap_or $($make_uncanonical)?, std::convert::identity),
+            $crate::impl_property_with_options!(@unwrap_or $(Some($bit_codec))?, None),
+            $crate::impl_property_with_options!(@unwrap_or $($hash_fn)?, $crate::hashing::hash_serialized_128),
+            $crate::impl_property_with_options!(@unwrap_or $($validate_fn)?, |_, _| Ok(())),
+            $crate::impl_property_with_options!(@unwrap_or_ty
+                $($value_storage)?,
+                $crate::value_vec::ValueVec<Option<$property>>
+            ),
+            $crate::impl_property_with_options!(@unwrap_or $(Some($entity_reference))?, None)
         );
     };
 
@@ -346,6 +539,14 @@ macro_rules! impl_property_with_options {
     (@unwrap_or_default_kind) => {
         $crate::entity::property::PropertyInitializationKind::Explicit
     };
+    // Likewise, if `default_fn` is present, use `DefaultFn`.
+    (@default_fn_kind $expr:expr) => {
+        $crate::entity::property::PropertyInitializationKind::DefaultFn
+    };
+    // Ignores `$expr`, to test whether an option is present.
+    (@true $expr:expr) => {
+        true
+    };
 }
 pub use impl_property_with_options;
 
@@ -366,10 +567,20 @@ pub use impl_property_with_options;
 /// * `$compute_derived_fn` — A function or closure used to compute the property’s
 ///   value if it is derived from other properties.
 /// * `$default_const` — The constant default value if the property has one.
+/// * `$default_fn` — A function computing the default value for an entity if the property has
+///   `DefaultFn` initialization.
+/// * `$default_at_creation` — Whether the `DefaultFn` default is computed when the entity is created
+///   rather than when the value is first read.
 /// * `$display_impl` — A function that takes a canonical value and returns a
 ///   string representation of the property.
 /// * `$make_canonical` — A function that takes a `Self` and converts it to a `Self::CanonicalValue`.
 /// * `$make_uncanonical` — A function that takes a `Self::CanonicalValue` and converts it to a `Self`.
+/// * `$bit_codec` — An `Option<BitCodec<Self>>`, `Some` if the values are stored bit-packed.
+/// * `$hash_fn` — A function that takes a `&Self::CanonicalValue` and returns a `u128` hash.
+/// * `$validate_fn` — A function that takes a `&Context` and a `&Self` and returns
+///   `Err(message)` if the value may not be stored.
+/// * `$value_storage` — The type of the vector storing the values, an `OptionSlots<Self>`.
+/// * `$entity_reference` — An `Option<EntityReference<Self>>`, `Some` if the values refer to entities.
 #[macro_export]
 macro_rules! __impl_property_common {
     (
@@ -380,13 +591,39 @@ macro_rules! __impl_property_common {
         $is_required:expr,         // Do we require that new entities have this property explicitly set?
         $compute_derived_fn:expr,  // If the property is derived, the function that computes the value
         $default_const:expr,       // If the property has a constant default initial value, the default value
+        $default_fn:expr,          // If the property has a per-entity default, the function that computes it
+        $default_at_creation:expr, // Is the per-entity default computed when the entity is created?
         $display_impl:expr,         // A function that takes a canonical value and returns a string representation of this property
         $make_canonical:expr,      // A function that takes a value and returns a canonical value
-        $make_uncanonical:expr     // A function that takes a canonical value and returns a value
+        $make_uncanonical:expr,    // A function that takes a canonical value and returns a value
+        $bit_codec:expr,           // If the values are stored bit-packed, how to convert them to and from `bool`
+        $hash_fn:expr,             // A function that takes a canonical value and returns a `u128` hash
+        $validate_fn:expr,         // A function that checks a value before it is stored
+        $value_storage:ty,         // The vector storing the values
+        $entity_reference:expr     // If the values refer to entities, how to renumber them
     ) => {
+        // A derived property can't be required: it has no stored value to supply at creation.
+        const _: () = assert!(
+            !($is_required
+                && matches!(
+                    $initialization_kind,
+                    $crate::entity::property::PropertyInitializationKind::Derived
+                )),
+            concat!("property ", stringify!($property), " is derived, so it can't be required")
+        );
+
         impl $crate::entity::property::Property<$entity> for $property {
             type CanonicalValue = $canonical_value;
 
+            type ValueStorage = $value_storage;
+
+            const BIT_CODEC: Option<$crate::entity::property::BitCodec<Self>> = $bit_codec;
+
+            const DEFAULT_AT_CREATION: bool = $default_at_creation;
+
+            const ENTITY_REFERENCE: Option<$crate::entity::property::EntityReference<Self>> =
+                $entity_reference;
+
             fn initialization_kind() -> $crate::entity::property::PropertyInitializationKind {
                 $initialization_kind
             }
@@ -398,7 +635,7 @@ macro_rules! __impl_property_common {
             fn compute_derived(
                 _context: &$crate::Context,
                 _entity_id: $crate::entity::EntityId<$entity>,
-            ) -> Self::CanonicalValue {
+            ) -> Self {
                 $compute_derived_fn(_context, _entity_id)
             }
 
@@ -406,6 +643,13 @@ macro_rules! __impl_property_common {
                 $default_const
             }
 
+            fn default_fn(
+                _context: &$crate::Context,
+                _entity_id: $crate::entity::EntityId<$entity>,
+            ) -> Self {
+                $default_fn(_context, _entity_id)
+            }
+
             fn make_canonical(self) -> Self::CanonicalValue {
                 $make_canonical(self)
             }
@@ -422,24 +666,63 @@ macro_rules! __impl_property_common {
                 $display_impl(self)
             }
 
-            fn index() -> usize {
-                // This static must be initialized with a compile-time constant expression.
-                // We use `usize::MAX` as a sentinel to mean "uninitialized". This
-                // static variable is shared among all instances of this concrete item type.
-                static INDEX: std::sync::atomic::AtomicUsize =
-                    std::sync::atomic::AtomicUsize::new(usize::MAX);
-
-                // Fast path: already initialized.
-                let index = INDEX.load(std::sync::atomic::Ordering::Relaxed);
-                if index != usize::MAX {
-                    return index;
-                }
+            fn hash_value(value: &Self::CanonicalValue) -> u128 {
+                $hash_fn(value)
+            }
 
-                // Slow path: initialize it.
+            fn validate(context: &$crate::Context, value: &Self) -> Result<(), String> {
+                $validate_fn(context, value)
+            }
+
+            fn index() -> usize {
+                // This static is shared among all instances of this concrete item type. Once it
+                // is initialized, reading it is a single atomic load.
+                static INDEX: std::sync::OnceLock<usize> = std::sync::OnceLock::new();
                 $crate::entity::property_store::initialize_property_index(&INDEX)
             }
         }
 
+        // A single property is a property list of length 1, so `context.add_entity(Age(25))` works
+        // without the trailing comma of a 1-tuple. (See the note in `property_list.rs` on why this
+        // can't be a blanket impl.)
+        impl $crate::entity::property_list::PropertyList<$entity> for $property {
+            fn validate() -> Result<(), String> {
+                Ok(())
+            }
+            fn contains_properties(property_type_ids: &[std::any::TypeId]) -> bool {
+                <($property,) as $crate::entity::property_list::PropertyList<$entity>>::contains_properties(
+                    property_type_ids,
+                )
+            }
+            fn validate_values(
+                &self,
+                context: &$crate::Context,
+            ) -> Result<(), $crate::InvalidPropertyValueError> {
+                $crate::InvalidPropertyValueError::check::<$entity, Self>(context, self)
+            }
+            fn set_values_for_entity(
+                &self,
+                entity_id: $crate::entity::EntityId<$entity>,
+                property_store: &$crate::entity::property_store::PropertyStore,
+            ) {
+                property_store
+                    .get::<$entity, $property>()
+                    .set(entity_id, self.clone());
+            }
+            fn add_to_query(
+                self,
+                query: $crate::query::Query<'_, $entity>,
+            ) -> $crate::query::Query<'_, $entity> {
+                query.matching(self)
+            }
+            fn get_values(
+                context: &$crate::Context,
+                entity_id: $crate::entity::EntityId<$entity>,
+            ) -> Result<Self, $crate::PropertyNotSetError> {
+                context.try_get_property::<$entity, $property>(entity_id)
+            }
+        }
+
         // Using `ctor` to initialize properties at program start-up means we know how many properties
         // there are at the time any `PropertyStore` is created, which means we never have
         // to mutate `PropertyStore` to initialize a `Property` that hasn't yet been accessed.
@@ -702,3 +985,230 @@ macro_rules! define_multi_property {
 }
 pub use define_multi_property;
 */
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use crate::{
+        AddEntityError, Context, InvalidPropertyValueError, SetPropertyByNameError, define_entity,
+        define_properties, define_property,
+        entity::{Entity, EntityId, property::Property},
+        impl_property_with_options,
+    };
+
+    define_entity!(Person);
+    define_entity!(Household);
+    define_entity!(Member);
+
+    define_property!(struct Membership(EntityId<Household>), Member);
+    define_property!(struct Mentor(EntityId<Member>), Member);
+
+    define_entity!(Patient);
+
+    define_properties!(Patient {
+        struct Years(u8);
+        enum RiskTier {
+            Low,
+            High,
+        }, default_from = |context: &Context, patient: PatientId| {
+            let years: Years = context.get_property(patient);
+            if years.0 >= 65 { RiskTier::High } else { RiskTier::Low }
+        };
+    });
+
+    // Stored as a bitset of the set slots next to the bare `u8`s.
+    #[derive(Debug, Default, PartialEq, Eq, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct Severity(u8);
+    impl_property_with_options!(
+        Severity,
+        Patient,
+        value_storage = crate::value_vec::OptionValueVec<Severity>
+    );
+
+    define_properties!(Person {
+        struct Age(u8), is_required = true;
+        struct Vaccinated(bool), default_const = Vaccinated(false);
+        struct Name(String);
+        enum InfectionStatus {
+            Susceptible,
+            Infectious,
+            Recovered,
+        }, default_const = InfectionStatus::Susceptible;
+    });
+
+    #[test]
+    fn define_properties_forwards_options() {
+        assert!(Age::is_required());
+        assert!(!Name::is_required());
+        assert_eq!(Vaccinated::default_const(), Vaccinated(false));
+        assert_eq!(
+            InfectionStatus::default_const(),
+            InfectionStatus::Susceptible
+        );
+        // The `bool` special case of `define_property!` still applies.
+        assert!(Vaccinated::BIT_CODEC.is_some());
+        assert_eq!(Person::property_ids().len(), 4);
+        assert!(Person::property_ids().contains(&TypeId::of::<Name>()));
+
+        let mut context = Context::new();
+        let person: PersonId = context.add_entity((Age(30), Name("Ada".to_string())));
+        assert_eq!(context.get_property::<Person, Age>(person), Age(30));
+        assert_eq!(
+            context.get_property::<Person, InfectionStatus>(person),
+            InfectionStatus::Susceptible
+        );
+    }
+
+    #[test]
+    fn values_stored_in_an_option_value_vec() {
+        let mut context = Context::new();
+        let patients: Vec<PatientId> = (0..100).map(|years| context.add_entity(Years(years))).collect();
+        for patient in patients.iter().step_by(3) {
+            context.set_property(*patient, Severity(patient.index() as u8));
+        }
+        context.unset_property::<_, Severity>(patients[3]);
+        assert!(context.remove_entity(patients[6]));
+
+        let store = context.property_store.get::<Patient, Severity>();
+        // One byte per slot, plus a bit.
+        assert!(store.heap_bytes() < 2 * patients.len());
+        assert_eq!(store.get(patients[9]), Some(Severity(9)));
+        assert_eq!(store.get(patients[10]), None);
+
+        let remap = context.compact::<Patient>();
+        let severity = |index: usize| {
+            let patient = *remap.get(patients[index]).unwrap();
+            context.try_get_property::<_, Severity>(patient).ok()
+        };
+        assert_eq!(severity(0), Some(Severity(0)));
+        assert_eq!(severity(3), None);
+        assert_eq!(severity(9), Some(Severity(9)));
+        assert_eq!(severity(99), Some(Severity(99)));
+        assert_eq!(severity(98), None);
+        assert_eq!(context.query::<Patient>().filter::<Severity>(|_| true).count(), 32);
+    }
+
+    #[test]
+    fn entity_reference_must_exist() {
+        let mut context = Context::new();
+        let household: HouseholdId = context.add_entity(());
+        let member: MemberId = context.add_entity(Membership(household));
+        assert_eq!(context.get_property::<Member, Membership>(member), Membership(household));
+
+        let dangling = Membership(HouseholdId::new(7));
+        assert_eq!(
+            context.try_set_property(member, dangling),
+            Err(InvalidPropertyValueError {
+                property_name: "Membership",
+                message: "Household#7 does not exist".to_string(),
+            })
+        );
+        assert_eq!(context.get_property::<Member, Membership>(member), Membership(household));
+        assert!(matches!(
+            context.try_add_entity::<Member, _>(dangling),
+            Err(AddEntityError::InvalidValue(_))
+        ));
+        assert_eq!(context.get_entity_count::<Member>(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value for property Membership: Household#0 does not exist")]
+    fn set_property_rejects_dangling_reference() {
+        let mut context = Context::new();
+        let member: MemberId = context.add_entity(());
+        context.set_property(member, Membership(HouseholdId::new(0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value for property Membership: Household#3 does not exist")]
+    fn modify_property_rejects_dangling_reference() {
+        let mut context = Context::new();
+        let household: HouseholdId = context.add_entity(());
+        let member: MemberId = context.add_entity(Membership(household));
+        context.modify_property(member, |membership: &mut Membership| {
+            membership.0 = HouseholdId::new(3);
+        });
+    }
+
+    #[test]
+    fn set_property_by_name_rejects_dangling_reference() {
+        let mut context = Context::new();
+        let household: HouseholdId = context.add_entity(());
+        let member: MemberId = context.add_entity(Membership(household));
+        assert!(matches!(
+            context.set_property_by_name(member, "Membership", "5"),
+            Err(SetPropertyByNameError::InvalidValue(_))
+        ));
+        assert_eq!(context.get_property::<Member, Membership>(member), Membership(household));
+    }
+
+    #[test]
+    fn compact_renumbers_references() {
+        let mut context = Context::new();
+        let households: Vec<HouseholdId> = (0..3).map(|_| context.add_entity(())).collect();
+        let first: MemberId = context.add_entity(Membership(households[0]));
+        let last: MemberId = context.add_entity(Membership(households[2]));
+        context.remove_entity(households[1]);
+        context.remove_entity(households[0]);
+
+        context.compact::<Household>();
+        assert!(!context.is_property_set::<Member, Membership>(first));
+        assert_eq!(
+            context.get_property::<Member, Membership>(last),
+            Membership(HouseholdId::new(0))
+        );
+
+        // A reference to the entity's own type is renumbered along with the entities.
+        let mentee: MemberId = context.add_entity(Mentor(last));
+        context.remove_entity(first);
+        let remap = context.compact::<Member>();
+        let mentee = *remap.get(mentee).unwrap();
+        assert_eq!(context.get_property::<Member, Mentor>(mentee), Mentor(*remap.get(last).unwrap()));
+    }
+
+    #[test]
+    fn merge_shifts_references_to_merged_entities() {
+        let mut context = Context::new();
+        context.add_entity::<Member, _>(());
+        let mut other = Context::new();
+        let mentor: MemberId = other.add_entity(());
+        let mentee: MemberId = other.add_entity(Mentor(mentor));
+
+        let remap = context.merge::<Member>(&other);
+        assert_eq!(
+            context.get_property::<Member, Mentor>(*remap.get(mentee).unwrap()),
+            Mentor(*remap.get(mentor).unwrap())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot merge property Membership of Member")]
+    fn merge_rejects_references_to_other_entities() {
+        let mut context = Context::new();
+        let mut other = Context::new();
+        let household: HouseholdId = other.add_entity(());
+        other.add_entity::<Member, _>(Membership(household));
+        context.merge::<Member>(&other);
+    }
+
+    #[test]
+    fn default_from_is_computed_at_creation() {
+        let mut context = Context::new();
+        let patient: PatientId = context.add_entity(Years(70));
+        // The default is stored when the patient is created, not when it's first read.
+        assert!(context.property_store.get::<Patient, RiskTier>().is_set(patient));
+        context.set_property(patient, Years(40));
+        assert_eq!(context.get_property::<Patient, RiskTier>(patient), RiskTier::High);
+
+        // The stored default can be overridden, like any other value.
+        context.set_property(patient, RiskTier::Low);
+        assert_eq!(context.get_property::<Patient, RiskTier>(patient), RiskTier::Low);
+
+        // A value in the property list takes precedence over the default.
+        let other: PatientId = context.add_entity((Years(30), RiskTier::High));
+        assert_eq!(context.get_property::<Patient, RiskTier>(other), RiskTier::High);
+        let young: PatientId = context.add_entity(Years(30));
+        assert_eq!(context.get_property::<Patient, RiskTier>(young), RiskTier::Low);
+    }
+}
@@ -23,6 +23,8 @@ use std::any::TypeId;
 
 use seq_macro::seq;
 
+use crate::{Context, InvalidPropertyValueError, PropertyNotSetError, query::Query};
+
 use super::{
     entity::{
         Entity,
@@ -32,20 +34,41 @@ use super::{
     property_store::PropertyStore
 };
 
-pub trait PropertyList<E: Entity>: Copy + 'static {
+pub trait PropertyList<E: Entity>: Clone + 'static {
     /// Validates that the properties are distinct. If not, returns a string describing the problematic properties.
     fn validate() -> Result<(), String>;
 
     /// Checks that this property list includes all properties in the given list.
     fn contains_properties(property_type_ids: &[TypeId]) -> bool;
 
-    /// Checks that this property list contains all required properties of the entity.
-    fn contains_required_properties() -> bool {
-        Self::contains_properties(E::required_property_ids())
+    /// Checks that this property list contains all required properties of the entity. If not,
+    /// returns the `TypeId`s of the missing required properties.
+    fn contains_required_properties() -> Result<(), Vec<TypeId>> {
+        let missing: Vec<TypeId> = E::required_property_ids()
+            .iter()
+            .copied()
+            .filter(|id| !Self::contains_properties(&[*id]))
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing)
+        }
     }
 
+    /// Checks each value in `self` with [`Property::validate`].
+    fn validate_values(&self, context: &Context) -> Result<(), InvalidPropertyValueError>;
+
     /// Assigns the given entity the property values in `self` in the `property_store`.
     fn set_values_for_entity(&self, entity_id: EntityId<E>, property_store: &PropertyStore);
+
+    /// Adds a [`Query::matching`] constraint to `query` for each value in `self`.
+    fn add_to_query(self, query: Query<'_, E>) -> Query<'_, E>;
+
+    /// Reads the value of each property in the list for the given entity with
+    /// [`Context::try_get_property`], failing on the first property that isn't set.
+    fn get_values(context: &Context, entity_id: EntityId<E>) -> Result<Self, PropertyNotSetError>;
 }
 
 // The empty tuple is an empty `PropertyList<E>` for every `E: Entity`.
@@ -56,18 +79,24 @@ impl<E: Entity> PropertyList<E> for () {
     fn contains_properties(property_type_ids: &[TypeId]) -> bool {
         property_type_ids.is_empty()
     }
+    fn validate_values(&self, _context: &Context) -> Result<(), InvalidPropertyValueError> {
+        Ok(())
+    }
     fn set_values_for_entity(&self, _entity_id: EntityId<E>, _property_store: &PropertyStore){
         // No values to assign.
     }
+    fn add_to_query(self, query: Query<'_, E>) -> Query<'_, E> {
+        query
+    }
+    fn get_values(_context: &Context, _entity_id: EntityId<E>) -> Result<Self, PropertyNotSetError> {
+        Ok(())
+    }
 }
 
-// ToDo: Why does the following trigger a "conflicting implementation" error?
-// A single `Property` is a `PropertyList` of length 1
-// impl<E: Entity, P: Property<E>> PropertyList<E> for P {
-//     fn validate() -> Result<(), String> {
-//         Ok(())
-//     }
-// }
+// A single `Property` is also a `PropertyList` of length 1, but that can't be a blanket
+// `impl<E: Entity, P: Property<E>> PropertyList<E> for P`: a downstream crate may implement
+// `Property<E>` for a tuple of its own types, which would then have two impls. Instead,
+// `impl_property!` implements `PropertyList<E>` for each property type it defines.
 
 // A single `Property` tuple is a `PropertyList` of length 1
 impl<E: Entity, P: Property<E>> PropertyList<E> for (P,) {
@@ -77,9 +106,18 @@ impl<E: Entity, P: Property<E>> PropertyList<E> for (P,) {
     fn contains_properties(property_type_ids: &[TypeId]) -> bool {
         property_type_ids.len() == 1 && property_type_ids[0] == P::type_id()
     }
+    fn validate_values(&self, context: &Context) -> Result<(), InvalidPropertyValueError> {
+        InvalidPropertyValueError::check(context, &self.0)
+    }
     fn set_values_for_entity(&self, entity_id: EntityId<E>, property_store: &PropertyStore){
         let property_value_store = property_store.get::<E, P>();
-        property_value_store.set(entity_id, self.0);
+        property_value_store.set(entity_id, self.0.clone());
+    }
+    fn add_to_query(self, query: Query<'_, E>) -> Query<'_, E> {
+        query.matching(self.0)
+    }
+    fn get_values(context: &Context, entity_id: EntityId<E>) -> Result<Self, PropertyNotSetError> {
+        Ok((context.try_get_property::<E, P>(entity_id)?,))
     }
 }
 
@@ -113,20 +151,89 @@ macro_rules! impl_property_list {
                     property_type_ids.len() <= $ct && property_type_ids.iter().all(|id| self_property_type_ids.contains(id))
                 }
 
+                fn validate_values(&self, context: &Context) -> Result<(), InvalidPropertyValueError> {
+                    #(InvalidPropertyValueError::check(context, &self.N)?;)*
+                    Ok(())
+                }
+
                 fn set_values_for_entity(&self, entity_id: EntityId<E>, property_store: &PropertyStore){
                     #({
                         let property_value_store = property_store.get::<E, P~N>();
                         // The compiler isn't smart enough to know that `entity_id` is `Copy` when this is
                         // borrow-checked, so we clone it.
-                        property_value_store.set(entity_id.clone(), self.N);
+                        property_value_store.set(entity_id.clone(), self.N.clone());
                     })*
                 }
+
+                fn add_to_query(self, query: Query<'_, E>) -> Query<'_, E> {
+                    query #(.matching(self.N))*
+                }
+
+                fn get_values(context: &Context, entity_id: EntityId<E>) -> Result<Self, PropertyNotSetError> {
+                    Ok((#(context.try_get_property::<E, P~N>(entity_id)?, )*))
+                }
             }
         });
     };
 }
 
-// Generate impls for tuple lengths 2 through 10.
-seq!(Z in 2..=5 {
+// Generate impls for tuple lengths 2 through 12.
+seq!(Z in 2..=12 {
     impl_property_list!(Z);
 });
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use super::PropertyList;
+    use crate::{Context, define_entity, define_properties};
+
+    define_entity!(Person);
+
+    define_properties!(Person {
+        struct P0(u8);
+        struct P1(u8);
+        struct P2(u8);
+        struct P3(u8);
+        struct P4(u8);
+        struct P5(u8);
+        struct P6(u8);
+        struct P7(u8);
+        struct P8(u8);
+        struct P9(u8);
+    });
+
+    #[test]
+    fn add_entity_with_a_bare_property() {
+        let mut context = Context::new();
+        let person = context.add_entity(P0(25));
+        assert_eq!(context.get_property::<Person, P0>(person), P0(25));
+        assert!(<P0 as PropertyList<Person>>::contains_properties(&[TypeId::of::<P0>()]));
+        assert!(!<P0 as PropertyList<Person>>::contains_properties(&[TypeId::of::<P1>()]));
+    }
+
+    #[test]
+    fn add_entity_with_ten_properties() {
+        let mut context = Context::new();
+        let person = context.add_entity((
+            P0(0),
+            P1(1),
+            P2(2),
+            P3(3),
+            P4(4),
+            P5(5),
+            P6(6),
+            P7(7),
+            P8(8),
+            P9(9),
+        ));
+        assert_eq!(context.get_property::<Person, P0>(person), P0(0));
+        assert_eq!(context.get_property::<Person, P9>(person), P9(9));
+        assert!(
+            context
+                .try_add_entity::<Person, _>((P0(0), P1(1), P2(2), P3(3), P4(4), P5(5), P0(6)))
+                .is_err()
+        );
+    }
+}
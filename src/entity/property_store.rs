@@ -7,24 +7,118 @@ types and their value stores, we implement the `index` method for each property
 
 */
 use std::{
-    any::Any,
+    any::{Any, TypeId},
     cell::OnceCell,
+    collections::HashMap,
     sync::{
-        Mutex,
+        LazyLock, Mutex, OnceLock,
         atomic::{AtomicUsize, Ordering},
     },
 };
 
 use super::{
-    entity::Entity,
+    entity::{Entity, EntityId},
     entity_store::register_property_with_entity,
-    property::Property,
+    property::{Property, PropertyInitializationKind},
     property_value_store::PropertyValueStore,
 };
+use crate::{Context, SetPropertyByNameError, value_vec::GrowthStrategy};
 
 /// Global item index counter; keeps track of the index that will be assigned to the next entity that
 /// requests an index. Equivalently, holds a *count* of the number of entities currently registered.
-static NEXT_PROPERTY_INDEX: Mutex<usize> = Mutex::new(0);
+static NEXT_PROPERTY_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+/// A description of a property of an entity, for code that enumerates an entity's properties at
+/// runtime (see [`Context::properties_of`]).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PropertyMeta {
+    pub name: &'static str,
+    pub type_id: TypeId,
+    pub is_required: bool,
+    pub kind: PropertyInitializationKind,
+}
+
+/// What the registry knows about a property without knowing its concrete type.
+///
+/// The function pointers are monomorphized for the concrete `(E, P)` pair when the property is
+/// registered, which lets code that only has a `TypeId` or an index operate on the property's
+/// `PropertyValueStore`.
+#[derive(Copy, Clone)]
+pub struct RegisteredProperty {
+    pub name: &'static str,
+    /// The fully qualified type name, which (unlike the `TypeId`) is stable across builds.
+    pub type_name: &'static str,
+    pub index: usize,
+    pub entity_type_id: TypeId,
+    pub entity_name: &'static str,
+    pub is_required: bool,
+    pub initialization_kind: PropertyInitializationKind,
+    /// The `TypeId` of the entity type the property's values refer to, if they do (see
+    /// [`Property::ENTITY_REFERENCE`]).
+    pub references: Option<TypeId>,
+    /// Returns the heap memory used by the property's values in the given store.
+    pub(crate) heap_bytes: fn(&PropertyStore) -> usize,
+    /// Empties the slot of the entity with the given index in the given store.
+    pub(crate) clear_value: fn(&PropertyStore, usize),
+    /// Moves the value of the entity with index `live_indices[i]` to index `i`, for every `i`,
+    /// dropping the values of all other entities.
+    pub(crate) compact_values: fn(&PropertyStore, &[usize]),
+    /// For a property whose values refer to entities, rewrites each value with the given
+    /// function, which maps the index of the entity it refers to to a new index, or to `None` to
+    /// empty the slot. Does nothing for any other property.
+    pub(crate) remap_references: fn(&PropertyStore, &dyn Fn(usize) -> Option<usize>),
+    /// Returns the number of entities with a value stored in the given store.
+    pub(crate) count_values: fn(&PropertyStore) -> usize,
+    /// Frees any capacity beyond what the property's values in the given store need.
+    pub(crate) shrink_values: fn(&PropertyStore),
+    /// Reserves room in the given store for the values of the given number of entities.
+    pub(crate) reserve_values: fn(&PropertyStore, usize),
+    /// Formats the value of the entity with the given index with `get_display`, or returns `None`
+    /// if it isn't set.
+    pub(crate) display_value: fn(&Context, usize) -> Option<String>,
+    /// Parses the given string as a value of the property and sets it for the entity with the
+    /// given index, or describes why the string couldn't be parsed or the value can't be stored.
+    pub(crate) set_value_from_str: fn(&Context, usize, &str) -> Result<(), SetPropertyByNameError>,
+    /// For a property whose default is computed when an entity is created, computes and stores
+    /// the default for the entity with the given index.
+    pub(crate) init_default: Option<fn(&Context, usize)>,
+    /// Returns a boxed copy of the property's `PropertyValueStore` in the given store, if it has
+    /// been instantiated.
+    pub(crate) clone_values: fn(&PropertyStore) -> Option<Box<dyn Any>>,
+    /// Copies the value of every entity in the second store to the first store, at the entity's
+    /// index plus the given offset.
+    pub(crate) append_values: fn(&PropertyStore, &PropertyStore, usize),
+    /// Serializes the value of the entity with the given index as JSON, or returns `None` if it
+    /// isn't set.
+    pub(crate) json_value: fn(&Context, usize) -> Option<serde_json::Result<serde_json::Value>>,
+    /// Serializes the value of the live entity with the given index as JSON, paired with its
+    /// `get_display` form, or returns `None` if it isn't set. Unlike `json_value`, this stores
+    /// nothing: a default function value that was never read is computed but not stored.
+    pub(crate) peek_value: fn(&Context, usize) -> Option<serde_json::Result<crate::diff::PeekedValue>>,
+    /// Deserializes a JSON value as a value of the property and checks it, returning a setter
+    /// that stores it for the entity with a given index.
+    pub(crate) json_setter:
+        fn(&Context, serde_json::Value) -> Result<crate::io::json::BxJsonSetter, crate::InvalidPropertyValueError>,
+    /// Builds the Arrow column of the property's values for the entities with the given indices.
+    #[cfg(feature = "arrow")]
+    pub(crate) arrow_column: fn(&Context, &[usize]) -> crate::io::arrow::ArrowColumn,
+    /// Reads the property's values from an Arrow column read from a Parquet file, checking each.
+    #[cfg(feature = "parquet")]
+    pub(crate) read_parquet_column: fn(
+        &Context,
+        &dyn arrow_array::Array,
+    ) -> Result<crate::io::parquet::ReadColumn, crate::InvalidPropertyValueError>,
+    #[cfg(feature = "snapshot")]
+    pub(crate) save_values: fn(&PropertyStore, &mut dyn std::io::Write) -> bincode::Result<()>,
+    #[cfg(feature = "snapshot")]
+    pub(crate) load_values: fn(&PropertyStore, &mut dyn std::io::Read) -> bincode::Result<()>,
+}
+
+/// Maps `property_type_id` to the [`RegisteredProperty`] record for that property. This lets code
+/// that only has a `TypeId` (e.g. from `Entity::required_property_ids()`) report properties by
+/// name. This data is written by the property ctors with a call to [`add_to_property_registry()`].
+static REGISTERED_PROPERTIES: LazyLock<Mutex<HashMap<TypeId, RegisteredProperty>>> =
+    LazyLock::new(|| Mutex::new(HashMap::default()));
 
 /// Adds a new item to the registry. The job of this method is to create whatever
 /// "singleton" data/metadata is associated with the [`Entity`] if it doesn't already
@@ -37,60 +131,266 @@ static NEXT_PROPERTY_INDEX: Mutex<usize> = Mutex::new(0);
 /// `OnceCell` itself handles the interior mutability required for initialization.)
 pub fn add_to_property_registry<E: Entity, P: Property<E>>() {
     // Initializes the index for the property type.
-    let _ = P::index();
+    let index = P::index();
     // Registers the property with the entity type.
     register_property_with_entity(
         <E as Entity>::type_id(),
         <P as Property<E>>::type_id(),
         P::is_required(),
     );
+    REGISTERED_PROPERTIES.lock().unwrap().insert(
+        <P as Property<E>>::type_id(),
+        RegisteredProperty {
+            name: P::name(),
+            type_name: std::any::type_name::<P>(),
+            index,
+            entity_type_id: <E as Entity>::type_id(),
+            entity_name: E::name(),
+            is_required: P::is_required(),
+            initialization_kind: P::initialization_kind(),
+            references: P::ENTITY_REFERENCE.map(|reference| (reference.target_type_id)()),
+            heap_bytes: property_heap_bytes::<E, P>,
+            clear_value: clear_property_value::<E, P>,
+            compact_values: compact_property_values::<E, P>,
+            remap_references: remap_property_references::<E, P>,
+            count_values: count_property_values::<E, P>,
+            shrink_values: shrink_property_values::<E, P>,
+            reserve_values: reserve_property_values::<E, P>,
+            display_value: display_property_value::<E, P>,
+            set_value_from_str: set_property_value_from_str::<E, P>,
+            init_default: P::DEFAULT_AT_CREATION.then_some(init_property_default::<E, P>),
+            clone_values: clone_property_values::<E, P>,
+            append_values: append_property_values::<E, P>,
+            json_value: crate::io::jsonl::json_property_value::<E, P>,
+            peek_value: crate::diff::peek_property_value::<E, P>,
+            json_setter: crate::io::json::json_property_setter::<E, P>,
+            #[cfg(feature = "arrow")]
+            arrow_column: crate::io::arrow::arrow_property_column::<E, P>,
+            #[cfg(feature = "parquet")]
+            read_parquet_column: crate::io::parquet::read_property_column::<E, P>,
+            #[cfg(feature = "snapshot")]
+            save_values: crate::io::snapshot::save_property_values::<E, P>,
+            #[cfg(feature = "snapshot")]
+            load_values: crate::io::snapshot::load_property_values::<E, P>,
+        },
+    );
 }
 
-/// A convenience getter for `NEXT_ENTITY_INDEX`.
-pub fn get_registered_property_count() -> usize {
-    *NEXT_PROPERTY_INDEX.lock().unwrap()
+/// Returns the heap memory used by the values of property `P` in `property_store`, without
+/// instantiating its `PropertyValueStore` if it hasn't been accessed yet.
+fn property_heap_bytes<E: Entity, P: Property<E>>(property_store: &PropertyStore) -> usize {
+    property_store
+        .try_get::<E, P>()
+        .map_or(0, PropertyValueStore::heap_bytes)
 }
 
-/// Encapsulates the synchronization logic for initializing an item's index.
-///
-/// Acquires a global lock on the next available item index, but only increments
-/// it if we successfully initialize the provided index. The `index` of a registered
-/// item is assigned at runtime but only once per type. It's possible for a single
-/// type to attempt to initialize its index multiple times from different threads,
-/// which is why all this synchronization is required. However, the overhead
-/// is negligible, as this initialization only happens once upon first access.
+/// Returns the number of values of property `P` stored in `property_store`, without
+/// instantiating its `PropertyValueStore` if it hasn't been accessed yet.
+fn count_property_values<E: Entity, P: Property<E>>(property_store: &PropertyStore) -> usize {
+    property_store
+        .try_get::<E, P>()
+        .map_or(0, PropertyValueStore::count_set)
+}
+
+/// Frees the excess capacity of the values of property `P` in `property_store`, without
+/// instantiating its `PropertyValueStore` if it hasn't been accessed yet.
+fn shrink_property_values<E: Entity, P: Property<E>>(property_store: &PropertyStore) {
+    if let Some(property_value_store) = property_store.try_get::<E, P>() {
+        property_value_store.shrink_to_fit();
+    }
+}
+
+/// Reserves room for the values of property `P` of `entity_count` entities in `property_store`.
+fn reserve_property_values<E: Entity, P: Property<E>>(property_store: &PropertyStore, entity_count: usize) {
+    property_store.get::<E, P>().reserve_for(entity_count);
+}
+
+/// Empties the slot of property `P` for the entity with the given index in `property_store`.
+fn clear_property_value<E: Entity, P: Property<E>>(property_store: &PropertyStore, index: usize) {
+    if let Some(property_value_store) = property_store.try_get::<E, P>() {
+        property_value_store.clear(EntityId::new(index));
+    }
+}
+
+/// Rewrites the values of property `P` in `property_store` so that the value of the entity with
+/// index `live_indices[i]` is at index `i`.
+fn compact_property_values<E: Entity, P: Property<E>>(
+    property_store: &PropertyStore,
+    live_indices: &[usize],
+) {
+    if let Some(property_value_store) = property_store.try_get::<E, P>() {
+        let values = property_value_store.to_values();
+        let compacted = live_indices
+            .iter()
+            .map(|&index| values.get(index).cloned().flatten())
+            .collect();
+        property_value_store.replace_values(compacted);
+    }
+}
+
+/// Rewrites each value of property `P` in `property_store` that refers to an entity with
+/// `renumber`, which maps the index of the entity to its new index, or to `None` to empty the slot.
+fn remap_property_references<E: Entity, P: Property<E>>(
+    property_store: &PropertyStore,
+    renumber: &dyn Fn(usize) -> Option<usize>,
+) {
+    let Some(reference) = P::ENTITY_REFERENCE else {
+        return;
+    };
+    let Some(property_value_store) = property_store.try_get::<E, P>() else {
+        return;
+    };
+    let values = property_value_store
+        .to_values()
+        .into_iter()
+        .map(|value| value.and_then(|value| renumber((reference.to_index)(&value)).map(reference.from_index)))
+        .collect();
+    property_value_store.replace_values(values);
+}
+
+/// Formats the value of property `P` for the entity with the given index, if it has one.
+fn display_property_value<E: Entity, P: Property<E>>(context: &Context, index: usize) -> Option<String> {
+    context
+        .try_get_property::<E, P>(EntityId::new(index))
+        .ok()
+        .map(|value| value.get_display())
+}
+
+/// Parses `field` as a value of property `P` and sets it for the entity with the given index.
 ///
-/// In fact, for our use case we know we are calling this function
-/// once for each type in each `Property`'s `ctor` function, which
-/// should be the only time this method is ever called for the type.
-pub fn initialize_property_index(index: &AtomicUsize) -> usize {
-    // Acquire a global lock.
-    let mut guard = NEXT_PROPERTY_INDEX.lock().unwrap();
-    let candidate = *guard;
-
-    // Try to claim the candidate index. Here we guard against the potential race condition that
-    // another instance of this plugin in another thread just initialized the index prior to us
-    // obtaining the lock. If the index has been initialized beneath us, we do not update
-    // NEXT_PROPERTY_INDEX, we just return the value `index` was initialized to.
-    // For a justification of the data ordering, see:
-    //     https://github.com/CDCgov/ixa/pull/477#discussion_r2244302872
-    match index.compare_exchange(usize::MAX, candidate, Ordering::AcqRel, Ordering::Acquire) {
-        Ok(_) => {
-            // We won the race — increment the global next plugin index and return the new index
-            *guard += 1;
-            candidate
-        }
-        Err(existing) => {
-            // Another thread beat us — don’t increment the global next plugin index,
-            // just return existing
-            existing
-        }
+/// The string is deserialized as a single CSV field, so it has the same format as a column read
+/// with `serde`: a number or `true`/`false` for a newtype over one, a variant name for a unit
+/// enum, and an empty string for `None`.
+fn set_property_value_from_str<E: Entity, P: Property<E>>(
+    context: &Context,
+    index: usize,
+    field: &str,
+) -> Result<(), SetPropertyByNameError> {
+    let record = ::csv::StringRecord::from(vec![field]);
+    let value: P = record.deserialize(None).map_err(|error| SetPropertyByNameError::Parse {
+        property_name: P::name(),
+        message: match error.kind() {
+            ::csv::ErrorKind::Deserialize { err, .. } => err.kind().to_string(),
+            _ => error.to_string(),
+        },
+    })?;
+    context
+        .try_set_property(EntityId::<E>::new(index), value)
+        .map_err(SetPropertyByNameError::InvalidValue)
+}
+
+/// Stores the `default_fn` value of property `P` for the entity with the given index. Reading a
+/// `DefaultFn` property stores its default.
+fn init_property_default<E: Entity, P: Property<E>>(context: &Context, index: usize) {
+    let _: P = context.get_property(EntityId::<E>::new(index));
+}
+
+/// Returns a boxed copy of the `PropertyValueStore` of property `P` in `property_store`, if it has
+/// been instantiated.
+fn clone_property_values<E: Entity, P: Property<E>>(
+    property_store: &PropertyStore,
+) -> Option<Box<dyn Any>> {
+    property_store
+        .try_get::<E, P>()
+        .map(|property_value_store| Box::new(property_value_store.clone()) as Box<dyn Any>)
+}
+
+/// Sets the values of property `P` in `source` in `target`, each at its entity's index plus
+/// `offset`. A value that refers to an entity of type `E` is shifted by `offset` too.
+fn append_property_values<E: Entity, P: Property<E>>(
+    target: &PropertyStore,
+    source: &PropertyStore,
+    offset: usize,
+) {
+    let shift = P::ENTITY_REFERENCE
+        .filter(|reference| (reference.target_type_id)() == <E as Entity>::type_id());
+    if let Some(source_values) = source.try_get::<E, P>() {
+        let updates: Vec<(EntityId<E>, P)> = source_values
+            .iter_set()
+            .map(|(entity_id, value)| {
+                let value = match shift {
+                    Some(reference) => (reference.from_index)((reference.to_index)(&value) + offset),
+                    None => value,
+                };
+                (EntityId::new(entity_id.0 + offset), value)
+            })
+            .collect();
+        target.get::<E, P>().set_bulk(updates);
     }
 }
 
+/// Returns the [`RegisteredProperty`] record of the property with the given `TypeId`, if there is one.
+pub fn get_registered_property(property_type_id: TypeId) -> Option<RegisteredProperty> {
+    REGISTERED_PROPERTIES
+        .lock()
+        .unwrap()
+        .get(&property_type_id)
+        .copied()
+}
+
+/// Returns the name of the registered property with the given `TypeId`, if there is one.
+pub fn get_property_name(property_type_id: TypeId) -> Option<&'static str> {
+    REGISTERED_PROPERTIES
+        .lock()
+        .unwrap()
+        .get(&property_type_id)
+        .map(|property| property.name)
+}
+
+/// Returns the records of all registered properties ordered by property index.
+pub fn get_registered_properties() -> Vec<RegisteredProperty> {
+    let mut properties: Vec<RegisteredProperty> =
+        REGISTERED_PROPERTIES.lock().unwrap().values().copied().collect();
+    properties.sort_by_key(|property| property.index);
+    properties
+}
+
+/// A convenience getter for `NEXT_PROPERTY_INDEX`.
+pub fn get_registered_property_count() -> usize {
+    NEXT_PROPERTY_INDEX.load(Ordering::Acquire)
+}
+
+/// Returns the index of the property whose index is stored in `index`, assigning it the next
+/// available index on first access. The `index` of a registered item is assigned at runtime but
+/// only once per type, normally in the property's `ctor` function, though several threads may
+/// race to initialize it.
+///
+/// No global lock is taken. Each property has its own `OnceLock`, so only threads initializing
+/// the same property wait for each other, and the winner claims an index with a single atomic
+/// increment, so that indices stay contiguous.
+pub fn initialize_property_index(index: &OnceLock<usize>) -> usize {
+    claim_index(index, &NEXT_PROPERTY_INDEX)
+}
+
+/// Returns the index stored in `index`, storing the next index counted by `next` on first access.
+fn claim_index(index: &OnceLock<usize>, next: &AtomicUsize) -> usize {
+    *index.get_or_init(|| next.fetch_add(1, Ordering::AcqRel))
+}
+
 /// A wrapper around a vector of property value stores.
 pub struct PropertyStore {
     items: Vec<OnceCell<Box<dyn Any>>>,
+    /// The growth strategy of every `PropertyValueStore` this store creates.
+    growth: GrowthStrategy,
+    /// The number of entities every `PropertyValueStore` this store creates reserves room for.
+    capacity: usize,
+}
+
+// Every instantiated `PropertyValueStore` is copied through its registry hook.
+impl Clone for PropertyStore {
+    fn clone(&self) -> Self {
+        let clone = Self {
+            items: (0..self.items.len()).map(|_| OnceCell::new()).collect(),
+            growth: self.growth,
+            capacity: self.capacity,
+        };
+        for property in get_registered_properties() {
+            if let Some(property_value_store) = (property.clone_values)(self) {
+                let _ = clone.items[property.index].set(property_value_store);
+            }
+        }
+        clone
+    }
 }
 
 impl Default for PropertyStore {
@@ -113,9 +413,25 @@ impl PropertyStore {
     /// we could just as easily eagerly initialize the "RegisteredItem" instances here
     /// instead (assuming we collected constructors somewhere).
     pub fn new() -> Self {
+        Self::with_growth(GrowthStrategy::default())
+    }
+
+    /// Creates a new [`PropertyStore`] whose property value stores grow according to `growth`.
+    pub fn with_growth(growth: GrowthStrategy) -> Self {
         let num_items = get_registered_property_count();
         Self {
             items: (0..num_items).map(|_| OnceCell::new()).collect(),
+            growth,
+            capacity: 0,
+        }
+    }
+
+    /// Creates a new [`PropertyStore`] whose property value stores each reserve room for the
+    /// values of `capacity` entities when they are first accessed.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Self::new()
         }
     }
 
@@ -127,10 +443,30 @@ impl PropertyStore {
         self.items
         .get(index)
         .unwrap_or_else(|| panic!("No registered property found with index = {index:?}. You must use the `define_property!` macro to create a registered property."))
-        .get_or_init(|| Box::new(PropertyValueStore::<E, P>::new()))
+        .get_or_init(|| {
+            let property_value_store = PropertyValueStore::<E, P>::with_growth(self.growth);
+            property_value_store.reserve(self.capacity);
+            Box::new(property_value_store)
+        })
         .downcast_ref::<PropertyValueStore::<E, P>>()
         .expect("TypeID does not match registered property type. You must use the `define_property!` macro to create a registered property.")
     }
+
+    /// Fetches an immutable reference to the `PropertyValueStore<P>` if it has been instantiated.
+    pub(crate) fn try_get<E: Entity, P: Property<E>>(&self) -> Option<&PropertyValueStore<E, P>> {
+        self.items
+            .get(P::index())?
+            .get()?
+            .downcast_ref::<PropertyValueStore<E, P>>()
+    }
+
+    /// Drops every `PropertyValueStore`, discarding all property values. The slots are kept, and
+    /// each store is lazily recreated empty on its next access.
+    pub fn clear(&mut self) {
+        for item in &mut self.items {
+            item.take();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -141,6 +477,10 @@ mod tests {
 
     define_entity!(Person);
 
+    // A tuple struct wrapping a `bool` is stored bit-packed.
+    define_property!(struct Alive(bool), Person, default_const = Alive(true));
+    define_property!(struct Masked(bool), Person);
+
     // The primary advantage of the `define_property!` macro is that you don't have to remember the list of traits you
     // need to put in the `derive` clause for a property.
     define_property!(struct Age(u8), Person);
@@ -162,10 +502,29 @@ mod tests {
     // `define_property!` macro. In that case, you can use the `impl_property!` macro for
     // a type that has already been defined. The downside is that you have to manually
     // specify the traits that all properties need to implement in the `derive` clause.
-    #[derive(Copy, Clone, Debug, PartialEq, crate::serde::Serialize)]
+    #[derive(Copy, Clone, Debug, PartialEq, crate::serde::Serialize, crate::serde::Deserialize)]
     struct Vaccinated(bool);
     impl_property!(Vaccinated, Person, default_const = Vaccinated(false));
 
+    // The value is an enum, but its canonical form is the variant's `u8` discriminant.
+    #[derive(Copy, Clone, Debug, PartialEq, crate::serde::Serialize, crate::serde::Deserialize)]
+    enum RiskGroup {
+        Low,
+        Medium,
+        High,
+    }
+    crate::impl_property_with_options!(
+        RiskGroup,
+        Person,
+        canonical_value = u8,
+        make_canonical = |group: RiskGroup| group as u8,
+        make_uncanonical = |value: u8| match value {
+            0 => RiskGroup::Low,
+            1 => RiskGroup::Medium,
+            _ => RiskGroup::High,
+        }
+    );
+
     #[test]
     fn test_get_property_store() {
         let property_store = PropertyStore::new();
@@ -223,4 +582,249 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn bit_packed_get_and_set() {
+        let property_store = PropertyStore::new();
+        let masked: &PropertyValueStore<_, Masked> = property_store.get();
+        let alive: &PropertyValueStore<_, Alive> = property_store.get();
+
+        masked.set(EntityId::<Person>::new(3), Masked(true));
+        masked.set(EntityId::<Person>::new(70), Masked(false));
+        masked.set(EntityId::<Person>::new(3), Masked(false));
+        masked.set(EntityId::<Person>::new(64), Masked(true));
+        assert_eq!(masked.get(EntityId::<Person>::new(3)), Some(Masked(false)));
+        assert_eq!(masked.get(EntityId::<Person>::new(64)), Some(Masked(true)));
+        assert_eq!(masked.get(EntityId::<Person>::new(70)), Some(Masked(false)));
+        assert_eq!(masked.get(EntityId::<Person>::new(0)), None);
+        assert_eq!(masked.get(EntityId::<Person>::new(1000)), None);
+        assert!(!masked.is_set(EntityId::<Person>::new(4)));
+
+        let values = masked.to_values();
+        assert_eq!(values.len(), 71);
+        assert_eq!(values[64], Some(Masked(true)));
+        assert_eq!(values[65], None);
+
+        // Unset slots of a constant property fall back to the default.
+        alive.set(EntityId::<Person>::new(5), Alive(false));
+        assert_eq!(alive.get(EntityId::<Person>::new(2)), Some(Alive(true)));
+        assert_eq!(alive.get(EntityId::<Person>::new(5)), Some(Alive(false)));
+    }
+
+    #[test]
+    fn bit_packed_storage_is_smaller() {
+        let property_store = PropertyStore::new();
+        let vaccinated: &PropertyValueStore<_, Vaccinated> = property_store.get();
+        let masked: &PropertyValueStore<_, Masked> = property_store.get();
+
+        for index in 0..10_000 {
+            vaccinated.set(EntityId::<Person>::new(index), Vaccinated(index % 2 == 0));
+            masked.set(EntityId::<Person>::new(index), Masked(index % 2 == 0));
+        }
+        for index in 0..10_000 {
+            assert_eq!(masked.get(EntityId::<Person>::new(index)), Some(Masked(index % 2 == 0)));
+        }
+
+        // Two bits per entity, allowing for the storage to have grown by doubling.
+        assert!(masked.heap_bytes() <= 2 * 2 * 10_000 / 8 + 16);
+        // An unpacked `Option<bool>` takes a whole byte.
+        assert!(masked.heap_bytes() * 2 < vaccinated.heap_bytes());
+    }
+
+    #[test]
+    fn iter_set_skips_gaps() {
+        let property_store = PropertyStore::new();
+        let ages: &PropertyValueStore<_, Age> = property_store.get();
+        let masked: &PropertyValueStore<_, Masked> = property_store.get();
+        let vaccinated: &PropertyValueStore<_, Vaccinated> = property_store.get();
+
+        ages.set(EntityId::<Person>::new(1), Age(10));
+        ages.set(EntityId::<Person>::new(4), Age(40));
+        assert_eq!(
+            ages.iter_set().collect::<Vec<_>>(),
+            vec![(EntityId::new(1), Age(10)), (EntityId::new(4), Age(40))]
+        );
+
+        masked.set(EntityId::<Person>::new(2), Masked(false));
+        masked.set(EntityId::<Person>::new(100), Masked(true));
+        assert_eq!(
+            masked.iter_set().collect::<Vec<_>>(),
+            vec![(EntityId::new(2), Masked(false)), (EntityId::new(100), Masked(true))]
+        );
+
+        assert_eq!(vaccinated.iter_set().count(), 0);
+        assert_eq!(ages.count_set(), 2);
+        assert_eq!(masked.count_set(), 2);
+        assert_eq!(vaccinated.count_set(), 0);
+    }
+
+    #[test]
+    fn iter_yields_canonical_values() {
+        let property_store = PropertyStore::new();
+        let ages: &PropertyValueStore<_, Age> = property_store.get();
+        let risk_groups: &PropertyValueStore<_, RiskGroup> = property_store.get();
+
+        for (index, age) in [(0, 7), (2, 35), (3, 70)] {
+            ages.set(EntityId::<Person>::new(index), Age(age));
+        }
+        let pairs: Vec<(EntityId<Person>, u8)> = ages.iter().map(|(id, age)| (id, age.0)).collect();
+        assert_eq!(pairs, vec![(EntityId::new(0), 7), (EntityId::new(2), 35), (EntityId::new(3), 70)]);
+
+        risk_groups.set(EntityId::<Person>::new(1), RiskGroup::High);
+        risk_groups.set(EntityId::<Person>::new(5), RiskGroup::Medium);
+        assert_eq!(
+            risk_groups.iter().collect::<Vec<_>>(),
+            vec![(EntityId::new(1), 2), (EntityId::new(5), 1)]
+        );
+    }
+
+    #[test]
+    fn property_value_stores_inherit_growth_strategy() {
+        for (growth, expected_bytes) in [
+            (GrowthStrategy::Fixed(100), 200),
+            (GrowthStrategy::ExactToLen, 130),
+        ] {
+            let property_store = PropertyStore::with_growth(growth);
+            let ages: &PropertyValueStore<_, Age> = property_store.get();
+            for index in 0..130 {
+                ages.set(EntityId::<Person>::new(index), Age(1));
+            }
+            assert_eq!(ages.heap_bytes(), expected_bytes * size_of::<Option<Age>>());
+        }
+    }
+
+    #[test]
+    fn extend_from_canonical_values() {
+        let property_store = PropertyStore::new();
+        let groups: &PropertyValueStore<_, RiskGroup> = property_store.get();
+        groups.extend_from(vec![
+            (EntityId::<Person>::new(3), 2),
+            (EntityId::new(0), 0),
+            (EntityId::new(2), 1),
+        ]);
+        assert_eq!(
+            groups.iter_set().collect::<Vec<_>>(),
+            vec![
+                (EntityId::new(0), RiskGroup::Low),
+                (EntityId::new(2), RiskGroup::Medium),
+                (EntityId::new(3), RiskGroup::High),
+            ]
+        );
+
+        let masked: &PropertyValueStore<_, Masked> = property_store.get();
+        masked.extend_from((0..100).map(|index| (EntityId::<Person>::new(index), Masked(index % 3 == 0))));
+        assert_eq!(masked.get(EntityId::new(99)), Some(Masked(true)));
+        assert_eq!(masked.get(EntityId::new(98)), Some(Masked(false)));
+    }
+
+    #[test]
+    fn get_or_default_falls_back_to_constant_default() {
+        let property_store = PropertyStore::new();
+        let statuses: &PropertyValueStore<_, InfectionStatus> = property_store.get();
+        statuses.set(EntityId::<Person>::new(1), InfectionStatus::Infected);
+        assert_eq!(statuses.get_or_default(EntityId::new(0)), InfectionStatus::Susceptible);
+        assert_eq!(statuses.get_or_default(EntityId::new(1)), InfectionStatus::Infected);
+        assert_eq!(statuses.get_or_default(EntityId::new(5)), InfectionStatus::Susceptible);
+
+        let groups: &PropertyValueStore<_, RiskGroup> = property_store.get();
+        groups.set(EntityId::<Person>::new(0), RiskGroup::High);
+        assert_eq!(groups.get_or_default(EntityId::new(0)), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "property Age of Person#3 is not set and has no constant default")]
+    fn get_or_default_without_default() {
+        let property_store = PropertyStore::new();
+        let ages: &PropertyValueStore<_, Age> = property_store.get();
+        ages.set(EntityId::<Person>::new(0), Age(10));
+        ages.get_or_default(EntityId::<Person>::new(3));
+    }
+
+    #[test]
+    fn is_set_ignores_defaults_and_out_of_range_ids() {
+        let property_store = PropertyStore::new();
+        let ages: &PropertyValueStore<_, Age> = property_store.get();
+        let alive: &PropertyValueStore<_, Alive> = property_store.get();
+        for person in [0, 1_000_000].map(EntityId::<Person>::new) {
+            assert!(!ages.is_set(person));
+            assert!(!alive.is_set(person));
+        }
+
+        ages.set(EntityId::new(2), Age(20));
+        alive.set(EntityId::new(2), Alive(false));
+        assert!(ages.is_set(EntityId::new(2)));
+        assert!(alive.is_set(EntityId::new(2)));
+        assert!(!ages.is_set(EntityId::new(1)));
+        // `Alive` reads as its default, but it was never stored.
+        assert!(!alive.is_set(EntityId::new(1)));
+        assert_eq!(alive.get(EntityId::new(1)), Some(Alive(true)));
+        assert!(!ages.is_set(EntityId::new(3)));
+        assert!(!alive.is_set(EntityId::new(1_000_000)));
+
+        // Unpacked and bit-packed constant properties agree: lower slots stay empty.
+        let statuses: &PropertyValueStore<_, InfectionStatus> = property_store.get();
+        statuses.set(EntityId::new(2), InfectionStatus::Infected);
+        assert!(!statuses.is_set(EntityId::new(1)));
+        assert_eq!(statuses.get(EntityId::new(1)), Some(InfectionStatus::Susceptible));
+        assert_eq!(statuses.count_set(), alive.count_set());
+        assert_eq!(
+            statuses.iter_set().collect::<Vec<_>>(),
+            [(EntityId::new(2), InfectionStatus::Infected)]
+        );
+    }
+
+    #[test]
+    fn canonical_value_round_trip() {
+        let property_store = PropertyStore::new();
+        let groups: &PropertyValueStore<_, RiskGroup> = property_store.get();
+        let values = [RiskGroup::High, RiskGroup::Low, RiskGroup::Medium];
+        for (index, group) in values.into_iter().enumerate() {
+            groups.set(EntityId::<Person>::new(index), group);
+        }
+
+        for (index, group) in values.into_iter().enumerate() {
+            let stored = groups.get(EntityId::new(index)).unwrap();
+            assert_eq!(stored, group);
+            let canonical: u8 = stored.make_canonical();
+            assert_eq!(canonical, group as u8);
+            assert_eq!(RiskGroup::make_uncanonical(canonical), group);
+        }
+    }
+
+    #[test]
+    fn concurrent_index_initialization_agrees() {
+        // Threads released together all see the index assigned by the ctor.
+        let barrier = std::sync::Barrier::new(16);
+        let indices: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..16)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        <Age as Property<Person>>::index()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        assert!(indices.iter().all(|index| *index == <Age as Property<Person>>::index()));
+
+        // Threads racing to initialize a fresh index all get the one index that was claimed, and
+        // only one is claimed. A local counter keeps the global one free of a phantom property.
+        let next = AtomicUsize::new(7);
+        let index = OnceLock::new();
+        let indices: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..16)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        claim_index(&index, &next)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        assert_eq!(indices, vec![7; 16]);
+        assert_eq!(index.get(), Some(&7));
+        assert_eq!(next.load(Ordering::Acquire), 8);
+    }
 }
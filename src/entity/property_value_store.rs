@@ -2,52 +2,219 @@
 
 A `PropertyStore<P: Property>` is the backing storage for property values.
 
+Values are normally stored one `Option<P>` per entity in the property's
+[`ValueStorage`](Property::ValueStorage), a `ValueVec` unless the property's type isn't `Copy`.
+A property that provides
+a [`BitCodec`] (see [`Property::BIT_CODEC`]) is instead stored in two bitsets, one recording
+which entities have a value and one holding the values themselves.
+
 */
 
+use std::cell::Cell;
+
 use super::{
     entity::{Entity, EntityId},
-    property::{Property, PropertyInitializationKind},
+    property::{BitCodec, Property, PropertyInitializationKind},
 };
-use crate::value_vec::ValueVec;
+use crate::value_vec::{GrowthStrategy, OptionSlots, ValueVec};
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+/// Bit-packed storage for `Option<bool>` slots.
+#[derive(Clone)]
+struct PackedBits {
+    /// Bit `i` is set if slot `i` holds a value.
+    presence: ValueVec<u64>,
+    /// Bit `i` is the value in slot `i`, if there is one.
+    values: ValueVec<u64>,
+    /// The number of slots, that is, one more than the largest index ever set.
+    len: Cell<usize>,
+}
+
+impl PackedBits {
+    fn with_capacity(capacity: usize) -> Self {
+        let words = capacity.div_ceil(BITS_PER_WORD);
+        Self {
+            presence: ValueVec::with_capacity(words),
+            values: ValueVec::with_capacity(words),
+            len: Cell::new(0),
+        }
+    }
+
+    fn with_growth(growth: GrowthStrategy) -> Self {
+        Self {
+            presence: ValueVec::with_growth(growth),
+            values: ValueVec::with_growth(growth),
+            len: Cell::new(0),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    fn reserve(&self, additional: usize) {
+        let words = (self.len() + additional).div_ceil(BITS_PER_WORD);
+        let additional_words = words.saturating_sub(self.presence.len());
+        self.presence.reserve(additional_words);
+        self.values.reserve(additional_words);
+    }
+
+    fn get(&self, index: usize) -> Option<bool> {
+        let word = index / BITS_PER_WORD;
+        let mask = 1u64 << (index % BITS_PER_WORD);
+        if self.presence.get(word)? & mask == 0 {
+            return None;
+        }
+        Some(self.values.at(word) & mask != 0)
+    }
+
+    fn set(&self, index: usize, bit: Option<bool>) {
+        let word = index / BITS_PER_WORD;
+        let mask = 1u64 << (index % BITS_PER_WORD);
+        if word >= self.presence.len() {
+            self.presence.resize_with(word + 1, || 0);
+            self.values.resize_with(word + 1, || 0);
+        }
+
+        let presence = self.presence.at(word);
+        let values = self.values.at(word);
+        match bit {
+            Some(bit) => {
+                self.presence.set(word, presence | mask);
+                self.values
+                    .set(word, if bit { values | mask } else { values & !mask });
+            }
+            None => self.presence.set(word, presence & !mask),
+        }
+        self.len.set(self.len().max(index + 1));
+    }
+
+    fn clear(&self) {
+        self.presence.clear();
+        self.values.clear();
+        self.len.set(0);
+    }
+
+    fn shrink_to_fit(&self) {
+        self.presence.shrink_to_fit();
+        self.values.shrink_to_fit();
+    }
+
+    fn heap_bytes(&self) -> usize {
+        (self.presence.capacity() + self.values.capacity()) * size_of::<u64>()
+    }
+
+    /// The number of slots holding a value.
+    fn count_set(&self) -> usize {
+        (0..self.presence.len())
+            .map(|word| self.presence.at(word).count_ones() as usize)
+            .sum()
+    }
+}
+
+#[derive(Clone)]
+enum Storage<V, P> {
+    Values(V),
+    Bits(PackedBits, BitCodec<P>),
+}
 
 pub struct PropertyValueStore<E: Entity, P: Property<E>> {
-    data: ValueVec<Option<P>>,
+    data: Storage<P::ValueStorage, P>,
 
     _phantom: std::marker::PhantomData<E>,
 }
 
-impl<E: Entity, P: Property<E>> Default for PropertyValueStore<E, P> {
-    fn default() -> Self {
+// The derive version of the `Clone` implementation introduces unnecessary trait bounds on `E`.
+impl<E: Entity, P: Property<E>> Clone for PropertyValueStore<E, P> {
+    fn clone(&self) -> Self {
         Self {
-            data: ValueVec::default(),
+            data: self.data.clone(),
             _phantom: Default::default(),
         }
     }
 }
 
+impl<E: Entity, P: Property<E>> Default for PropertyValueStore<E, P> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
 impl<E: Entity, P: Property<E>> PropertyValueStore<E, P> {
     pub fn new() -> Self {
         Self::default()
     }
 
     pub fn with_capacity(capacity: usize) -> Self {
+        let data = match P::BIT_CODEC {
+            Some(codec) => Storage::Bits(PackedBits::with_capacity(capacity), codec),
+            None => Storage::Values(P::ValueStorage::with_capacity(capacity)),
+        };
         Self {
-            data: ValueVec::with_capacity(capacity),
+            data,
+            _phantom: Default::default(),
+        }
+    }
+
+    /// Creates an empty store whose storage grows according to `growth`.
+    pub fn with_growth(growth: GrowthStrategy) -> Self {
+        let data = match P::BIT_CODEC {
+            Some(codec) => Storage::Bits(PackedBits::with_growth(growth), codec),
+            None => Storage::Values(P::ValueStorage::with_growth(growth)),
+        };
+        Self {
+            data,
             _phantom: Default::default(),
         }
     }
 
     /// Ensures capacity for at least `additional` more elements
     pub fn reserve(&self, additional: usize) {
-        self.data.reserve(additional);
+        match &self.data {
+            Storage::Values(values) => values.reserve(additional),
+            Storage::Bits(bits, _) => bits.reserve(additional),
+        }
+    }
+
+    /// Ensures capacity for the values of the entities with indices `0..entity_count`.
+    pub(crate) fn reserve_for(&self, entity_count: usize) {
+        match &self.data {
+            Storage::Values(values) => values.reserve(entity_count.saturating_sub(values.len())),
+            Storage::Bits(bits, _) => bits.reserve(entity_count.saturating_sub(bits.len())),
+        }
+    }
+
+    /// The number of entities the store can hold values for without reallocating.
+    pub fn capacity(&self) -> usize {
+        match &self.data {
+            Storage::Values(values) => values.capacity(),
+            Storage::Bits(bits, _) => bits.presence.capacity() * BITS_PER_WORD,
+        }
+    }
+
+    /// The number of bytes of heap memory allocated for the values.
+    pub fn heap_bytes(&self) -> usize {
+        match &self.data {
+            Storage::Values(values) => values.heap_bytes(),
+            Storage::Bits(bits, _) => bits.heap_bytes(),
+        }
+    }
+
+    /// Returns the value stored in the slot for the given index, or `None` if the index is out of
+    /// bounds or the slot is empty.
+    fn get_slot(&self, index: usize) -> Option<P> {
+        match &self.data {
+            Storage::Values(values) => values.get(index),
+            Storage::Bits(bits, codec) => bits.get(index).map(codec.from_bit),
+        }
     }
 
     /// Returns the property value for the given entity. Returns `None`
     /// if the property is both not set and has no default value.
     pub fn get(&self, entity_id: EntityId<E>) -> Option<P> {
-        self.data.get(entity_id.0).unwrap_or_else(|| {
-            // `None` means index was out of bounds, which means the property is not set.
-            // Return the default if there is one.
+        self.get_slot(entity_id.0).or_else(|| {
+            // The property is not set. Return the default if there is one.
             if P::initialization_kind() == PropertyInitializationKind::Constant {
                 Some(P::default_const())
             } else {
@@ -56,30 +223,193 @@ impl<E: Entity, P: Property<E>> PropertyValueStore<E, P> {
         })
     }
 
-    /// Sets the value for `entity_id` to `value`.
+    /// Returns the canonical form of the property value for the given entity, as by
+    /// [`PropertyValueStore::get`]: an unset constant property reads as its default.
+    ///
+    /// Panics if the property is not set and has no constant default.
+    pub fn get_or_default(&self, entity_id: EntityId<E>) -> P::CanonicalValue {
+        match self.get(entity_id) {
+            Some(value) => value.make_canonical(),
+            None => panic!(
+                "property {} of {entity_id} is not set and has no constant default",
+                P::name()
+            ),
+        }
+    }
+
+    /// Returns `true` if a value has been stored in the slot for the given entity. Unlike
+    /// [`PropertyValueStore::get`], this does not fall back to the default value. An id beyond
+    /// the end of the storage has no value, so it returns `false`.
+    pub fn is_set(&self, entity_id: EntityId<E>) -> bool {
+        self.get_slot(entity_id.0).is_some()
+    }
+
+    /// Iterates over the entities whose slot holds a value, in id order, yielding a copy of each
+    /// value. Empty slots are skipped, including those of a constant property that read as its
+    /// default.
+    ///
+    /// Values are yielded by value rather than by reference because the storage never hands out
+    /// references: a `ValueVec` may reallocate while the iterator is alive, and bit-packed values
+    /// don't exist in memory as a `P`. [`PropertyValueStore::iter`] yields canonical values instead.
+    pub fn iter_set(&self) -> impl Iterator<Item = (EntityId<E>, P)> + '_ {
+        let slot_count = match &self.data {
+            Storage::Values(values) => values.len(),
+            Storage::Bits(bits, _) => bits.len(),
+        };
+        (0..slot_count)
+            .filter_map(|index| self.get_slot(index).map(|value| (EntityId::new(index), value)))
+    }
+
+    /// Iterates over the entities whose slot holds a value, in id order, yielding the canonical
+    /// form of each value. Like [`PropertyValueStore::iter_set`], empty slots are skipped and the
+    /// values are yielded by value.
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId<E>, P::CanonicalValue)> + '_ {
+        self.iter_set()
+            .map(|(entity_id, value)| (entity_id, value.make_canonical()))
+    }
+
+    /// The number of slots holding a value, i.e. the number of items [`PropertyValueStore::iter_set`]
+    /// yields, counted without reading the values.
+    pub fn count_set(&self) -> usize {
+        match &self.data {
+            Storage::Values(values) => values.count_set(),
+            Storage::Bits(bits, _) => bits.count_set(),
+        }
+    }
+
+    /// Empties the slot for `entity_id`, so that it reads as never having been set.
+    pub(crate) fn clear(&self, entity_id: EntityId<E>) {
+        let index = entity_id.0;
+        match &self.data {
+            Storage::Values(values) => {
+                if index < values.len() {
+                    values.set(index, None);
+                }
+            }
+            Storage::Bits(bits, _) => {
+                if index < bits.len() {
+                    bits.set(index, None);
+                }
+            }
+        }
+    }
+
+    /// Empties every slot and frees the storage, so that every entity reads as never having had a
+    /// value.
+    pub(crate) fn clear_all(&self) {
+        match &self.data {
+            Storage::Values(values) => {
+                values.clear();
+                values.shrink_to_fit();
+            }
+            Storage::Bits(bits, _) => {
+                bits.clear();
+                bits.shrink_to_fit();
+            }
+        }
+    }
+
+    /// Frees any capacity beyond what the stored values need.
+    pub fn shrink_to_fit(&self) {
+        match &self.data {
+            Storage::Values(values) => values.shrink_to_fit(),
+            Storage::Bits(bits, _) => bits.shrink_to_fit(),
+        }
+    }
+
+    /// Returns a copy of every slot in entity index order. `None` marks an unset value.
+    pub(crate) fn to_values(&self) -> Vec<Option<P>> {
+        match &self.data {
+            Storage::Values(values) => values.to_vec(),
+            Storage::Bits(bits, codec) => (0..bits.len())
+                .map(|index| bits.get(index).map(codec.from_bit))
+                .collect(),
+        }
+    }
+
+    /// Replaces every slot with `values`, given in entity index order.
+    pub(crate) fn replace_values(&self, values: Vec<Option<P>>) {
+        match &self.data {
+            Storage::Values(data) => {
+                data.clear();
+                data.extend(values);
+            }
+            Storage::Bits(bits, codec) => {
+                bits.clear();
+                for (index, value) in values.into_iter().enumerate() {
+                    bits.set(index, value.map(codec.to_bit));
+                }
+            }
+        }
+    }
+
+    /// Sets the value for `entity_id` to `value`. Slots below it that were never set stay empty,
+    /// and read as the default through [`PropertyValueStore::get`] if there is one.
     pub fn set(&self, entity_id: EntityId<E>, value: P) {
         let index = entity_id.0;
-        let len = self.data.len();
+        let data = match &self.data {
+            Storage::Values(data) => data,
+            Storage::Bits(bits, codec) => {
+                bits.set(index, Some((codec.to_bit)(value)));
+                return;
+            }
+        };
+        let len = data.len();
 
         if index >= len {
-            // The index is out of bounds, so we need to fill in the missing slots.
-            let default_value = match P::initialization_kind() {
-                PropertyInitializationKind::Constant => Some(P::default_const()),
-                _ => None,
-            };
-
+            // The index is out of bounds, so we need to add the missing slots.
             // Pre-reserve exact capacity to avoid reallocations
-            self.data.reserve(index + 1 - len);
+            data.reserve(index + 1 - len);
 
-            // Fill any missing slots up to (but not including) `idx`
-            self.data.resize_with(index, || default_value.clone());
+            // Add empty slots up to (but not including) `idx`
+            data.resize_with(index, || None);
             // ...and finally push the provided value
-            self.data.push(Some(value));
+            data.push(Some(value));
         } else {
             // The index is in bounds, so we can just set the value directly.
-            self.data.set(index, Some(value));
+            data.set(index, Some(value));
         }
     }
+
+    /// Sets the value for each `(entity_id, value)` pair in `updates`, in order. The backing
+    /// storage is grown at most once, to fit the largest entity id.
+    pub fn set_bulk(&self, updates: Vec<(EntityId<E>, P)>) {
+        let Some(max_index) = updates.iter().map(|(entity_id, _)| entity_id.0).max() else {
+            return;
+        };
+        let data = match &self.data {
+            Storage::Values(data) => data,
+            Storage::Bits(bits, codec) => {
+                // Setting the largest index first grows the bitsets once.
+                bits.set(max_index, None);
+                for (entity_id, value) in updates {
+                    bits.set(entity_id.0, Some((codec.to_bit)(value)));
+                }
+                return;
+            }
+        };
+        let len = data.len();
+
+        if max_index >= len {
+            data.reserve(max_index + 1 - len);
+            data.resize_with(max_index + 1, || None);
+        }
+
+        for (entity_id, value) in updates {
+            data.set(entity_id.0, Some(value));
+        }
+    }
+
+    /// Sets the value for each `(entity_id, canonical value)` pair in `values`, as by
+    /// [`PropertyValueStore::set_bulk`], e.g. to fill the store from values read from disk. Unlike
+    /// the `Context` methods, this doesn't check that the entities exist or emit change events.
+    pub fn extend_from(&self, values: impl IntoIterator<Item = (EntityId<E>, P::CanonicalValue)>) {
+        let updates = values
+            .into_iter()
+            .map(|(entity_id, value)| (entity_id, P::make_uncanonical(value)))
+            .collect();
+        self.set_bulk(updates);
+    }
 }
 
 // See tests in `property_store.rs`.
@@ -0,0 +1,443 @@
+/*!
+
+Many-to-many relationships between the entities of two types, e.g. a contact network between
+`Person`s or the enrollment of `Person`s in `School`s.
+
+```rust,ignore
+context.add_edge(alice, bob);
+for contact in context.neighbors_of::<Person, Person>(alice) {
+    // ...
+}
+```
+
+A [`RelationshipStore<A, B>`] holds edges between `EntityId<A>`s and `EntityId<B>`s. It keeps
+adjacency lists in both directions, so the neighbors of an entity at either end are found in
+time proportional to its degree. An edge is directed from its `A` end to its `B` end: when `A`
+and `B` are the same type, [`RelationshipStore::neighbors_of`] gives the entities an entity
+points to and [`RelationshipStore::reverse_neighbors_of`] the entities that point to it.
+
+A `Context` holds at most one store per pair of entity types, created when it's first needed.
+Removing an entity from the context deletes its edges from every store, so a store never refers
+to a removed entity.
+
+*/
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use super::{Entity, EntityId};
+use crate::Context;
+
+/// The edges between entities of type `A` and entities of type `B`.
+pub struct RelationshipStore<A: Entity, B: Entity> {
+    /// The `B` ends of the edges leaving each `A`, in the order they were added.
+    forward: HashMap<EntityId<A>, Vec<EntityId<B>>>,
+    /// The `A` ends of the edges arriving at each `B`, in the order they were added.
+    reverse: HashMap<EntityId<B>, Vec<EntityId<A>>>,
+}
+
+impl<A: Entity, B: Entity> Default for RelationshipStore<A, B> {
+    fn default() -> Self {
+        Self {
+            forward: HashMap::new(),
+            reverse: HashMap::new(),
+        }
+    }
+}
+
+// The derive version of the `Clone` implementation introduces unnecessary trait bounds on `A` and `B`.
+impl<A: Entity, B: Entity> Clone for RelationshipStore<A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            forward: self.forward.clone(),
+            reverse: self.reverse.clone(),
+        }
+    }
+}
+
+impl<A: Entity, B: Entity> RelationshipStore<A, B> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the edge from `a` to `b`. Returns `false`, doing nothing, if it already exists.
+    pub fn add_edge(&mut self, a: EntityId<A>, b: EntityId<B>) -> bool {
+        let targets = self.forward.entry(a).or_default();
+        if targets.contains(&b) {
+            return false;
+        }
+        targets.push(b);
+        self.reverse.entry(b).or_default().push(a);
+        true
+    }
+
+    /// Removes the edge from `a` to `b`. Returns `false` if there was no such edge.
+    pub fn remove_edge(&mut self, a: EntityId<A>, b: EntityId<B>) -> bool {
+        if !remove_from_list(&mut self.forward, &a, &b) {
+            return false;
+        }
+        remove_from_list(&mut self.reverse, &b, &a);
+        true
+    }
+
+    /// Returns `true` if there is an edge from `a` to `b`.
+    pub fn contains_edge(&self, a: EntityId<A>, b: EntityId<B>) -> bool {
+        self.neighbors_of(a).contains(&b)
+    }
+
+    /// The `B` ends of the edges from `a`, in the order they were added.
+    pub fn neighbors_of(&self, a: EntityId<A>) -> &[EntityId<B>] {
+        self.forward.get(&a).map_or(&[], Vec::as_slice)
+    }
+
+    /// The `A` ends of the edges to `b`, in the order they were added.
+    pub fn reverse_neighbors_of(&self, b: EntityId<B>) -> &[EntityId<A>] {
+        self.reverse.get(&b).map_or(&[], Vec::as_slice)
+    }
+
+    /// The number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.forward.values().map(Vec::len).sum()
+    }
+
+    /// Deletes every edge from `a`.
+    fn remove_edges_from(&mut self, a: &EntityId<A>) {
+        for b in self.forward.remove(a).unwrap_or_default() {
+            remove_from_list(&mut self.reverse, &b, a);
+        }
+    }
+
+    /// Deletes every edge to `b`.
+    fn remove_edges_to(&mut self, b: &EntityId<B>) {
+        for a in self.reverse.remove(b).unwrap_or_default() {
+            remove_from_list(&mut self.forward, &a, b);
+        }
+    }
+
+    /// Rewrites the id of every entity of the type with the given `TypeId`, whichever end it is
+    /// at, with `renumber`, which maps an entity index to its new index.
+    fn renumber(&mut self, entity_type_id: TypeId, renumber: impl Fn(usize) -> usize) {
+        if entity_type_id == <A as Entity>::type_id() {
+            self.forward = renumber_keys(std::mem::take(&mut self.forward), &renumber);
+            renumber_lists(&mut self.reverse, &renumber);
+        }
+        if entity_type_id == <B as Entity>::type_id() {
+            self.reverse = renumber_keys(std::mem::take(&mut self.reverse), &renumber);
+            renumber_lists(&mut self.forward, &renumber);
+        }
+    }
+}
+
+fn renumber_keys<K: Entity, V>(
+    lists: HashMap<EntityId<K>, V>,
+    renumber: impl Fn(usize) -> usize,
+) -> HashMap<EntityId<K>, V> {
+    lists
+        .into_iter()
+        .map(|(key, list)| (EntityId::new(renumber(key.0)), list))
+        .collect()
+}
+
+fn renumber_lists<K, V: Entity>(
+    lists: &mut HashMap<K, Vec<EntityId<V>>>,
+    renumber: impl Fn(usize) -> usize,
+) {
+    for id in lists.values_mut().flatten() {
+        *id = EntityId::new(renumber(id.0));
+    }
+}
+
+/// Removes `value` from the list of `key` in `lists`, dropping the list if it becomes empty.
+/// Returns `false` if `value` wasn't in the list.
+pub(super) fn remove_from_list<K: Eq + std::hash::Hash, V: PartialEq>(
+    lists: &mut HashMap<K, Vec<V>>,
+    key: &K,
+    value: &V,
+) -> bool {
+    let Some(list) = lists.get_mut(key) else {
+        return false;
+    };
+    let Some(position) = list.iter().position(|item| item == value) else {
+        return false;
+    };
+    list.remove(position);
+    if list.is_empty() {
+        lists.remove(key);
+    }
+    true
+}
+
+/// The type-erased interface through which a `Context` manages its relationship stores.
+pub(crate) trait AnyRelationshipStore {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn clone_boxed(&self) -> Box<dyn AnyRelationshipStore>;
+    /// Deletes the edges to and from the entity with the given index of the entity type with the
+    /// given `TypeId`, which may be either end of the relationship, or both.
+    fn remove_entity(&mut self, entity_type_id: TypeId, index: usize);
+    /// Renumbers the entities of the type with the given `TypeId` after they were compacted, so
+    /// that the entity with index `live_indices[i]` gets index `i`.
+    fn remap_entity(&mut self, entity_type_id: TypeId, live_indices: &[usize]);
+    /// An empty store of the same type.
+    fn new_empty(&self) -> Box<dyn AnyRelationshipStore>;
+    /// Adds the edges of `other`, a store of the same type, with the ids of the entities of the
+    /// type with the given `TypeId` shifted by `offset`.
+    fn append_edges(&mut self, other: &dyn AnyRelationshipStore, entity_type_id: TypeId, offset: usize);
+    /// The `TypeId`s of the entity types at the `A` and `B` ends.
+    fn end_type_ids(&self) -> (TypeId, TypeId);
+}
+
+impl<A: Entity, B: Entity> AnyRelationshipStore for RelationshipStore<A, B> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_boxed(&self) -> Box<dyn AnyRelationshipStore> {
+        Box::new(self.clone())
+    }
+
+    fn remove_entity(&mut self, entity_type_id: TypeId, index: usize) {
+        if entity_type_id == <A as Entity>::type_id() {
+            self.remove_edges_from(&EntityId::new(index));
+        }
+        if entity_type_id == <B as Entity>::type_id() {
+            self.remove_edges_to(&EntityId::new(index));
+        }
+    }
+
+    fn remap_entity(&mut self, entity_type_id: TypeId, live_indices: &[usize]) {
+        // Removing an entity deletes its edges, so every entity in the store is live.
+        self.renumber(entity_type_id, |index| {
+            live_indices
+                .binary_search(&index)
+                .expect("relationship store refers to a removed entity")
+        });
+    }
+
+    fn new_empty(&self) -> Box<dyn AnyRelationshipStore> {
+        Box::new(Self::new())
+    }
+
+    fn append_edges(&mut self, other: &dyn AnyRelationshipStore, entity_type_id: TypeId, offset: usize) {
+        let mut other: Self = other
+            .as_any()
+            .downcast_ref::<Self>()
+            .expect("relationship store has the wrong type")
+            .clone();
+        other.renumber(entity_type_id, |index| index + offset);
+        // Add the edges in id order, so that the adjacency lists come out the same every run.
+        let mut forward: Vec<_> = other.forward.into_iter().collect();
+        forward.sort_by_key(|(a, _)| *a);
+        for (a, targets) in forward {
+            for b in targets {
+                self.add_edge(a, b);
+            }
+        }
+    }
+
+    fn end_type_ids(&self) -> (TypeId, TypeId) {
+        (<A as Entity>::type_id(), <B as Entity>::type_id())
+    }
+}
+
+impl Context {
+    /// The edges between entities of type `A` and entities of type `B`, or `None` if none have
+    /// ever been added.
+    pub fn relationships<A: Entity, B: Entity>(&self) -> Option<&RelationshipStore<A, B>> {
+        self.relationship_stores
+            .get(&TypeId::of::<RelationshipStore<A, B>>())
+            .map(|store| {
+                store
+                    .as_any()
+                    .downcast_ref()
+                    .expect("relationship store has the wrong type")
+            })
+    }
+
+    /// The edges between entities of type `A` and entities of type `B`, creating an empty store
+    /// if there isn't one yet.
+    pub fn relationships_mut<A: Entity, B: Entity>(&mut self) -> &mut RelationshipStore<A, B> {
+        self.relationship_stores
+            .entry(TypeId::of::<RelationshipStore<A, B>>())
+            .or_insert_with(|| Box::new(RelationshipStore::<A, B>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("relationship store has the wrong type")
+    }
+
+    /// Adds the edge from `a` to `b`. Returns `false`, doing nothing, if it already exists.
+    ///
+    /// Panics if either entity doesn't exist.
+    pub fn add_edge<A: Entity, B: Entity>(&mut self, a: EntityId<A>, b: EntityId<B>) -> bool {
+        for (entity_id, is_live) in [
+            (a.to_string(), self.entity_store.is_live(a)),
+            (b.to_string(), self.entity_store.is_live(b)),
+        ] {
+            assert!(is_live, "cannot add an edge to {entity_id}, which does not exist");
+        }
+        self.relationships_mut().add_edge(a, b)
+    }
+
+    /// Removes the edge from `a` to `b`. Returns `false` if there was no such edge.
+    pub fn remove_edge<A: Entity, B: Entity>(&mut self, a: EntityId<A>, b: EntityId<B>) -> bool {
+        self.relationships_mut().remove_edge(a, b)
+    }
+
+    /// The `B` ends of the edges from `a`, in the order they were added.
+    pub fn neighbors_of<A: Entity, B: Entity>(&self, a: EntityId<A>) -> &[EntityId<B>] {
+        self.relationships::<A, B>()
+            .map_or(&[], |store| store.neighbors_of(a))
+    }
+
+    /// The `A` ends of the edges to `b`, in the order they were added.
+    pub fn reverse_neighbors_of<A: Entity, B: Entity>(&self, b: EntityId<B>) -> &[EntityId<A>] {
+        self.relationships::<A, B>()
+            .map_or(&[], |store| store.reverse_neighbors_of(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, define_entity};
+
+    define_entity!(Person);
+    define_entity!(School);
+
+    #[test]
+    fn neighbors_from_both_ends() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..4).map(|_| context.add_entity(())).collect();
+        let schools: Vec<SchoolId> = (0..2).map(|_| context.add_entity(())).collect();
+
+        assert!(context.add_edge(people[0], schools[0]));
+        assert!(context.add_edge(people[1], schools[0]));
+        assert!(context.add_edge(people[1], schools[1]));
+        assert!(!context.add_edge(people[1], schools[1]));
+
+        assert_eq!(context.neighbors_of::<_, School>(people[1]), [schools[0], schools[1]]);
+        assert_eq!(context.neighbors_of::<_, School>(people[2]), []);
+        assert_eq!(
+            context.reverse_neighbors_of::<Person, _>(schools[0]),
+            [people[0], people[1]]
+        );
+        assert_eq!(context.relationships::<Person, School>().unwrap().edge_count(), 3);
+
+        assert!(context.remove_edge(people[1], schools[0]));
+        assert!(!context.remove_edge(people[1], schools[0]));
+        assert_eq!(context.neighbors_of::<_, School>(people[1]), [schools[1]]);
+        assert_eq!(context.reverse_neighbors_of::<Person, _>(schools[0]), [people[0]]);
+    }
+
+    #[test]
+    fn edges_between_entities_of_the_same_type() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..3).map(|_| context.add_entity(())).collect();
+        context.add_edge(people[0], people[1]);
+        context.add_edge(people[2], people[1]);
+
+        assert_eq!(context.neighbors_of::<_, Person>(people[0]), [people[1]]);
+        assert_eq!(context.neighbors_of::<_, Person>(people[1]), []);
+        assert_eq!(
+            context.reverse_neighbors_of::<_, Person>(people[1]),
+            [people[0], people[2]]
+        );
+        assert!(context.relationships::<Person, School>().is_none());
+    }
+
+    #[test]
+    fn removing_an_entity_deletes_its_edges() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..5).map(|_| context.add_entity(())).collect();
+        let school: SchoolId = context.add_entity(());
+        let hub = people[0];
+        for &person in &people[1..] {
+            context.add_edge(hub, person);
+            context.add_edge(person, hub);
+        }
+        context.add_edge(people[1], people[2]);
+        context.add_edge(hub, school);
+        context.add_edge(people[3], school);
+
+        assert!(context.remove_entity(hub));
+        for &person in &people[1..] {
+            assert!(!context.neighbors_of::<_, Person>(person).contains(&hub));
+            assert!(!context.reverse_neighbors_of::<_, Person>(person).contains(&hub));
+        }
+        assert_eq!(context.neighbors_of::<_, Person>(hub), []);
+        assert_eq!(context.reverse_neighbors_of::<Person, _>(hub), []);
+        assert_eq!(context.neighbors_of::<_, Person>(people[1]), [people[2]]);
+        assert_eq!(context.relationships::<Person, Person>().unwrap().edge_count(), 1);
+        assert_eq!(context.reverse_neighbors_of::<Person, _>(school), [people[3]]);
+
+        assert!(context.remove_entity(school));
+        assert_eq!(context.neighbors_of::<_, School>(people[3]), []);
+        assert_eq!(context.relationships::<Person, School>().unwrap().edge_count(), 0);
+    }
+
+    #[test]
+    fn edges_survive_compaction() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..6).map(|_| context.add_entity(())).collect();
+        let schools: Vec<SchoolId> = (0..3).map(|_| context.add_entity(())).collect();
+        context.add_edge(people[1], people[5]);
+        context.add_edge(people[5], people[3]);
+        context.add_edge(people[3], schools[2]);
+        context.add_edge(people[5], schools[0]);
+        context.remove_entity(people[0]);
+        context.remove_entity(people[2]);
+        context.remove_entity(schools[1]);
+
+        let people_remap = context.compact::<Person>();
+        let person = |index: usize| *people_remap.get(people[index]).unwrap();
+        assert_eq!(person(5), PersonId::new(3));
+        assert_eq!(context.neighbors_of::<_, Person>(person(1)), [person(5)]);
+        assert_eq!(context.neighbors_of::<_, Person>(person(5)), [person(3)]);
+        assert_eq!(context.reverse_neighbors_of::<_, Person>(person(3)), [person(5)]);
+        assert_eq!(context.neighbors_of::<_, School>(person(5)), [schools[0]]);
+
+        let school_remap = context.compact::<School>();
+        let school = *school_remap.get(schools[2]).unwrap();
+        assert_eq!(school, SchoolId::new(1));
+        assert_eq!(context.reverse_neighbors_of::<Person, _>(school), [person(3)]);
+        assert_eq!(context.neighbors_of::<_, School>(person(3)), [school]);
+        assert_eq!(context.relationships::<Person, School>().unwrap().edge_count(), 2);
+    }
+
+    #[test]
+    fn merge_appends_edges_between_merged_entities() {
+        let mut context = Context::new();
+        let ada: PersonId = context.add_entity(());
+        let bo: PersonId = context.add_entity(());
+        context.add_edge(ada, bo);
+
+        let mut other = Context::new();
+        let people: Vec<PersonId> = (0..3).map(|_| other.add_entity(())).collect();
+        let school: SchoolId = other.add_entity(());
+        other.add_edge(people[2], people[0]);
+        other.add_edge(people[0], people[1]);
+        other.add_edge(people[1], school);
+
+        let remap = context.merge::<Person>(&other);
+        let person = |index: usize| *remap.get(people[index]).unwrap();
+        assert_eq!(context.neighbors_of::<_, Person>(ada), [bo]);
+        assert_eq!(context.neighbors_of::<_, Person>(person(0)), [person(1)]);
+        assert_eq!(context.neighbors_of::<_, Person>(person(2)), [person(0)]);
+        assert_eq!(context.relationships::<Person, Person>().unwrap().edge_count(), 3);
+        // The school isn't merged, so neither is the edge to it.
+        assert!(context.relationships::<Person, School>().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot add an edge to School#0, which does not exist")]
+    fn add_edge_to_missing_entity() {
+        let mut context = Context::new();
+        let person: PersonId = context.add_entity(());
+        context.add_edge(person, SchoolId::new(0));
+    }
+}
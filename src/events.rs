@@ -0,0 +1,411 @@
+/*!
+
+Callbacks that model code registers on a `Context` to react to changes in the population.
+
+```rust,ignore
+context.subscribe_property_change(|context, person: PersonId, old: Option<InfectionStatus>, new| {
+    if new == InfectionStatus::Infected {
+        // ...
+    }
+});
+```
+
+Handlers are stored type-erased per property or entity type, keyed by `TypeId`, and invoked synchronously
+in registration order. Every handler receives a shared reference to the `Context`, so it can read
+(and set) property values, but it can't subscribe further handlers.
+
+# Deferred dispatch
+
+By default, handlers run inside the call that triggered them, e.g. inside `set_property`, so a
+handler that sets another property triggers further handlers before the first one returns. After
+`context.set_deferred_events(true)`, events are instead queued and their handlers only run when
+the model calls [`Context::flush_events`] at a point of its choosing. Handlers still see the
+event's values as they were when it was queued, but the context as it is when it is flushed.
+
+*/
+
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+};
+
+use crate::{
+    Context,
+    entity::{Entity, EntityId, property::Property},
+};
+
+/// A handler for changes to the value of property `P` of an entity: `(context, entity_id,
+/// old_value, new_value)`.
+pub type PropertyChangeHandler<E, P> = Box<dyn Fn(&Context, EntityId<E>, Option<P>, P)>;
+
+/// A handler for an event concerning a single entity: `(context, entity_id)`.
+pub type EntityHandler<E> = Box<dyn Fn(&Context, EntityId<E>)>;
+
+/// An event waiting to be dispatched to its handlers by [`Context::flush_events`].
+type QueuedEvent = Box<dyn FnOnce(&Context)>;
+
+/// The handlers registered on a `Context`.
+#[derive(Default)]
+pub(crate) struct EventHandlers {
+    /// If `true`, events are queued until `Context::flush_events` instead of dispatched at once.
+    deferred: bool,
+    queue: RefCell<Vec<QueuedEvent>>,
+    /// Maps a property `TypeId` to a `Vec<PropertyChangeHandler<E, P>>`.
+    property_change: HashMap<TypeId, Box<dyn Any>>,
+    /// Maps an entity `TypeId` to a `Vec<EntityHandler<E>>`.
+    entity_created: HashMap<TypeId, Box<dyn Any>>,
+    /// Maps an entity `TypeId` to a `Vec<EntityHandler<E>>`.
+    entity_removed: HashMap<TypeId, Box<dyn Any>>,
+}
+
+/// Returns the handlers of type `H` stored under `type_id`, in registration order.
+fn handlers<H: 'static>(handlers: &HashMap<TypeId, Box<dyn Any>>, type_id: TypeId) -> &[H] {
+    handlers
+        .get(&type_id)
+        .and_then(|handlers| handlers.downcast_ref::<Vec<H>>())
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Appends `handler` to the handlers of type `H` stored under `type_id`.
+fn push_handler<H: 'static>(handlers: &mut HashMap<TypeId, Box<dyn Any>>, type_id: TypeId, handler: H) {
+    handlers
+        .entry(type_id)
+        .or_insert_with(|| Box::new(Vec::<H>::new()))
+        .downcast_mut::<Vec<H>>()
+        .expect("event handlers have the wrong type")
+        .push(handler);
+}
+
+impl EventHandlers {
+    /// The handlers for changes to property `P`, in registration order.
+    pub(crate) fn property_change<E: Entity, P: Property<E>>(&self) -> &[PropertyChangeHandler<E, P>] {
+        handlers(&self.property_change, P::type_id())
+    }
+
+    /// The handlers for the creation of entities of type `E`, in registration order.
+    pub(crate) fn entity_created<E: Entity>(&self) -> &[EntityHandler<E>] {
+        handlers(&self.entity_created, <E as Entity>::type_id())
+    }
+
+    /// The handlers for the removal of entities of type `E`, in registration order.
+    pub(crate) fn entity_removed<E: Entity>(&self) -> &[EntityHandler<E>] {
+        handlers(&self.entity_removed, <E as Entity>::type_id())
+    }
+}
+
+impl Context {
+    /// Registers `handler` to be called whenever a value of property `P` is set on an existing
+    /// entity, after the new value has been stored. The handler receives the entity's previous
+    /// value and the new value. The previous value is `None` if an explicit property had never
+    /// been set, and the default value if a constant property had never been set.
+    ///
+    /// Values given in the property list of [`Context::add_entity`] don't trigger the handler.
+    pub fn subscribe_property_change<E: Entity, P: Property<E>>(
+        &mut self,
+        handler: impl Fn(&Context, EntityId<E>, Option<P>, P) + 'static,
+    ) {
+        push_handler::<PropertyChangeHandler<E, P>>(
+            &mut self.event_handlers.property_change,
+            P::type_id(),
+            Box::new(handler),
+        );
+    }
+
+    /// Registers `handler` to be called whenever an entity of type `E` is created, after all of
+    /// its initial property values have been set, so the handler can read them.
+    pub fn subscribe_entity_created<E: Entity>(
+        &mut self,
+        handler: impl Fn(&Context, EntityId<E>) + 'static,
+    ) {
+        push_handler::<EntityHandler<E>>(
+            &mut self.event_handlers.entity_created,
+            <E as Entity>::type_id(),
+            Box::new(handler),
+        );
+    }
+
+    /// Registers `handler` to be called whenever an entity of type `E` is removed with
+    /// [`Context::remove_entity`]. The handler runs before any of the entity's property values
+    /// are cleared, so it can read the entity's final values. Removed ids are never reused, so
+    /// the id remains a valid key for tearing down external references during and after the
+    /// handler.
+    pub fn subscribe_entity_removed<E: Entity>(
+        &mut self,
+        handler: impl Fn(&Context, EntityId<E>) + 'static,
+    ) {
+        push_handler::<EntityHandler<E>>(
+            &mut self.event_handlers.entity_removed,
+            <E as Entity>::type_id(),
+            Box::new(handler),
+        );
+    }
+
+    /// Switches between dispatching events immediately (the default) and queueing them until
+    /// [`Context::flush_events`]. Switching to immediate dispatch doesn't flush the queue.
+    pub fn set_deferred_events(&mut self, deferred: bool) {
+        self.event_handlers.deferred = deferred;
+    }
+
+    /// Dispatches queued events in the order they were queued, including any events queued by
+    /// the handlers themselves, until the queue is empty.
+    pub fn flush_events(&mut self) {
+        loop {
+            let queued = self.event_handlers.queue.take();
+            if queued.is_empty() {
+                break;
+            }
+            for event in queued {
+                event(self);
+            }
+        }
+    }
+
+    /// Drops the queued events without dispatching them, for when the entities they are about
+    /// are discarded.
+    pub(crate) fn discard_queued_events(&mut self) {
+        self.event_handlers.queue.get_mut().clear();
+    }
+
+    /// Runs `dispatch` now, or queues it if events are deferred.
+    fn dispatch(&self, dispatch: impl FnOnce(&Context) + 'static) {
+        if self.event_handlers.deferred {
+            self.event_handlers.queue.borrow_mut().push(Box::new(dispatch));
+        } else {
+            dispatch(self);
+        }
+    }
+
+    /// Calls the handlers for changes to property `P`.
+    pub(crate) fn emit_property_change<E: Entity, P: Property<E>>(
+        &self,
+        entity_id: EntityId<E>,
+        old_value: Option<P>,
+        new_value: P,
+    ) {
+        self.dispatch(move |context| {
+            for handler in context.event_handlers.property_change::<E, P>() {
+                handler(context, entity_id, old_value.clone(), new_value.clone());
+            }
+        });
+    }
+
+    /// Calls the handlers for the creation of entities of type `E`.
+    pub(crate) fn emit_entity_created<E: Entity>(&self, entity_id: EntityId<E>) {
+        self.dispatch(move |context| {
+            for handler in context.event_handlers.entity_created::<E>() {
+                handler(context, entity_id);
+            }
+        });
+    }
+
+    /// Calls the handlers for the removal of entities of type `E`. These are never deferred,
+    /// because the entity's values are cleared as soon as the handlers return.
+    pub(crate) fn emit_entity_removed<E: Entity>(&self, entity_id: EntityId<E>) {
+        for handler in self.event_handlers.entity_removed::<E>() {
+            handler(self, entity_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{define_entity, define_property};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+
+    define_property!(struct Weight(u16), Person);
+
+    #[test]
+    fn property_change_handlers_see_old_and_new_values() {
+        let mut context = Context::new();
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let recorded = changes.clone();
+        context.subscribe_property_change(move |_, person: PersonId, old: Option<Weight>, new| {
+            recorded.borrow_mut().push((person, old, new));
+        });
+
+        let person = context.add_entity((Age(30),));
+        context.set_property(person, Weight(70));
+        context.set_property(person, Weight(72));
+        context.update_property(person, |weight: Weight| Weight(weight.0 + 1));
+
+        assert_eq!(
+            *changes.borrow(),
+            vec![
+                (person, None, Weight(70)),
+                (person, Some(Weight(70)), Weight(72)),
+                (person, Some(Weight(72)), Weight(73)),
+            ]
+        );
+    }
+
+    #[test]
+    fn property_change_handlers_run_in_order_and_can_read_context() {
+        let mut context = Context::new();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        for handler_id in 0..2 {
+            let calls = calls.clone();
+            context.subscribe_property_change(
+                move |context: &Context, person: PersonId, old: Option<InfectionStatus>, new| {
+                    // The new value is already stored.
+                    assert_eq!(context.get_property::<_, InfectionStatus>(person), new);
+                    calls.borrow_mut().push((handler_id, old, new));
+                },
+            );
+        }
+
+        // Setting initial values when creating an entity doesn't count as a change.
+        let person = context.add_entity((Age(30), InfectionStatus::Infected));
+        assert!(calls.borrow().is_empty());
+
+        context.set_property(person, InfectionStatus::Recovered);
+        assert_eq!(
+            *calls.borrow(),
+            vec![
+                (0, Some(InfectionStatus::Infected), InfectionStatus::Recovered),
+                (1, Some(InfectionStatus::Infected), InfectionStatus::Recovered),
+            ]
+        );
+    }
+
+    #[test]
+    fn entity_created_handlers_fire_in_order() {
+        let mut context = Context::new();
+        let created = Rc::new(RefCell::new(Vec::new()));
+        for handler_id in 0..2 {
+            let created = created.clone();
+            context.subscribe_entity_created(move |context: &Context, person: PersonId| {
+                // The initial property values are already set.
+                let age: Age = context.get_property(person);
+                created.borrow_mut().push((handler_id, person, age));
+            });
+        }
+
+        let person1 = context.add_entity((Age(30),));
+        let person2 = context.add_entity((Age(40), Weight(80)));
+        // A failed creation doesn't fire the handlers.
+        assert!(context.try_add_entity((Weight(80),)).is_err());
+
+        assert_eq!(
+            *created.borrow(),
+            vec![
+                (0, person1, Age(30)),
+                (1, person1, Age(30)),
+                (0, person2, Age(40)),
+                (1, person2, Age(40)),
+            ]
+        );
+    }
+
+    #[test]
+    fn entity_removed_handlers_see_final_values_once() {
+        let mut context = Context::new();
+        let removed = Rc::new(RefCell::new(Vec::new()));
+        let recorded = removed.clone();
+        context.subscribe_entity_removed(move |context: &Context, person: PersonId| {
+            let age: Age = context.get_property(person);
+            let weight: Option<Weight> = context.try_get_property(person).ok();
+            recorded.borrow_mut().push((person, age, weight));
+        });
+
+        let person1 = context.add_entity((Age(30), Weight(70)));
+        let person2 = context.add_entity((Age(40),));
+
+        assert!(context.remove_entity(person1));
+        assert!(!context.remove_entity(person1));
+        assert_eq!(*removed.borrow(), vec![(person1, Age(30), Some(Weight(70)))]);
+
+        // The removed entity's values are cleared, and other entities are unaffected.
+        assert!(context.try_get_property::<_, Weight>(person1).is_err());
+        assert_eq!(context.get_property::<_, Age>(person2), Age(40));
+
+        // Ids aren't reused.
+        let person3 = context.add_entity((Age(50),));
+        assert_ne!(person3, person1);
+    }
+
+    #[test]
+    fn deferred_events_drain_to_a_fixed_point() {
+        let mut context = Context::new();
+        context.set_deferred_events(true);
+
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let recorded = calls.clone();
+        context.subscribe_property_change(
+            move |context: &Context, person: PersonId, old: Option<Weight>, new: Weight| {
+                recorded.borrow_mut().push((old, new));
+                // Each change queues another until the weight reaches 73.
+                if new.0 < 73 {
+                    context.set_property(person, Weight(new.0 + 1));
+                }
+            },
+        );
+
+        let person = context.add_entity((Age(30),));
+        context.set_property(person, Weight(70));
+        assert!(calls.borrow().is_empty());
+
+        context.flush_events();
+        assert_eq!(context.get_property::<_, Weight>(person), Weight(73));
+        assert_eq!(
+            *calls.borrow(),
+            vec![
+                (None, Weight(70)),
+                (Some(Weight(70)), Weight(71)),
+                (Some(Weight(71)), Weight(72)),
+                (Some(Weight(72)), Weight(73)),
+            ]
+        );
+
+        // Nothing is left in the queue.
+        context.flush_events();
+        assert_eq!(calls.borrow().len(), 4);
+    }
+
+    #[test]
+    fn reset_discards_queued_events() {
+        let mut context = Context::new();
+        context.set_deferred_events(true);
+
+        let created = Rc::new(RefCell::new(Vec::new()));
+        let recorded = created.clone();
+        context.subscribe_entity_created(move |_context: &Context, person: PersonId| {
+            recorded.borrow_mut().push(person);
+        });
+        let changes = Rc::new(RefCell::new(0));
+        let counted = changes.clone();
+        context.subscribe_property_change(
+            move |_context: &Context, _person: PersonId, _old: Option<Weight>, _new: Weight| {
+                *counted.borrow_mut() += 1;
+            },
+        );
+
+        let person = context.add_entity((Age(30),));
+        context.set_property(person, Weight(70));
+        context.reset();
+        context.flush_events();
+        assert!(created.borrow().is_empty());
+        assert_eq!(*changes.borrow(), 0);
+
+        // Events about the new population are still queued and dispatched.
+        let person = context.add_entity((Age(40),));
+        context.flush_events();
+        assert_eq!(*created.borrow(), [person]);
+    }
+}
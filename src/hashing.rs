@@ -0,0 +1,92 @@
+/*!
+
+Stable 128-bit hashes of property values, for building indices and for comparing populations
+across runs. See [`Property::hash_value`](crate::entity::property::Property::hash_value).
+
+The hashes use 128-bit FNV-1a, which, unlike `std`'s `DefaultHasher`, is fixed by its
+specification, so a hash computed today can be compared with one computed by a later build. By
+default a value is hashed by its JSON serialization, which, unlike its `Debug` output, is a
+stable format.
+
+*/
+
+use serde::Serialize;
+use serde_json::Value;
+
+const FNV_OFFSET_BASIS_128: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME_128: u128 = 0x0000000001000000000000000000013b;
+
+/// The 128-bit FNV-1a hash of `bytes`.
+pub fn fnv1a_128(bytes: &[u8]) -> u128 {
+    bytes.iter().fold(FNV_OFFSET_BASIS_128, |hash, &byte| {
+        (hash ^ byte as u128).wrapping_mul(FNV_PRIME_128)
+    })
+}
+
+/// Hashes `value` by its JSON serialization. This is the default for
+/// [`Property::hash_value`](crate::entity::property::Property::hash_value): values that are equal
+/// serialize the same way and so have the same hash. Floating point zeros are serialized without
+/// their sign first, since `0.0 == -0.0`.
+///
+/// Panics if `value` can't be serialized as JSON, e.g. a map whose keys aren't strings. Give such
+/// a property its own hash with the `hash_fn` option of `impl_property_with_options!`.
+pub fn hash_serialized_128<T: Serialize + ?Sized>(value: &T) -> u128 {
+    let mut json = serde_json::to_value(value)
+        .unwrap_or_else(|error| panic!("cannot hash a value that can't be serialized: {error}"));
+    clear_zero_signs(&mut json);
+    fnv1a_128(json.to_string().as_bytes())
+}
+
+/// Replaces every `-0.0` in `json` with `0.0`.
+fn clear_zero_signs(json: &mut Value) {
+    match json {
+        Value::Number(number) if number.as_f64() == Some(0.0) && number.is_f64() => {
+            *number = serde_json::Number::from_f64(0.0).expect("zero is finite");
+        }
+        Value::Array(values) => values.iter_mut().for_each(clear_zero_signs),
+        Value::Object(fields) => fields.values_mut().for_each(clear_zero_signs),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        define_entity, define_property, entity::property::Property, impl_property_with_options,
+    };
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person);
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct HouseholdId(u32);
+    impl_property_with_options!(HouseholdId, Person, hash_fn = |value: &HouseholdId| value.0 as u128);
+
+    #[test]
+    fn fnv1a_128_known_values() {
+        assert_eq!(fnv1a_128(b""), FNV_OFFSET_BASIS_128);
+        assert_eq!(fnv1a_128(b"a"), 0xd228cb696f1a8caf78912b704e4a8964);
+    }
+
+    #[test]
+    fn equal_values_hash_equally() {
+        assert_eq!(Age::hash_value(&Age(30)), Age::hash_value(&Age(30)));
+        assert_ne!(Age::hash_value(&Age(30)), Age::hash_value(&Age(31)));
+        assert_eq!(Age::hash_value(&Age(30)), hash_serialized_128(&Age(30)));
+
+        // Equal floats hash equally even when they are formatted differently.
+        assert_eq!(
+            hash_serialized_128(&(-0.0f64, [1.5, -0.0])),
+            hash_serialized_128(&(0.0f64, [1.5, 0.0]))
+        );
+        assert_ne!(hash_serialized_128(&0.5f64), hash_serialized_128(&-0.5f64));
+    }
+
+    #[test]
+    fn custom_hash_overrides_default() {
+        assert_eq!(HouseholdId::hash_value(&HouseholdId(7)), 7);
+        assert_ne!(HouseholdId::hash_value(&HouseholdId(7)), hash_serialized_128(&HouseholdId(7)));
+    }
+}
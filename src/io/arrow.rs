@@ -0,0 +1,445 @@
+/*!
+
+Exporting a population as an Arrow [`RecordBatch`], enabled with the `arrow` feature.
+
+```rust,ignore
+let batch = context.to_record_batch::<Person>();
+let ages = batch.column_by_name("Age").unwrap();
+```
+
+The batch has one row per live entity, in id order, and one column per property of the entity,
+named after the property and ordered by name. A column's Arrow type follows from how its values
+serialize with `serde`:
+
+| values                                   | Arrow type |
+|------------------------------------------|------------|
+| `bool`                                   | `Boolean`  |
+| signed integers                          | `Int64`    |
+| unsigned integers, and unit enum variants (as the variant index) | `UInt64` |
+| floats                                   | `Float64`  |
+| strings and `char`s                      | `Utf8`     |
+
+Newtype structs and `Option`s are looked through, so `struct Age(u8)` gives a `UInt64` column
+and `struct Dose(Option<u8>)` one with nulls for `None`. Values of any other shape, e.g. a struct
+with two fields, or values of different types in one column, are written as `Utf8` using
+[`Property::get_display`], as is a column in which every value is null.
+
+An explicit property that isn't set for an entity is null. Derived values are computed, and
+default function values are computed and stored, as by [`Context::try_get_property`].
+
+*/
+
+use std::{
+    fmt::{Display, Formatter},
+    sync::Arc,
+};
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, RecordBatchOptions, StringArray,
+    UInt64Array,
+};
+use arrow_schema::{Field, Schema};
+use serde::{
+    Serialize, Serializer,
+    ser::{self, Impossible},
+};
+
+use crate::{
+    Context,
+    entity::{
+        Entity, EntityId,
+        property::Property,
+        property_store::{RegisteredProperty, get_registered_property},
+    },
+};
+
+impl Context {
+    /// Builds an Arrow [`RecordBatch`] with one row per live entity of type `E`, in id order, and
+    /// one column per property of `E`, ordered by name. See the [module documentation](self) for
+    /// how values map to Arrow types.
+    pub fn to_record_batch<E: Entity>(&self) -> RecordBatch {
+        let (indices, columns) = self.arrow_columns::<E>();
+        let fields: Vec<Field> = columns
+            .iter()
+            .map(|(property, column)| {
+                Field::new(property.name, column.array.data_type().clone(), true)
+            })
+            .collect();
+        let arrays: Vec<ArrayRef> = columns.into_iter().map(|(_, column)| column.array).collect();
+        // The row count is given explicitly for an entity without properties.
+        let options = RecordBatchOptions::new().with_row_count(Some(indices.len()));
+        RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), arrays, &options)
+            .expect("every column has one row per entity")
+    }
+
+    /// Returns the indices of the live entities of type `E`, in id order, and the Arrow column
+    /// of each property of `E` for those entities, ordered by property name.
+    pub(crate) fn arrow_columns<E: Entity>(
+        &self,
+    ) -> (Vec<usize>, Vec<(RegisteredProperty, ArrowColumn)>) {
+        let indices: Vec<usize> = self
+            .entity_store
+            .live_entity_ids::<E>()
+            .map(|entity_id| entity_id.0)
+            .collect();
+        let mut properties: Vec<RegisteredProperty> = E::property_ids()
+            .iter()
+            .filter_map(|property_type_id| get_registered_property(*property_type_id))
+            .collect();
+        properties.sort_by_key(|property| property.name);
+        let columns = properties
+            .into_iter()
+            .map(|property| (property, (property.arrow_column)(self, &indices)))
+            .collect();
+        (indices, columns)
+    }
+}
+
+/// The Arrow column of one property.
+pub(crate) struct ArrowColumn {
+    pub(crate) array: ArrayRef,
+    /// Whether the values are the variant indices of an enum.
+    pub(crate) is_enum: bool,
+}
+
+/// Builds the Arrow column of property `P` for the entities with the given indices.
+pub(crate) fn arrow_property_column<E: Entity, P: Property<E>>(
+    context: &Context,
+    indices: &[usize],
+) -> ArrowColumn {
+    let values: Vec<Option<P>> = indices
+        .iter()
+        .map(|index| context.try_get_property::<E, P>(EntityId::new(*index)).ok())
+        .collect();
+    let scalars: Result<Vec<Option<Scalar>>, Unsupported> = values
+        .iter()
+        .map(|value| value.as_ref().map(|value| value.serialize(ScalarSerializer)).transpose())
+        .collect();
+    if let Some(column) = scalars.ok().and_then(scalar_column) {
+        return column;
+    }
+    ArrowColumn {
+        array: Arc::new(StringArray::from(
+            values
+                .iter()
+                .map(|value| value.as_ref().map(P::get_display))
+                .collect::<Vec<_>>(),
+        )),
+        is_enum: false,
+    }
+}
+
+/// A single value as it serializes, reduced to the types an Arrow column can hold.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Scalar {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    /// The index of a unit enum variant.
+    Variant(u64),
+    Float(f64),
+    Str(String),
+}
+
+/// Builds a column from `scalars` if all of the non-null ones have the same type, of which there
+/// is at least one.
+fn scalar_column(scalars: Vec<Option<Scalar>>) -> Option<ArrowColumn> {
+    let scalars: Vec<Option<Scalar>> = scalars
+        .into_iter()
+        .map(|scalar| scalar.filter(|scalar| *scalar != Scalar::Null))
+        .collect();
+    let first = scalars.iter().flatten().next()?;
+    let same_type = |scalar: &Scalar| std::mem::discriminant(scalar) == std::mem::discriminant(first);
+    if !scalars.iter().flatten().all(same_type) {
+        return None;
+    }
+
+    // Every non-null scalar has the type of `first`, so each closure below sees only that type.
+    macro_rules! column {
+        ($array:ident, $variant:ident) => {
+            Arc::new($array::from(
+                scalars
+                    .iter()
+                    .map(|scalar| match scalar {
+                        Some(Scalar::$variant(value)) => Some(value.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            )) as ArrayRef
+        };
+    }
+    let array = match first {
+        Scalar::Null => unreachable!("nulls were filtered out"),
+        Scalar::Bool(_) => column!(BooleanArray, Bool),
+        Scalar::Int(_) => column!(Int64Array, Int),
+        Scalar::UInt(_) => column!(UInt64Array, UInt),
+        Scalar::Variant(_) => column!(UInt64Array, Variant),
+        Scalar::Float(_) => column!(Float64Array, Float),
+        Scalar::Str(_) => column!(StringArray, Str),
+    };
+    Some(ArrowColumn {
+        array,
+        is_enum: matches!(first, Scalar::Variant(_)),
+    })
+}
+
+/// The error a [`ScalarSerializer`] returns for a value that isn't a [`Scalar`].
+#[derive(Debug)]
+struct Unsupported;
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "value has no scalar Arrow representation")
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+impl ser::Error for Unsupported {
+    fn custom<T: Display>(_msg: T) -> Self {
+        Unsupported
+    }
+}
+
+/// Serializes a value into a [`Scalar`], looking through newtype structs and `Option`s.
+struct ScalarSerializer;
+
+impl Serializer for ScalarSerializer {
+    type Ok = Scalar;
+    type Error = Unsupported;
+    type SerializeSeq = Impossible<Scalar, Unsupported>;
+    type SerializeTuple = Impossible<Scalar, Unsupported>;
+    type SerializeTupleStruct = Impossible<Scalar, Unsupported>;
+    type SerializeTupleVariant = Impossible<Scalar, Unsupported>;
+    type SerializeMap = Impossible<Scalar, Unsupported>;
+    type SerializeStruct = Impossible<Scalar, Unsupported>;
+    type SerializeStructVariant = Impossible<Scalar, Unsupported>;
+
+    fn serialize_bool(self, v: bool) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Int(v.into()))
+    }
+    fn serialize_i16(self, v: i16) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Int(v.into()))
+    }
+    fn serialize_i32(self, v: i32) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Int(v.into()))
+    }
+    fn serialize_i64(self, v: i64) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Int(v))
+    }
+    fn serialize_u8(self, v: u8) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::UInt(v.into()))
+    }
+    fn serialize_u16(self, v: u16) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::UInt(v.into()))
+    }
+    fn serialize_u32(self, v: u32) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::UInt(v.into()))
+    }
+    fn serialize_u64(self, v: u64) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::UInt(v))
+    }
+    fn serialize_f32(self, v: f32) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Float(v.into()))
+    }
+    fn serialize_f64(self, v: f64) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Float(v))
+    }
+    fn serialize_char(self, v: char) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Str(v.to_string()))
+    }
+    fn serialize_str(self, v: &str) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Str(v.to_string()))
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Scalar, Unsupported> {
+        Err(Unsupported)
+    }
+    fn serialize_none(self) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Null)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Scalar, Unsupported> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Null)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Null)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Scalar, Unsupported> {
+        Ok(Scalar::Variant(variant_index.into()))
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Scalar, Unsupported> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Scalar, Unsupported> {
+        Err(Unsupported)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Unsupported> {
+        Err(Unsupported)
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Unsupported> {
+        Err(Unsupported)
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Unsupported> {
+        Err(Unsupported)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Unsupported> {
+        Err(Unsupported)
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Unsupported> {
+        Err(Unsupported)
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Unsupported> {
+        Err(Unsupported)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Unsupported> {
+        Err(Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Array, BooleanArray, StringArray, UInt64Array};
+    use arrow_schema::DataType;
+
+    use crate::{
+        Context, define_entity, define_property, entity::property::PropertyInitializationKind,
+        impl_property_with_options,
+    };
+
+    define_entity!(Person);
+    define_entity!(Building);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+    define_property!(struct Weight(u16), Person);
+    define_property!(struct Name(String), Person);
+    define_property!(struct Location { x: i32, y: i32 }, Person);
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct IsAdult(bool);
+    impl_property_with_options!(
+        IsAdult,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            IsAdult(age.0 >= 18)
+        }
+    );
+
+    #[test]
+    fn record_batch_of_people() {
+        let mut context = Context::new();
+        context.add_entity((Age(12), Name("Ada".to_string())));
+        let removed = context.add_entity((Age(40),));
+        context.add_entity((Age(30), InfectionStatus::Recovered, Weight(70)));
+        assert!(context.remove_entity(removed));
+        context.add_entity((Age(65), Location { x: -1, y: 2 }));
+
+        let batch = context.to_record_batch::<Person>();
+        assert_eq!(batch.num_rows(), 3);
+        let schema = batch.schema();
+        let names: Vec<&str> = schema.fields().iter().map(|field| field.name().as_str()).collect();
+        assert_eq!(
+            names,
+            ["Age", "InfectionStatus", "IsAdult", "Location", "Name", "Weight"]
+        );
+        let data_types: Vec<&DataType> = schema.fields().iter().map(|field| field.data_type()).collect();
+        assert_eq!(
+            data_types,
+            [
+                &DataType::UInt64,
+                &DataType::UInt64,
+                &DataType::Boolean,
+                &DataType::Utf8,
+                &DataType::Utf8,
+                &DataType::UInt64,
+            ]
+        );
+
+        let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+        let ages = column("Age");
+        let ages = ages.as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(ages.values().to_vec(), [12, 30, 65]);
+
+        let statuses = column("InfectionStatus");
+        let statuses = statuses.as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(statuses.values().to_vec(), [0, 2, 0]);
+
+        let adults = column("IsAdult");
+        let adults = adults.as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert_eq!(adults.iter().collect::<Vec<_>>(), [Some(false), Some(true), Some(true)]);
+
+        let weights = column("Weight");
+        let weights = weights.as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(weights.iter().collect::<Vec<_>>(), [None, Some(70), None]);
+
+        let names = column("Name");
+        let names = names.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(names.iter().collect::<Vec<_>>(), [Some("Ada"), None, None]);
+
+        let locations = column("Location");
+        let locations = locations.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(locations.null_count(), 2);
+        assert_eq!(locations.value(2), "Location { x: -1, y: 2 }");
+    }
+
+    #[test]
+    fn record_batch_without_properties() {
+        let mut context = Context::new();
+        context.add_entity::<Building, _>(());
+        context.add_entity::<Building, _>(());
+        let batch = context.to_record_batch::<Building>();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.num_columns(), 0);
+    }
+}
@@ -0,0 +1,453 @@
+/*!
+
+Populating entities from a CSV file in which each column holds the values of one property, and
+exporting a single property column back out to CSV.
+
+The caller describes how to turn a column into a property with a [`CsvColumn`]:
+
+```rust,ignore
+let columns = [
+    CsvColumn::new("age", |s| s.parse().map(Age)),
+    CsvColumn::new("vaccinated", |s| s.parse().map(Vaccinated)),
+];
+let people: Vec<PersonId> = context.load_csv(reader, &columns)?;
+```
+
+Columns in the file that have no `CsvColumn` are ignored, as are `CsvColumn`s whose column
+does not appear in the header. Every required property of the entity must be populated by some
+column that is present, and no property by more than one, which is checked against the header
+before any row is loaded. Each parsed value is checked like a value passed to
+[`Context::add_entity`], e.g. that an entity it refers to exists.
+
+*/
+
+use std::{
+    any::TypeId,
+    fmt::{Display, Formatter},
+    io::{Read, Write},
+};
+
+use crate::{
+    Context, InvalidPropertyValueError,
+    entity::{
+        Entity, EntityId,
+        property::Property,
+        property_store::{PropertyStore, get_property_name},
+    },
+};
+
+/// A deferred assignment of one parsed property value to an entity that doesn't exist yet.
+pub type BxPropertySetter<E> = Box<dyn FnOnce(&PropertyStore, EntityId<E>)>;
+
+/// Why one field couldn't be turned into a [`BxPropertySetter`].
+enum FieldError {
+    /// The parser failed, with this message.
+    Parse(String),
+    /// The parsed value may not be stored.
+    InvalidValue(InvalidPropertyValueError),
+}
+
+/// Parses and checks one field, returning a [`BxPropertySetter`] that stores its value.
+type BxColumnParser<E> = Box<dyn Fn(&Context, &str) -> Result<BxPropertySetter<E>, FieldError>>;
+
+/// Describes how the values of one CSV column are parsed into a property of `E`.
+pub struct CsvColumn<E: Entity> {
+    column: String,
+    property_type_id: TypeId,
+    parse: BxColumnParser<E>,
+}
+
+impl<E: Entity> CsvColumn<E> {
+    /// Maps the column named `column` to the property `P` using the given parser.
+    pub fn new<P, F, Err>(column: &str, parse: F) -> Self
+    where
+        P: Property<E>,
+        F: Fn(&str) -> Result<P, Err> + 'static,
+        Err: Display,
+    {
+        Self {
+            column: column.to_string(),
+            property_type_id: P::type_id(),
+            parse: Box::new(move |context, field| {
+                let value = parse(field).map_err(|err| FieldError::Parse(err.to_string()))?;
+                InvalidPropertyValueError::check(context, &value).map_err(FieldError::InvalidValue)?;
+                Ok(Box::new(move |property_store: &PropertyStore, entity_id| {
+                    property_store.get::<E, P>().set(entity_id, value);
+                }) as BxPropertySetter<E>)
+            }),
+        }
+    }
+}
+
+/// The ways loading entities from a CSV file can fail.
+#[derive(Debug)]
+pub enum CsvError {
+    /// The underlying reader or the CSV syntax failed.
+    Csv(::csv::Error),
+    /// The header has no column for these required properties. No rows were loaded.
+    MissingRequiredProperties(Vec<&'static str>),
+    /// More than one column in the header is mapped to this property. No rows were loaded.
+    DuplicateProperty(&'static str),
+    /// A field could not be parsed into its property. Rows before `row` remain loaded.
+    Parse {
+        /// The 1-based data row, not counting the header.
+        row: usize,
+        column: String,
+        message: String,
+    },
+    /// A parsed value may not be stored, e.g. it refers to an entity that doesn't exist, or its
+    /// property is derived. Rows before `row` remain loaded.
+    InvalidValue {
+        /// The 1-based data row, not counting the header.
+        row: usize,
+        column: String,
+        error: InvalidPropertyValueError,
+    },
+}
+
+impl Display for CsvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvError::Csv(err) => write!(f, "CSV error: {err}"),
+            CsvError::MissingRequiredProperties(names) => write!(
+                f,
+                "CSV header has no column for required properties: {}",
+                names.join(", ")
+            ),
+            CsvError::DuplicateProperty(name) => {
+                write!(f, "CSV header has more than one column for property {name}")
+            }
+            CsvError::Parse {
+                row,
+                column,
+                message,
+            } => write!(f, "could not parse column \"{column}\" in row {row}: {message}"),
+            CsvError::InvalidValue { row, column, error } => {
+                write!(f, "column \"{column}\" in row {row}: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl From<::csv::Error> for CsvError {
+    fn from(err: ::csv::Error) -> Self {
+        CsvError::Csv(err)
+    }
+}
+
+impl Context {
+    /// Creates one entity of type `E` per row of the CSV data in `reader`, setting the
+    /// properties described by `columns`. Returns the ids of the new entities in row order.
+    ///
+    /// All fields of a row are parsed and checked before its entity is created, so a row that
+    /// fails never leaves a partially initialized entity behind. Each entity is created like one
+    /// added with [`Context::add_entity`]: defaults computed from its initial values are stored,
+    /// and entity-created handlers run.
+    pub fn load_csv<E: Entity>(
+        &mut self,
+        reader: impl Read,
+        columns: &[CsvColumn<E>],
+    ) -> Result<Vec<EntityId<E>>, CsvError> {
+        let mut reader = ::csv::Reader::from_reader(reader);
+        let header = reader.headers()?.clone();
+
+        // Pair each mapped column with its position in the header, dropping absent columns.
+        let present: Vec<(usize, &CsvColumn<E>)> = columns
+            .iter()
+            .filter_map(|column| {
+                header
+                    .iter()
+                    .position(|name| name == column.column)
+                    .map(|position| (position, column))
+            })
+            .collect();
+
+        // Check that all required properties are present before touching any rows.
+        let missing: Vec<&'static str> = E::required_property_ids()
+            .iter()
+            .filter(|id| !present.iter().any(|(_, column)| column.property_type_id == **id))
+            .map(|id| get_property_name(*id).unwrap_or("<unknown property>"))
+            .collect();
+        if !missing.is_empty() {
+            return Err(CsvError::MissingRequiredProperties(missing));
+        }
+        for (i, (_, column)) in present.iter().enumerate() {
+            if present[..i]
+                .iter()
+                .any(|(_, earlier)| earlier.property_type_id == column.property_type_id)
+            {
+                let name = get_property_name(column.property_type_id).unwrap_or("<unknown property>");
+                return Err(CsvError::DuplicateProperty(name));
+            }
+        }
+
+        let mut entity_ids = Vec::new();
+        for (row, record) in reader.records().enumerate() {
+            let record = record?;
+
+            let mut setters = Vec::with_capacity(present.len());
+            for (position, column) in &present {
+                let field = record.get(*position).unwrap_or_default();
+                let setter = (column.parse)(self, field).map_err(|error| match error {
+                    FieldError::Parse(message) => CsvError::Parse {
+                        row: row + 1,
+                        column: column.column.clone(),
+                        message,
+                    },
+                    FieldError::InvalidValue(error) => CsvError::InvalidValue {
+                        row: row + 1,
+                        column: column.column.clone(),
+                        error,
+                    },
+                })?;
+                setters.push(setter);
+            }
+
+            entity_ids.push(self.create_entity(
+                |property_store, entity_id| {
+                    for setter in setters {
+                        setter(property_store, entity_id);
+                    }
+                },
+                |property_type_id| {
+                    present
+                        .iter()
+                        .any(|(_, column)| column.property_type_id == property_type_id)
+                },
+            ));
+        }
+
+        Ok(entity_ids)
+    }
+
+    /// Writes the values of property `P` for every entity of type `E` to `writer` as a
+    /// single-column CSV with a header of `P::name()`, one row per entity in id order.
+    ///
+    /// Values are formatted with `P::get_display`. Derived properties are computed row by
+    /// row. Unset explicit values are written as an empty field, which the CSV writer quotes
+    /// (`""`) so that the row isn't mistaken for a blank line.
+    pub fn export_column<E: Entity, P: Property<E>>(
+        &self,
+        writer: impl Write,
+    ) -> Result<(), ::csv::Error> {
+        let mut writer = ::csv::Writer::from_writer(writer);
+        writer.write_record([P::name()])?;
+
+        for index in 0..self.get_entity_count::<E>() {
+            let value = self.try_get_property::<E, P>(EntityId::new(index));
+            let field = value.map(|value| value.get_display()).unwrap_or_default();
+            writer.write_record([field])?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        define_entity, define_properties, define_property,
+        entity::property::PropertyInitializationKind, impl_property_with_options,
+    };
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+
+    define_property!(
+        struct Vaccinated(bool),
+        Person,
+        default_const = Vaccinated(false)
+    );
+
+    define_property!(struct Weight(u16), Person);
+
+    define_property!(struct MentorOf(EntityId<Person>), Person);
+
+    define_properties!(Person {
+        enum AgeGroup {
+            Child,
+            Adult,
+        }, default_from = |context: &Context, person: PersonId| {
+            let age: Age = context.get_property(person);
+            if age.0 >= 18 { AgeGroup::Adult } else { AgeGroup::Child }
+        };
+    });
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct IsAdult(bool);
+    impl_property_with_options!(
+        IsAdult,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            IsAdult(age.0 >= 18)
+        }
+    );
+
+    fn columns() -> [CsvColumn<Person>; 2] {
+        [
+            CsvColumn::new("age", |s| s.parse().map(Age)),
+            CsvColumn::new("vaccinated", |s| s.parse().map(Vaccinated)),
+        ]
+    }
+
+    #[test]
+    fn load_people_from_csv() {
+        let mut context = Context::new();
+        let data = "age,vaccinated\n12,true\n34,false\n56,true\n";
+
+        let people = context.load_csv(data.as_bytes(), &columns()).unwrap();
+        assert_eq!(people.len(), 3);
+
+        let ages: Vec<Age> = people.iter().map(|p| context.get_property(*p)).collect();
+        assert_eq!(ages, vec![Age(12), Age(34), Age(56)]);
+
+        let vaccinated: Vec<Vaccinated> = people.iter().map(|p| context.get_property(*p)).collect();
+        assert_eq!(
+            vaccinated,
+            vec![Vaccinated(true), Vaccinated(false), Vaccinated(true)]
+        );
+    }
+
+    #[test]
+    fn load_csv_without_optional_column() {
+        let mut context = Context::new();
+        let data = "age\n40\n";
+
+        let people = context.load_csv(data.as_bytes(), &columns()).unwrap();
+        let vaccinated: Vaccinated = context.get_property(people[0]);
+        assert_eq!(vaccinated, Vaccinated(false));
+    }
+
+    #[test]
+    fn load_csv_missing_required_column() {
+        let mut context = Context::new();
+        let data = "vaccinated\ntrue\n";
+
+        match context.load_csv(data.as_bytes(), &columns()) {
+            Err(CsvError::MissingRequiredProperties(names)) => assert_eq!(names, vec!["Age"]),
+            other => panic!("expected a missing required property error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn load_csv_parse_error() {
+        let mut context = Context::new();
+        let data = "age,vaccinated\n12,true\nold,false\n";
+
+        match context.load_csv(data.as_bytes(), &columns()) {
+            Err(CsvError::Parse { row, column, .. }) => {
+                assert_eq!(row, 2);
+                assert_eq!(column, "age");
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn load_csv_computes_defaults_from_loaded_values() {
+        let mut context = Context::new();
+        let columns = [
+            CsvColumn::new("age", |s| s.parse().map(Age)),
+            CsvColumn::new("group", |s| match s {
+                "child" => Ok(AgeGroup::Child),
+                "adult" => Ok(AgeGroup::Adult),
+                _ => Err(format!("unknown age group {s}")),
+            }),
+        ];
+        let people = context.load_csv("age\n12\n34\n".as_bytes(), &columns).unwrap();
+        // The defaults are stored when the rows are loaded, from the loaded ages.
+        let store = context.property_store.get::<Person, AgeGroup>();
+        assert!(people.iter().all(|person| store.is_set(*person)));
+        assert_eq!(context.get_property::<_, AgeGroup>(people[0]), AgeGroup::Child);
+        assert_eq!(context.get_property::<_, AgeGroup>(people[1]), AgeGroup::Adult);
+
+        // A column for the property overrides its default.
+        let people = context.load_csv("age,group\n12,adult\n".as_bytes(), &columns).unwrap();
+        assert_eq!(context.get_property::<_, AgeGroup>(people[0]), AgeGroup::Adult);
+    }
+
+    #[test]
+    fn load_csv_rejects_derived_and_dangling_values() {
+        let mut context = Context::new();
+        let mentor = context.add_entity((Age(50),));
+        let columns = [
+            CsvColumn::new("age", |s| s.parse().map(Age)),
+            CsvColumn::new("adult", |s| s.parse().map(IsAdult)),
+        ];
+        match context.load_csv("age,adult\n12,true\n".as_bytes(), &columns) {
+            Err(CsvError::InvalidValue { row, column, error }) => {
+                assert_eq!((row, column.as_str()), (1, "adult"));
+                assert_eq!(error.property_name, "IsAdult");
+            }
+            other => panic!("expected an invalid value error, got {other:?}"),
+        }
+
+        let columns = [
+            CsvColumn::new("age", |s| s.parse().map(Age)),
+            CsvColumn::new("mentor", |s| s.parse().map(|index| MentorOf(PersonId::new(index)))),
+        ];
+        let data = "age,mentor\n12,0\n34,7\n";
+        match context.load_csv(data.as_bytes(), &columns) {
+            Err(CsvError::InvalidValue { row, column, .. }) => {
+                assert_eq!((row, column.as_str()), (2, "mentor"));
+            }
+            other => panic!("expected an invalid value error, got {other:?}"),
+        }
+        // The row before the invalid one was loaded.
+        assert_eq!(context.get_entity_count::<Person>(), 2);
+        assert_eq!(context.get_property::<_, MentorOf>(PersonId::new(1)), MentorOf(mentor));
+    }
+
+    #[test]
+    fn load_csv_duplicate_property_columns() {
+        let mut context = Context::new();
+        let columns = [
+            CsvColumn::new("age", |s| s.parse().map(Age)),
+            CsvColumn::new("age_years", |s| s.parse().map(Age)),
+        ];
+        match context.load_csv("age,age_years\n12,13\n".as_bytes(), &columns) {
+            Err(CsvError::DuplicateProperty(name)) => assert_eq!(name, "Age"),
+            other => panic!("expected a duplicate property error, got {other:?}"),
+        }
+        assert_eq!(context.get_entity_count::<Person>(), 0);
+
+        // A second column that isn't in the header isn't a duplicate.
+        let people = context.load_csv("age\n12\n".as_bytes(), &columns).unwrap();
+        assert_eq!(context.get_property::<_, Age>(people[0]), Age(12));
+    }
+
+    #[test]
+    fn export_column_to_csv() {
+        let mut context = Context::new();
+        let person1 = context.add_entity((Age(12), Weight(80)));
+        let _person2 = context.add_entity((Age(33), Vaccinated(true)));
+        let person3 = context.add_entity((Age(44), Weight(95)));
+        assert_eq!(context.get_property::<_, Age>(person1), Age(12));
+        assert_eq!(context.get_property::<_, Weight>(person3), Weight(95));
+
+        let mut output = Vec::new();
+        context.export_column::<Person, Weight>(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "Weight\nWeight(80)\n\"\"\nWeight(95)\n");
+
+        let mut output = Vec::new();
+        context.export_column::<Person, Vaccinated>(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "Vaccinated\nVaccinated(false)\nVaccinated(true)\nVaccinated(false)\n"
+        );
+
+        let mut output = Vec::new();
+        context.export_column::<Person, IsAdult>(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "IsAdult\nIsAdult(false)\nIsAdult(true)\nIsAdult(true)\n");
+    }
+}
@@ -0,0 +1,280 @@
+/*!
+
+Importing a population from a JSON array of objects, one object per entity.
+
+```rust,ignore
+let people: Vec<PersonId> = context.import_json(reader, UnknownFields::Error)?;
+```
+
+Each field of an object names a property of the entity and holds its value in the form `serde`
+deserializes it from, so `{"Age": 12, "InfectionStatus": "Infected"}` sets `Age(12)` and
+`InfectionStatus::Infected`. This is the form [`Context::export_jsonl`] writes, so the objects of
+a JSON lines export, wrapped in an array, can be imported again. Fields of derived properties,
+which an export includes, are skipped, since their values are computed from the others.
+
+Every object is checked before any entity is created: each must have a field for every required
+property, and every value must deserialize and pass [`InvalidPropertyValueError::check`]. Fields
+that aren't named after a property are an error or ignored, as chosen with [`UnknownFields`].
+
+*/
+
+use std::{
+    any::TypeId,
+    fmt::{Display, Formatter},
+    io::Read,
+};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    Context, InvalidPropertyValueError,
+    entity::{
+        Entity, EntityId,
+        property::{Property, PropertyInitializationKind},
+        property_store::{PropertyStore, get_property_name, get_registered_property},
+    },
+};
+
+/// A deferred assignment of one deserialized property value to the entity with a given index.
+pub(crate) type BxJsonSetter = Box<dyn FnOnce(&PropertyStore, usize)>;
+
+/// Deserializes `value` as a value of property `P` and checks it, returning a setter that stores
+/// it. Registered as a hook for each property.
+pub(crate) fn json_property_setter<E: Entity, P: Property<E>>(
+    context: &Context,
+    value: Value,
+) -> Result<BxJsonSetter, InvalidPropertyValueError> {
+    let value: P = serde_json::from_value(value).map_err(|err| InvalidPropertyValueError {
+        property_name: P::name(),
+        message: err.to_string(),
+    })?;
+    InvalidPropertyValueError::check(context, &value)?;
+    Ok(Box::new(move |property_store: &PropertyStore, index| {
+        property_store.get::<E, P>().set(EntityId::new(index), value);
+    }))
+}
+
+/// What [`Context::import_json`] does with a field that isn't named after a property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnknownFields {
+    /// Fail with [`JsonImportError::UnknownProperty`].
+    Error,
+    /// Skip the field.
+    Ignore,
+}
+
+/// The ways importing entities from JSON can fail. Nothing is imported if any object fails.
+#[derive(Debug)]
+pub enum JsonImportError {
+    /// The reader failed, or its contents aren't an array of objects.
+    Json(serde_json::Error),
+    /// An object has a field that isn't named after a property of the entity.
+    UnknownProperty {
+        /// The 0-based position of the object in the array.
+        index: usize,
+        name: String,
+    },
+    /// An object has no field for these required properties.
+    MissingRequiredProperties {
+        index: usize,
+        names: Vec<&'static str>,
+    },
+    /// A field's value isn't a valid value of its property.
+    InvalidValue {
+        index: usize,
+        error: InvalidPropertyValueError,
+    },
+}
+
+impl Display for JsonImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonImportError::Json(err) => write!(f, "JSON error: {err}"),
+            JsonImportError::UnknownProperty { index, name } => {
+                write!(f, "object {index} has unknown property \"{name}\"")
+            }
+            JsonImportError::MissingRequiredProperties { index, names } => write!(
+                f,
+                "object {index} has no value for required properties: {}",
+                names.join(", ")
+            ),
+            JsonImportError::InvalidValue { index, error } => write!(f, "object {index}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for JsonImportError {}
+
+impl From<serde_json::Error> for JsonImportError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonImportError::Json(err)
+    }
+}
+
+impl Context {
+    /// Creates one entity of type `E` per object in the JSON array in `reader`, setting the
+    /// properties named by the object's fields. Returns the ids of the new entities in array
+    /// order. See the [module documentation](self).
+    pub fn import_json<E: Entity>(
+        &mut self,
+        reader: impl Read,
+        unknown_fields: UnknownFields,
+    ) -> Result<Vec<EntityId<E>>, JsonImportError> {
+        let objects: Vec<Map<String, Value>> = serde_json::from_reader(reader)?;
+
+        let mut rows = Vec::with_capacity(objects.len());
+        for (index, object) in objects.into_iter().enumerate() {
+            let mut assigned: Vec<TypeId> = Vec::with_capacity(object.len());
+            let mut setters = Vec::with_capacity(object.len());
+            for (name, value) in object {
+                let Some((property_type_id, property)) = E::property_ids()
+                    .iter()
+                    .filter_map(|property_type_id| {
+                        get_registered_property(*property_type_id).map(|property| (*property_type_id, property))
+                    })
+                    .find(|(_, property)| property.name == name)
+                else {
+                    match unknown_fields {
+                        UnknownFields::Error => {
+                            return Err(JsonImportError::UnknownProperty { index, name });
+                        }
+                        UnknownFields::Ignore => continue,
+                    }
+                };
+                if property.initialization_kind == PropertyInitializationKind::Derived {
+                    continue;
+                }
+                let setter = (property.json_setter)(self, value)
+                    .map_err(|error| JsonImportError::InvalidValue { index, error })?;
+                assigned.push(property_type_id);
+                setters.push(setter);
+            }
+
+            let missing: Vec<&'static str> = E::required_property_ids()
+                .iter()
+                .filter(|property_type_id| !assigned.contains(property_type_id))
+                .map(|property_type_id| get_property_name(*property_type_id).unwrap_or("<unknown property>"))
+                .collect();
+            if !missing.is_empty() {
+                return Err(JsonImportError::MissingRequiredProperties { index, names: missing });
+            }
+            rows.push((assigned, setters));
+        }
+
+        let mut entity_ids = Vec::with_capacity(rows.len());
+        for (assigned, setters) in rows {
+            entity_ids.push(self.create_entity(
+                |property_store, entity_id: EntityId<E>| {
+                    for setter in setters {
+                        setter(property_store, entity_id.0);
+                    }
+                },
+                |property_type_id| assigned.contains(&property_type_id),
+            ));
+        }
+        Ok(entity_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{define_entity, define_property, impl_property_with_options};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+    define_property!(struct Name(String), Person);
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct IsAdult(bool);
+    impl_property_with_options!(
+        IsAdult,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            IsAdult(age.0 >= 18)
+        }
+    );
+
+    #[test]
+    fn import_two_people() {
+        let mut context = Context::new();
+        let json = r#"[
+            {"Age": 12, "Name": "Ada"},
+            {"Age": 30, "InfectionStatus": "Recovered"}
+        ]"#;
+        let people = context
+            .import_json::<Person>(json.as_bytes(), UnknownFields::Error)
+            .unwrap();
+        assert_eq!(people, [PersonId::new(0), PersonId::new(1)]);
+
+        assert_eq!(context.get_property::<_, Age>(people[0]), Age(12));
+        assert_eq!(
+            context.get_property::<_, InfectionStatus>(people[0]),
+            InfectionStatus::Susceptible
+        );
+        assert_eq!(context.get_property::<_, Name>(people[0]), Name("Ada".to_string()));
+        assert_eq!(context.get_property::<_, Age>(people[1]), Age(30));
+        assert_eq!(
+            context.get_property::<_, InfectionStatus>(people[1]),
+            InfectionStatus::Recovered
+        );
+        assert!(!context.is_property_set::<_, Name>(people[1]));
+    }
+
+    #[test]
+    fn import_checks_every_object_first() {
+        let mut context = Context::new();
+        let json = r#"[{"Age": 12, "Height": 150}, {"Name": "Ada"}]"#;
+
+        match context.import_json::<Person>(json.as_bytes(), UnknownFields::Error) {
+            Err(JsonImportError::UnknownProperty { index, name }) => {
+                assert_eq!((index, name.as_str()), (0, "Height"));
+            }
+            result => panic!("expected an unknown property, got {result:?}"),
+        }
+        match context.import_json::<Person>(json.as_bytes(), UnknownFields::Ignore) {
+            Err(JsonImportError::MissingRequiredProperties { index, names }) => {
+                assert_eq!((index, names), (1, vec!["Age"]));
+            }
+            result => panic!("expected a missing required property, got {result:?}"),
+        }
+        match context.import_json::<Person>(r#"[{"Age": -1}]"#.as_bytes(), UnknownFields::Error) {
+            Err(JsonImportError::InvalidValue { index: 0, error }) => {
+                assert_eq!(error.property_name, "Age");
+            }
+            result => panic!("expected an invalid value, got {result:?}"),
+        }
+        assert_eq!(context.get_entity_count::<Person>(), 0);
+    }
+
+    #[test]
+    fn import_a_json_lines_export() {
+        let mut context = Context::new();
+        context.add_entity((Age(12), Name("Ada".to_string())));
+        context.add_entity((Age(30), InfectionStatus::Recovered));
+        let mut output = Vec::new();
+        context.export_jsonl::<Person>(&mut output).unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+        let json = format!("[{}]", lines.join(","));
+        assert!(json.contains(r#""IsAdult":false"#));
+
+        let mut imported = Context::new();
+        let people = imported
+            .import_json::<Person>(json.as_bytes(), UnknownFields::Error)
+            .unwrap();
+        assert!(imported.diff::<Person>(&context).unwrap().is_empty());
+        assert_eq!(imported.get_property::<_, IsAdult>(people[1]), IsAdult(true));
+    }
+}
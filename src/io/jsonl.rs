@@ -0,0 +1,124 @@
+/*!
+
+Streaming a population out as JSON lines: one JSON object per entity, one entity per line.
+
+```rust,ignore
+context.export_jsonl::<Person>(BufWriter::new(File::create("people.jsonl")?))?;
+```
+
+Each object maps the names of the entity's properties to their values, serialized with `serde`,
+so `struct Age(u8)` is written as a number and a unit enum variant as its name. Only one entity's
+object is built at a time, so memory use doesn't grow with the population.
+
+*/
+
+use std::io::Write;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    Context,
+    entity::{
+        Entity, EntityId,
+        property::Property,
+        property_store::{RegisteredProperty, get_registered_property},
+    },
+};
+
+/// Serializes the value of property `P` for the entity with the given index, or returns `None`
+/// if it isn't set. Registered as a hook for each property.
+pub(crate) fn json_property_value<E: Entity, P: Property<E>>(
+    context: &Context,
+    index: usize,
+) -> Option<serde_json::Result<Value>> {
+    context
+        .try_get_property::<E, P>(EntityId::new(index))
+        .ok()
+        .map(serde_json::to_value)
+}
+
+impl Context {
+    /// Writes one line to `writer` for each live entity of type `E`, in id order, holding a JSON
+    /// object of its property values, and flushes `writer` after each line. Derived values are
+    /// computed, and unset explicit values are left out of the object.
+    pub fn export_jsonl<E: Entity>(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let properties: Vec<RegisteredProperty> = E::property_ids()
+            .iter()
+            .filter_map(|property_type_id| get_registered_property(*property_type_id))
+            .collect();
+
+        for entity_id in self.entity_store.live_entity_ids::<E>() {
+            let mut object = Map::new();
+            for property in &properties {
+                if let Some(value) = (property.json_value)(self, entity_id.0) {
+                    object.insert(property.name.to_string(), value?);
+                }
+            }
+            serde_json::to_writer(&mut writer, &object)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        define_entity, define_property, entity::property::PropertyInitializationKind,
+        impl_property_with_options,
+    };
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+    define_property!(struct Name(String), Person);
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct IsAdult(bool);
+    impl_property_with_options!(
+        IsAdult,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            IsAdult(age.0 >= 18)
+        }
+    );
+
+    #[test]
+    fn export_people_as_json_lines() {
+        let mut context = Context::new();
+        context.add_entity((Age(12), Name("Ada".to_string())));
+        let removed = context.add_entity((Age(40),));
+        context.add_entity((Age(30), InfectionStatus::Recovered));
+        assert!(context.remove_entity(removed));
+
+        let mut output = Vec::new();
+        context.export_jsonl::<Person>(&mut output).unwrap();
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                json!({"Age": 12, "InfectionStatus": "Susceptible", "IsAdult": false, "Name": "Ada"}),
+                json!({"Age": 30, "InfectionStatus": "Recovered", "IsAdult": true}),
+            ]
+        );
+    }
+}
@@ -0,0 +1,21 @@
+/*!
+
+Reading and writing entity populations from and to external formats.
+
+Each submodule adds an `impl Context` block with the methods for one format. Reading a
+population goes through the same `PropertyValueStore`s as `Context::set_property`, so
+values loaded from a file are indistinguishable from values set in code.
+
+*/
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod csv;
+pub mod json;
+pub mod jsonl;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "polars")]
+pub mod polars;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
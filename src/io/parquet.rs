@@ -0,0 +1,417 @@
+/*!
+
+Writing a population to a Parquet file and reading it back, enabled with the `parquet` feature.
+
+```rust,ignore
+context.write_parquet::<Person>(File::create("people.parquet")?)?;
+let people = other_context.read_parquet::<Person>(File::open("people.parquet")?)?;
+```
+
+[`Context::write_parquet`] writes the record batch of [`Context::to_record_batch`], so the file
+has one column per property, named after it, with the Arrow types described in the
+[`arrow`](super::arrow) module. [`Context::read_parquet`] adds one entity per row, deserializing
+each value from its column with `serde`.
+
+Like the snapshot loader, the reader checks the file's schema before adding anything: every
+column must be named after a property of the entity, and every required property must have a
+column with no nulls. Columns of derived properties are ignored, since their values are
+computed. A null is read as an unset value, so a property such as `struct Dose(Option<u8>)`
+reads `Dose(None)` back as unset. Values written as text with [`Property::get_display`], such
+as structs with several fields, can't be read back.
+
+*/
+
+use std::{
+    any::{Any, TypeId},
+    fmt::{Display, Formatter},
+    io::Write,
+};
+
+use arrow_array::{
+    Array, RecordBatchReader,
+    cast::AsArray,
+    types::{Float64Type, Int64Type, UInt64Type},
+};
+use arrow_schema::DataType;
+use ::parquet::{
+    arrow::{ArrowWriter, arrow_reader::ParquetRecordBatchReaderBuilder},
+    file::reader::ChunkReader,
+};
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, IntoDeserializer, Visitor, value::Error as DeError},
+    forward_to_deserialize_any,
+};
+
+use crate::{
+    Context, InvalidPropertyValueError,
+    entity::{
+        Entity, EntityId,
+        property::{Property, PropertyInitializationKind},
+        property_store::{PropertyStore, RegisteredProperty, get_registered_property},
+    },
+    io::arrow::Scalar,
+};
+
+/// The ways writing or reading a Parquet file can fail.
+#[derive(Debug)]
+pub enum ParquetError {
+    /// Encoding or decoding the file, or reading or writing it, failed.
+    Parquet(::parquet::errors::ParquetError),
+    /// The file has a column that isn't named after a property of the entity.
+    UnknownProperty(String),
+    /// The file has no column, or a column with nulls, for a required property.
+    MissingRequiredProperty(&'static str),
+    /// A value couldn't be deserialized as a value of its property, or is rejected by it.
+    InvalidValue(InvalidPropertyValueError),
+}
+
+impl Display for ParquetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParquetError::Parquet(err) => write!(f, "Parquet error: {err}"),
+            ParquetError::UnknownProperty(name) => {
+                write!(f, "Parquet file contains unknown property \"{name}\"")
+            }
+            ParquetError::MissingRequiredProperty(name) => {
+                write!(f, "Parquet file is missing values of required property \"{name}\"")
+            }
+            ParquetError::InvalidValue(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for ParquetError {}
+
+impl From<::parquet::errors::ParquetError> for ParquetError {
+    fn from(err: ::parquet::errors::ParquetError) -> Self {
+        ParquetError::Parquet(err)
+    }
+}
+
+/// The values of one property read from a Parquet file, waiting to be assigned, with functions
+/// monomorphized for the property when it was read, like the hooks of a `RegisteredProperty`.
+pub(crate) struct ReadColumn {
+    property_type_id: TypeId,
+    /// A `Vec<Option<P>>` with one value per row.
+    values: Box<dyn Any>,
+    /// Returns whether the value in the given row is set.
+    is_set: fn(&dyn Any, usize) -> bool,
+    /// Sets the value in the given row, if it is set, for the entity with the given index.
+    assign: fn(&PropertyStore, &dyn Any, usize, usize),
+}
+
+/// Reads the values of property `P` from `array` and checks them with
+/// [`InvalidPropertyValueError::check`]. Registered as a hook for each property.
+pub(crate) fn read_property_column<E: Entity, P: Property<E>>(
+    context: &Context,
+    array: &dyn Array,
+) -> Result<ReadColumn, InvalidPropertyValueError> {
+    let invalid = |message: String| InvalidPropertyValueError {
+        property_name: P::name(),
+        message,
+    };
+    let values: Vec<Option<P>> = column_scalars(array)
+        .map_err(invalid)?
+        .into_iter()
+        .map(|scalar| {
+            scalar
+                .map(|scalar| P::deserialize(ScalarDeserializer(scalar)))
+                .transpose()
+                .map_err(|err| invalid(err.to_string()))
+        })
+        .collect::<Result<_, _>>()?;
+    for value in values.iter().flatten() {
+        InvalidPropertyValueError::check(context, value)?;
+    }
+    Ok(ReadColumn {
+        property_type_id: <P as Property<E>>::type_id(),
+        values: Box::new(values),
+        is_set: is_read_value_set::<P>,
+        assign: assign_read_value::<E, P>,
+    })
+}
+
+fn is_read_value_set<P: 'static>(values: &dyn Any, row: usize) -> bool {
+    let values: &Vec<Option<P>> = values.downcast_ref().expect("read values have the wrong type");
+    values[row].is_some()
+}
+
+fn assign_read_value<E: Entity, P: Property<E>>(
+    property_store: &PropertyStore,
+    values: &dyn Any,
+    row: usize,
+    index: usize,
+) {
+    let values: &Vec<Option<P>> = values.downcast_ref().expect("read values have the wrong type");
+    if let Some(value) = &values[row] {
+        property_store.get::<E, P>().set(EntityId::new(index), value.clone());
+    }
+}
+
+/// Converts the values of a column into [`Scalar`]s, with `None` for nulls. Unsigned integers
+/// become `Scalar::UInt`, which also deserializes as an enum variant index.
+fn column_scalars(array: &dyn Array) -> Result<Vec<Option<Scalar>>, String> {
+    let scalars = match array.data_type() {
+        DataType::Boolean => array.as_boolean().iter().map(|value| value.map(Scalar::Bool)).collect(),
+        DataType::Int64 => array
+            .as_primitive::<Int64Type>()
+            .iter()
+            .map(|value| value.map(Scalar::Int))
+            .collect(),
+        DataType::UInt64 => array
+            .as_primitive::<UInt64Type>()
+            .iter()
+            .map(|value| value.map(Scalar::UInt))
+            .collect(),
+        DataType::Float64 => array
+            .as_primitive::<Float64Type>()
+            .iter()
+            .map(|value| value.map(Scalar::Float))
+            .collect(),
+        DataType::Utf8 => array
+            .as_string::<i32>()
+            .iter()
+            .map(|value| value.map(|value| Scalar::Str(value.to_string())))
+            .collect(),
+        data_type => return Err(format!("a column of type {data_type} can't be read")),
+    };
+    Ok(scalars)
+}
+
+/// Deserializes a value from a [`Scalar`], looking through newtype structs and `Option`s, the
+/// inverse of the serializer of the Arrow export.
+struct ScalarDeserializer(Scalar);
+
+impl<'de> Deserializer<'de> for ScalarDeserializer {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Scalar::Null => visitor.visit_unit(),
+            Scalar::Bool(v) => visitor.visit_bool(v),
+            Scalar::Int(v) => visitor.visit_i64(v),
+            Scalar::UInt(v) | Scalar::Variant(v) => visitor.visit_u64(v),
+            Scalar::Float(v) => visitor.visit_f64(v),
+            Scalar::Str(v) => visitor.visit_string(v),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Scalar::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        match self.0 {
+            Scalar::UInt(index) | Scalar::Variant(index) => {
+                let index = u32::try_from(index)
+                    .map_err(|_| de::Error::custom(format!("variant index {index} is out of range")))?;
+                visitor.visit_enum(index.into_deserializer())
+            }
+            Scalar::Str(name) => visitor.visit_enum(name.into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl Context {
+    /// Writes the values of every property of the live entities of type `E` to `writer` as a
+    /// Parquet file. See the [module documentation](self).
+    pub fn write_parquet<E: Entity>(&self, writer: impl Write + Send) -> Result<(), ParquetError> {
+        let batch = self.to_record_batch::<E>();
+        let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+
+    /// Adds an entity of type `E` for each row of a Parquet file written by
+    /// [`Context::write_parquet`], with the values in that row, and returns their ids in row
+    /// order. Fails without adding anything if the file's schema doesn't match `E` or a value
+    /// can't be read.
+    pub fn read_parquet<E: Entity>(
+        &mut self,
+        reader: impl ChunkReader + 'static,
+    ) -> Result<Vec<EntityId<E>>, ParquetError> {
+        let batches = ParquetRecordBatchReaderBuilder::try_new(reader)?.build()?;
+
+        // Resolve the columns against the properties of `E`.
+        let schema = batches.schema();
+        let mut properties: Vec<Option<RegisteredProperty>> = Vec::with_capacity(schema.fields().len());
+        for field in schema.fields() {
+            let property = Self::find_property_by_name::<E>(field.name())
+                .ok_or_else(|| ParquetError::UnknownProperty(field.name().clone()))?;
+            let is_derived = property.initialization_kind == PropertyInitializationKind::Derived;
+            properties.push((!is_derived).then_some(property));
+        }
+        for property_type_id in E::required_property_ids() {
+            let required = get_registered_property(*property_type_id)
+                .expect("required properties are registered");
+            if !properties.iter().flatten().any(|property| property.index == required.index) {
+                return Err(ParquetError::MissingRequiredProperty(required.name));
+            }
+        }
+
+        // Read and check every value before adding any entity.
+        let mut read_batches: Vec<(usize, Vec<ReadColumn>)> = Vec::new();
+        for batch in batches {
+            let batch = batch.map_err(|err| ParquetError::Parquet(err.into()))?;
+            let mut columns = Vec::new();
+            for (property, array) in properties.iter().zip(batch.columns()) {
+                let Some(property) = property else {
+                    continue;
+                };
+                let column = (property.read_parquet_column)(self, array.as_ref())
+                    .map_err(ParquetError::InvalidValue)?;
+                if property.is_required
+                    && !(0..batch.num_rows()).all(|row| (column.is_set)(column.values.as_ref(), row))
+                {
+                    return Err(ParquetError::MissingRequiredProperty(property.name));
+                }
+                columns.push(column);
+            }
+            read_batches.push((batch.num_rows(), columns));
+        }
+
+        let mut entity_ids = Vec::new();
+        for (num_rows, columns) in &read_batches {
+            for row in 0..*num_rows {
+                entity_ids.push(self.create_entity(
+                    |property_store, entity_id: EntityId<E>| {
+                        for column in columns {
+                            (column.assign)(property_store, column.values.as_ref(), row, entity_id.0);
+                        }
+                    },
+                    |property_type_id| {
+                        columns.iter().any(|column| {
+                            column.property_type_id == property_type_id
+                                && (column.is_set)(column.values.as_ref(), row)
+                        })
+                    },
+                ));
+            }
+        }
+        Ok(entity_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{define_entity, define_property, impl_property_with_options};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+    define_property!(struct Weight(u16), Person);
+    define_property!(struct Name(String), Person);
+    define_property!(struct Balance(i32), Person);
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct IsAdult(bool);
+    impl_property_with_options!(
+        IsAdult,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            IsAdult(age.0 >= 18)
+        }
+    );
+
+    define_entity!(Household);
+
+    define_property!(struct Size(u8), Household, is_required = true);
+
+    fn write(context: &Context) -> Bytes {
+        let mut buffer = Vec::new();
+        context.write_parquet::<Person>(&mut buffer).unwrap();
+        Bytes::from(buffer)
+    }
+
+    #[test]
+    fn parquet_round_trip() {
+        let mut context = Context::new();
+        context.add_entity((Age(12), Name("Ada".to_string())));
+        let removed = context.add_entity((Age(40),));
+        context.add_entity((Age(30), InfectionStatus::Recovered, Weight(70), Balance(-20)));
+        assert!(context.remove_entity(removed));
+
+        let mut restored = Context::new();
+        let people = restored.read_parquet::<Person>(write(&context)).unwrap();
+        assert_eq!(people, [PersonId::new(0), PersonId::new(1)]);
+
+        for (person, original) in people.into_iter().zip([PersonId::new(0), PersonId::new(2)]) {
+            assert_eq!(
+                restored.get_property::<_, Age>(person),
+                context.get_property::<_, Age>(original)
+            );
+            assert_eq!(
+                restored.get_property::<_, InfectionStatus>(person),
+                context.get_property::<_, InfectionStatus>(original)
+            );
+            assert_eq!(
+                restored.get_property::<_, IsAdult>(person),
+                context.get_property::<_, IsAdult>(original)
+            );
+            assert_eq!(
+                restored.property_store.get::<_, Weight>().get(person),
+                context.property_store.get::<_, Weight>().get(original)
+            );
+            assert_eq!(
+                restored.property_store.get::<_, Name>().get(person),
+                context.property_store.get::<_, Name>().get(original)
+            );
+            assert_eq!(
+                restored.property_store.get::<_, Balance>().get(person),
+                context.property_store.get::<_, Balance>().get(original)
+            );
+        }
+    }
+
+    #[test]
+    fn parquet_schema_is_checked() {
+        let mut context = Context::new();
+        context.add_entity((Age(12),));
+        let bytes = write(&context);
+
+        let mut other = Context::new();
+        match other.read_parquet::<Household>(bytes) {
+            Err(ParquetError::UnknownProperty(name)) => assert_eq!(name, "Age"),
+            result => panic!("expected an unknown property, got {result:?}"),
+        }
+        assert_eq!(other.get_entity_count::<Household>(), 0);
+    }
+}
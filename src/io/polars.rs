@@ -0,0 +1,135 @@
+/*!
+
+Converting a population to a Polars [`DataFrame`], enabled with the `polars` feature.
+
+```rust,ignore
+let people = context.to_dataframe::<Person>();
+let counts = people.group_by(["InfectionStatus"])?.select(["Age"]).count()?;
+```
+
+The frame is built from the Arrow columns of [`Context::to_record_batch`], so it has the same
+rows and columns, with the same types, except that the column of an enum property holds the
+variant names, formatted with [`Property::get_display`], instead of the variant indices.
+
+*/
+
+use arrow_array::{Array, BooleanArray, Float64Array, Int64Array, StringArray, UInt64Array};
+use polars::prelude::{Column, DataFrame, NamedFrom, Series};
+
+use crate::{
+    Context,
+    entity::{Entity, property::Property},
+};
+
+impl Context {
+    /// Builds a Polars [`DataFrame`] with one row per live entity of type `E`, in id order, and
+    /// one column per property of `E`, ordered by name. See the [module documentation](self).
+    pub fn to_dataframe<E: Entity>(&self) -> DataFrame {
+        let (indices, columns) = self.arrow_columns::<E>();
+        if columns.is_empty() {
+            return DataFrame::empty_with_height(indices.len());
+        }
+        let columns: Vec<Column> = columns
+            .into_iter()
+            .map(|(property, column)| {
+                let series = if column.is_enum {
+                    let names: Vec<Option<String>> = indices
+                        .iter()
+                        .map(|index| (property.display_value)(self, *index))
+                        .collect();
+                    Series::new(property.name.into(), names)
+                } else {
+                    arrow_to_series(property.name, column.array.as_ref())
+                };
+                Column::from(series)
+            })
+            .collect();
+        DataFrame::new(columns).expect("every column has one row per entity")
+    }
+}
+
+/// Copies an Arrow column built by `arrow_property_column` into a `Series`.
+fn arrow_to_series(name: &str, array: &dyn Array) -> Series {
+    let array = array.as_any();
+    if let Some(array) = array.downcast_ref::<BooleanArray>() {
+        return Series::new(name.into(), array.iter().collect::<Vec<_>>());
+    }
+    if let Some(array) = array.downcast_ref::<Int64Array>() {
+        return Series::new(name.into(), array.iter().collect::<Vec<_>>());
+    }
+    if let Some(array) = array.downcast_ref::<UInt64Array>() {
+        return Series::new(name.into(), array.iter().collect::<Vec<_>>());
+    }
+    if let Some(array) = array.downcast_ref::<Float64Array>() {
+        return Series::new(name.into(), array.iter().collect::<Vec<_>>());
+    }
+    if let Some(array) = array.downcast_ref::<StringArray>() {
+        return Series::new(name.into(), array.iter().collect::<Vec<_>>());
+    }
+    unreachable!("property columns have one of the types `arrow_property_column` builds")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use polars::prelude::DataType;
+
+    use crate::{Context, define_entity, define_property};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+    define_property!(struct Weight(u16), Person);
+
+    #[test]
+    fn dataframe_group_by() {
+        let mut context = Context::new();
+        for (age, status) in [
+            (8, InfectionStatus::Infected),
+            (15, InfectionStatus::Susceptible),
+            (30, InfectionStatus::Infected),
+            (42, InfectionStatus::Recovered),
+            (67, InfectionStatus::Infected),
+        ] {
+            context.add_entity((Age(age), status));
+        }
+        context.set_property(PersonId::new(2), Weight(70));
+
+        let people = context.to_dataframe::<Person>();
+        assert_eq!(people.height(), 5);
+        assert_eq!(people.get_column_names(), ["Age", "InfectionStatus", "Weight"]);
+        assert_eq!(
+            people.dtypes(),
+            [DataType::UInt64, DataType::String, DataType::UInt64]
+        );
+        assert_eq!(people.column("Weight").unwrap().null_count(), 4);
+
+        let counts = people
+            .group_by(["InfectionStatus"])
+            .unwrap()
+            .select(["Age"])
+            .count()
+            .unwrap();
+        let statuses = counts.column("InfectionStatus").unwrap().str().unwrap();
+        let group_sizes = counts.column("Age_count").unwrap().u32().unwrap();
+        let counts: HashMap<&str, u32> = statuses
+            .into_iter()
+            .zip(group_sizes)
+            .map(|(status, count)| (status.unwrap(), count.unwrap()))
+            .collect();
+        assert_eq!(
+            counts,
+            HashMap::from([("Infected", 3), ("Susceptible", 1), ("Recovered", 1)])
+        );
+    }
+}
@@ -0,0 +1,346 @@
+/*!
+
+Binary snapshots of an entire `Context` using `bincode`, enabled with the `snapshot` feature.
+
+A snapshot starts with a header describing the schema of the binary that wrote it: the ordered
+lists of registered entities and properties, each identified by name and a hash of its fully
+qualified type name, along with the entity counts. The header is followed by one column of
+values per property, in property index order.
+
+Property indices are assigned by the `ctor`-based registration at program start-up and can
+differ between builds, so a loader never relies on them. Instead it uses the header to match
+each saved entity and column to the entity or property of the running binary with the same
+identity. This tolerates a binary that has gained non-required properties since the snapshot
+was written (they are simply left unset), but a snapshot is rejected before any values are read
+if it contains an entity or property the running binary doesn't know, or if it lacks a property
+the running binary requires.
+
+*/
+
+use std::{
+    fmt::{Display, Formatter},
+    io::{Read, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Context,
+    entity::{
+        Entity,
+        entity_store::get_registered_entities,
+        property::Property,
+        property_store::{PropertyStore, get_registered_properties},
+    },
+};
+
+/// The ways saving or restoring a snapshot can fail.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Reading, writing, or (de)serializing the snapshot failed.
+    Bincode(bincode::Error),
+    /// The snapshot contains an entity type the running binary doesn't have.
+    UnknownEntity(String),
+    /// The snapshot contains a property the running binary doesn't have.
+    UnknownProperty(String),
+    /// The running binary has a required property that the snapshot doesn't contain.
+    MissingRequiredProperty(&'static str),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Bincode(err) => write!(f, "snapshot error: {err}"),
+            SnapshotError::UnknownEntity(name) => {
+                write!(f, "snapshot contains unknown entity \"{name}\"")
+            }
+            SnapshotError::UnknownProperty(name) => {
+                write!(f, "snapshot contains unknown property \"{name}\"")
+            }
+            SnapshotError::MissingRequiredProperty(name) => {
+                write!(f, "snapshot is missing required property \"{name}\"")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(err: bincode::Error) -> Self {
+        SnapshotError::Bincode(err)
+    }
+}
+
+/// Identifies a registered entity or property in a snapshot header.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct SchemaEntry {
+    name: String,
+    type_hash: u64,
+}
+
+impl SchemaEntry {
+    fn new(name: &str, type_name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            type_hash: hash_type_name(type_name),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SnapshotHeader {
+    entities: Vec<SchemaEntry>,
+    entity_counts: Vec<usize>,
+    properties: Vec<SchemaEntry>,
+}
+
+/// A 64-bit FNV-1a hash of a type name. Unlike `std::hash::DefaultHasher`, the result is
+/// guaranteed not to change between Rust releases.
+fn hash_type_name(type_name: &str) -> u64 {
+    type_name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Writes the column of values of property `P` to `writer`. Registered as a hook for each property.
+pub(crate) fn save_property_values<E: Entity, P: Property<E>>(
+    property_store: &PropertyStore,
+    writer: &mut dyn Write,
+) -> bincode::Result<()> {
+    bincode::serialize_into(writer, &property_store.get::<E, P>().to_values())
+}
+
+/// Reads a column of values of property `P` from `reader` into `property_store`. Registered as a
+/// hook for each property.
+pub(crate) fn load_property_values<E: Entity, P: Property<E>>(
+    property_store: &PropertyStore,
+    reader: &mut dyn Read,
+) -> bincode::Result<()> {
+    let values: Vec<Option<P>> = bincode::deserialize_from(reader)?;
+    property_store.get::<E, P>().replace_values(values);
+    Ok(())
+}
+
+impl Context {
+    /// Writes a snapshot of every entity count and property value to `writer`.
+    pub fn save_snapshot(&self, mut writer: impl Write) -> Result<(), SnapshotError> {
+        let entities = get_registered_entities();
+        let properties = get_registered_properties();
+
+        let header = SnapshotHeader {
+            entities: entities
+                .iter()
+                .map(|entity| SchemaEntry::new(entity.name, entity.type_name))
+                .collect(),
+            entity_counts: entities
+                .iter()
+                .map(|entity| self.entity_store.get_entity_count_by_index(entity.index))
+                .collect(),
+            properties: properties
+                .iter()
+                .map(|property| SchemaEntry::new(property.name, property.type_name))
+                .collect(),
+        };
+        bincode::serialize_into(&mut writer, &header)?;
+
+        for property in &properties {
+            (property.save_values)(&self.property_store, &mut writer)?;
+        }
+        writer.flush().map_err(|err| SnapshotError::Bincode(err.into()))?;
+
+        Ok(())
+    }
+
+    /// Restores a `Context` from a snapshot written by [`Context::save_snapshot`]. Fails without
+    /// reading any values if the snapshot's schema is incompatible with the running binary.
+    pub fn load_snapshot(mut reader: impl Read) -> Result<Context, SnapshotError> {
+        let header: SnapshotHeader = bincode::deserialize_from(&mut reader)?;
+
+        // Resolve the saved entities and properties against the running binary.
+        let entities = get_registered_entities();
+        let mut entity_indices = Vec::with_capacity(header.entities.len());
+        for saved in &header.entities {
+            let entity = entities
+                .iter()
+                .find(|entity| SchemaEntry::new(entity.name, entity.type_name) == *saved)
+                .ok_or_else(|| SnapshotError::UnknownEntity(saved.name.clone()))?;
+            entity_indices.push(entity.index);
+        }
+
+        let properties = get_registered_properties();
+        let mut saved_properties = Vec::with_capacity(header.properties.len());
+        for saved in &header.properties {
+            let property = properties
+                .iter()
+                .find(|property| SchemaEntry::new(property.name, property.type_name) == *saved)
+                .ok_or_else(|| SnapshotError::UnknownProperty(saved.name.clone()))?;
+            saved_properties.push(property);
+        }
+        if let Some(missing) = properties.iter().find(|property| {
+            property.is_required
+                && !saved_properties
+                    .iter()
+                    .any(|saved| saved.index == property.index)
+        }) {
+            return Err(SnapshotError::MissingRequiredProperty(missing.name));
+        }
+
+        let mut context = Context::new();
+        for (index, entity_count) in entity_indices.into_iter().zip(header.entity_counts) {
+            context
+                .entity_store
+                .set_entity_count_by_index(index, entity_count);
+        }
+        for property in saved_properties {
+            (property.load_values)(&context.property_store, &mut reader)?;
+        }
+
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        define_entity, define_property,
+        entity::{EntityId, property_store::RegisteredProperty},
+    };
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+
+    define_property!(struct Weight(u16), Person);
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut context = Context::new();
+        let person1 = context.add_entity((Age(12), Weight(80)));
+        let person2 = context.add_entity((Age(33), InfectionStatus::Infected));
+        let person3 = context.add_entity((Age(44), InfectionStatus::Recovered, Weight(95)));
+        context.set_property(person1, InfectionStatus::Recovered);
+
+        let mut bytes = Vec::new();
+        context.save_snapshot(&mut bytes).unwrap();
+        let restored = Context::load_snapshot(bytes.as_slice()).unwrap();
+
+        assert_eq!(restored.get_entity_count::<Person>(), 3);
+        for person in [person1, person2, person3] {
+            assert_eq!(
+                restored.get_property::<_, Age>(person),
+                context.get_property::<_, Age>(person)
+            );
+            assert_eq!(
+                restored.get_property::<_, InfectionStatus>(person),
+                context.get_property::<_, InfectionStatus>(person)
+            );
+            assert_eq!(
+                restored.property_store.get::<_, Weight>().get(person),
+                context.property_store.get::<_, Weight>().get(person)
+            );
+        }
+    }
+
+    #[test]
+    fn truncated_snapshot_is_an_error() {
+        let mut context = Context::new();
+        context.add_entity((Age(12),));
+
+        let mut bytes = Vec::new();
+        context.save_snapshot(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() / 2);
+
+        assert!(matches!(
+            Context::load_snapshot(bytes.as_slice()),
+            Err(SnapshotError::Bincode(_))
+        ));
+    }
+
+    /// Writes a snapshot of `context` as if it came from a binary whose properties were those
+    /// of this binary accepted by `keep`, plus the (column-less) `extra` properties.
+    fn write_altered_snapshot(
+        context: &Context,
+        keep: impl Fn(&RegisteredProperty) -> bool,
+        extra: Vec<SchemaEntry>,
+    ) -> Vec<u8> {
+        let entities = get_registered_entities();
+        let properties: Vec<RegisteredProperty> =
+            get_registered_properties().into_iter().filter(keep).collect();
+
+        let mut header = SnapshotHeader {
+            entities: entities
+                .iter()
+                .map(|entity| SchemaEntry::new(entity.name, entity.type_name))
+                .collect(),
+            entity_counts: entities
+                .iter()
+                .map(|entity| context.entity_store.get_entity_count_by_index(entity.index))
+                .collect(),
+            properties: properties
+                .iter()
+                .map(|property| SchemaEntry::new(property.name, property.type_name))
+                .collect(),
+        };
+        header.properties.extend(extra);
+
+        let mut bytes = Vec::new();
+        bincode::serialize_into(&mut bytes, &header).unwrap();
+        for property in &properties {
+            (property.save_values)(&context.property_store, &mut bytes).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn snapshot_from_binary_without_an_added_property() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(12), Weight(80)));
+
+        // The snapshot predates `Weight`, which isn't required, so it loads with `Weight` unset.
+        let bytes = write_altered_snapshot(&context, |property| property.name != "Weight", vec![]);
+        let restored = Context::load_snapshot(bytes.as_slice()).unwrap();
+        assert_eq!(restored.get_property::<_, Age>(person), Age(12));
+        assert_eq!(restored.property_store.get::<_, Weight>().get(person), None);
+    }
+
+    #[test]
+    fn snapshot_from_binary_without_an_added_required_property() {
+        let mut context = Context::new();
+        context.add_entity((Age(12),));
+
+        // The snapshot predates `Age`, which the running binary requires.
+        let type_name = std::any::type_name::<Age>();
+        let bytes =
+            write_altered_snapshot(&context, |property| property.type_name != type_name, vec![]);
+        assert!(matches!(
+            Context::load_snapshot(bytes.as_slice()),
+            Err(SnapshotError::MissingRequiredProperty("Age"))
+        ));
+    }
+
+    #[test]
+    fn snapshot_from_binary_with_a_removed_property() {
+        let mut context = Context::new();
+        context.add_entity((Age(12),));
+
+        // The snapshot has a property the running binary no longer has.
+        let removed = SchemaEntry::new("Height", "ixa_entities::io::snapshot::tests::Height");
+        let bytes = write_altered_snapshot(&context, |_| true, vec![removed]);
+        match Context::load_snapshot(bytes.as_slice()) {
+            Err(SnapshotError::UnknownProperty(name)) => assert_eq!(name, "Height"),
+            other => panic!("expected an unknown property error, got {:?}", other.err()),
+        }
+    }
+}
@@ -1,10 +1,24 @@
 #![allow(unused)]
 
+pub mod change_tracking;
+pub mod diff;
 pub mod entity;
+pub mod events;
+pub mod hashing;
+pub mod io;
+pub mod memory_report;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod query;
+pub mod report;
+pub mod sampling;
+pub mod sync_context;
 pub mod value_vec;
 mod context;
 
-pub use context::Context;
+pub use context::{
+    AddEntityError, Context, InvalidPropertyValueError, PropertyNotSetError, SetPropertyByNameError,
+};
 
 pub use ctor;
 pub use paste;
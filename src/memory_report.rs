@@ -0,0 +1,139 @@
+/*!
+
+A summary of the memory a `Context` uses to store its entities' property values, for profiling
+large runs.
+
+```rust,ignore
+let report = context.memory_report();
+println!("{report}");
+```
+
+Only the heap storage of each property's `PropertyValueStore` is counted, as capacity times the
+size of a slot, so the total is a lower bound on the memory the process uses.
+
+*/
+
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    Context,
+    entity::{entity_store::get_registered_entities, property_store::get_registered_properties},
+};
+
+/// The number of entities of one registered entity type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntityMemoryUsage {
+    pub name: &'static str,
+    pub count: usize,
+}
+
+/// The heap memory allocated for the values of one registered property.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertyMemoryUsage {
+    pub name: &'static str,
+    /// The fully qualified type name, which distinguishes properties with the same name.
+    pub type_name: &'static str,
+    pub entity_name: &'static str,
+    pub heap_bytes: usize,
+}
+
+/// The result of [`Context::memory_report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// One entry per registered entity type, in registration order.
+    pub entities: Vec<EntityMemoryUsage>,
+    /// One entry per registered property, in registration order.
+    pub properties: Vec<PropertyMemoryUsage>,
+}
+
+impl MemoryReport {
+    /// The heap memory allocated for the values of all properties.
+    pub fn total_bytes(&self) -> usize {
+        self.properties
+            .iter()
+            .map(|property| property.heap_bytes)
+            .sum()
+    }
+}
+
+impl Display for MemoryReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for entity in &self.entities {
+            writeln!(f, "{}: {} entities", entity.name, entity.count)?;
+        }
+        for property in &self.properties {
+            writeln!(
+                f,
+                "{}.{}: {} bytes",
+                property.entity_name, property.name, property.heap_bytes
+            )?;
+        }
+        write!(f, "total: {} bytes", self.total_bytes())
+    }
+}
+
+impl Context {
+    /// Reports the number of entities of each type and the heap memory allocated for the values
+    /// of each property.
+    pub fn memory_report(&self) -> MemoryReport {
+        let entities = get_registered_entities()
+            .into_iter()
+            .map(|entity| EntityMemoryUsage {
+                name: entity.name,
+                count: self.entity_store.get_entity_count_by_index(entity.index),
+            })
+            .collect();
+        let properties = get_registered_properties()
+            .into_iter()
+            .map(|property| PropertyMemoryUsage {
+                name: property.name,
+                type_name: property.type_name,
+                entity_name: property.entity_name,
+                heap_bytes: (property.heap_bytes)(&self.property_store),
+            })
+            .collect();
+
+        MemoryReport {
+            entities,
+            properties,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{define_entity, define_property};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+
+    define_property!(struct Weight(u16), Person);
+
+    fn age_usage(report: &MemoryReport) -> &PropertyMemoryUsage {
+        let type_name = std::any::type_name::<Age>();
+        report
+            .properties
+            .iter()
+            .find(|property| property.type_name == type_name)
+            .unwrap()
+    }
+
+    #[test]
+    fn memory_report_grows_with_population() {
+        let mut context = Context::new();
+        let before = context.memory_report();
+        assert_eq!(age_usage(&before).heap_bytes, 0);
+
+        for age in 0..10_000 {
+            context.add_entity((Age((age % 100) as u8),));
+        }
+        let after = context.memory_report();
+
+        assert!(age_usage(&after).heap_bytes >= 10_000 * size_of::<Option<Age>>());
+        assert!(after.total_bytes() > before.total_bytes());
+        assert!(after.entities.iter().any(|entity| entity.count == 10_000));
+        assert!(after.to_string().ends_with(&format!("total: {} bytes", after.total_bytes())));
+    }
+}
@@ -0,0 +1,161 @@
+/*!
+
+Parallel iteration over property values with `rayon`, enabled by the `rayon` feature.
+
+```rust,ignore
+let total_age: u64 = context
+    .property_store
+    .get::<Person, Age>()
+    .par_iter_set()
+    .map(|(_, age)| age.0 as u64)
+    .sum();
+let infected = context.par_filter_entities::<Person, InfectionStatus>(|status| {
+    *status == InfectionStatus::Infected
+});
+```
+
+Neither a `Context` nor a `PropertyValueStore` can be shared across threads, because their storage
+is mutated through shared references. So these methods first copy the values they need out of
+the store, on the calling thread, and only then hand them to `rayon`. Copying a column is cheap
+next to the work that is worth parallelizing, and it means the closures run on other threads
+never touch the store.
+
+*/
+
+use rayon::prelude::*;
+
+use crate::{
+    Context,
+    entity::{
+        Entity, EntityId,
+        property::Property,
+        property_value_store::PropertyValueStore,
+    },
+    report::Partition,
+};
+
+impl<E: Entity + Send, P: Property<E> + Send> PropertyValueStore<E, P> {
+    /// A parallel version of [`PropertyValueStore::iter_set`]. The items are produced from a copy
+    /// of the values taken when this method is called.
+    pub fn par_iter_set(&self) -> impl ParallelIterator<Item = (EntityId<E>, P)> + use<E, P> {
+        self.to_values()
+            .into_par_iter()
+            .enumerate()
+            .filter_map(|(index, value)| value.map(|value| (EntityId::new(index), value)))
+    }
+}
+
+impl<E: Entity + Sync, K: Sync> Partition<E, K> {
+    /// A parallel version of [`Partition::iter`], with one item per group.
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (&K, &[EntityId<E>])> {
+        self.groups.par_iter().map(|(key, ids)| (key, ids.as_slice()))
+    }
+}
+
+impl Context {
+    /// Returns the ids, in order, of the entities of type `E` whose value of property `P`
+    /// satisfies `predicate`, which is evaluated in parallel. Entities without a value are
+    /// skipped. Derived and default function values are computed on the calling thread first,
+    /// since computing them needs the `Context`.
+    pub fn par_filter_entities<E, P>(
+        &self,
+        predicate: impl Fn(&P) -> bool + Sync,
+    ) -> Vec<EntityId<E>>
+    where
+        E: Entity + Send,
+        P: Property<E> + Send,
+    {
+        self.copy_values::<E, P>()
+            .into_par_iter()
+            .enumerate()
+            .filter_map(|(index, value)| {
+                value
+                    .filter(|value| predicate(value))
+                    .map(|_| EntityId::new(index))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use crate::{
+        Context, define_entity, define_property, entity::property::PropertyInitializationKind,
+        impl_property_with_options,
+    };
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+
+    define_property!(
+        struct Vaccinated(bool),
+        Person,
+        default_const = Vaccinated(false)
+    );
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct IsAdult(bool);
+    impl_property_with_options!(
+        IsAdult,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            IsAdult(age.0 >= 18)
+        }
+    );
+
+    fn population() -> Context {
+        let mut context = Context::new();
+        for index in 0..1000 {
+            let person = context.add_entity((Age((index % 90) as u8),));
+            if index % 3 == 0 {
+                context.set_property(person, Vaccinated(true));
+            }
+        }
+        context
+    }
+
+    #[test]
+    fn parallel_sum_matches_serial_sum() {
+        let context = population();
+        let ages = context.property_store.get::<Person, Age>();
+        let serial: u64 = ages.iter_set().map(|(_, age)| age.0 as u64).sum();
+        let parallel: u64 = ages.par_iter_set().map(|(_, age)| age.0 as u64).sum();
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn par_filter_entities_matches_serial_filter() {
+        let mut context = population();
+        assert!(context.remove_entity(PersonId::new(3)));
+        assert!(context.remove_entity(PersonId::new(4)));
+
+        let unvaccinated = context.par_filter_entities::<Person, Vaccinated>(|v| !v.0);
+        let expected: Vec<PersonId> = context
+            .entity_store
+            .live_entity_ids::<Person>()
+            .filter(|person| !context.get_property::<_, Vaccinated>(*person).0)
+            .collect();
+        assert_eq!(unvaccinated, expected);
+        assert!(!unvaccinated.contains(&PersonId::new(4)));
+
+        let adults = context.par_filter_entities::<Person, IsAdult>(|is_adult| is_adult.0);
+        assert_eq!(adults.len(), context.tabulate::<Person, IsAdult>()[&IsAdult(true)]);
+    }
+
+    #[test]
+    fn partition_groups_in_parallel() {
+        let context = population();
+        let partition = context.partition::<Person, Vaccinated>();
+        let mut group_sizes: Vec<(bool, usize)> = partition
+            .par_iter()
+            .map(|(vaccinated, people)| (vaccinated.0, people.len()))
+            .collect();
+        group_sizes.sort();
+        assert_eq!(group_sizes, [(false, 666), (true, 334)]);
+    }
+}
@@ -0,0 +1,375 @@
+/*!
+
+Selecting the entities whose property values satisfy a set of constraints.
+
+```rust,ignore
+let infected_adults: Vec<PersonId> = context
+    .query::<Person>()
+    .matching(InfectionStatus::Infected)
+    .range::<Age>(18..65)
+    .ids();
+```
+
+A [`Query`] accumulates constraints and is evaluated by one of its terminal methods,
+[`Query::ids`], [`Query::count`], or [`Query::sample`]. Only live entities are considered. An entity without a
+value for an explicit property satisfies no constraint on that property, not even
+[`Query::not_matching`]: "unset" is neither equal nor unequal to any value.
+
+Constraints on derived properties are "compute-only": their values aren't stored, so they are
+computed with [`Property::compute_derived`] for each entity that reaches them. They are checked
+after every constraint on a stored property, so that the cheaper constraints narrow the set
+first.
+
+*/
+
+use std::ops::RangeBounds;
+
+use rand::{Rng, seq::SliceRandom};
+
+use crate::{
+    Context,
+    entity::{
+        Entity, EntityId,
+        entity_keyed_map::EntityKeyedSparseMap,
+        property::{Property, PropertyInitializationKind},
+        property_list::PropertyList,
+    },
+};
+
+/// Tests one entity against one constraint.
+type BxConstraintFn<E> = Box<dyn Fn(&Context, EntityId<E>) -> bool>;
+
+/// A single constraint of a [`Query`], along with how expensive it is to check.
+struct Constraint<E: Entity> {
+    /// Whether the constraint is on a derived property, whose values must be computed. These are
+    /// checked last.
+    compute_only: bool,
+    /// Among constraints that are equally compute-only, those with a lower cost are checked first.
+    cost: u8,
+    test: BxConstraintFn<E>,
+}
+
+/// A builder for selecting entities of type `E` by their property values. Created with
+/// [`Context::query`].
+pub struct Query<'a, E: Entity> {
+    context: &'a Context,
+    constraints: Vec<Constraint<E>>,
+}
+
+impl<'a, E: Entity> Query<'a, E> {
+    fn new(context: &'a Context) -> Self {
+        Self {
+            context,
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Adds a constraint on property `P`.
+    fn push<P: Property<E>>(
+        mut self,
+        cost: u8,
+        test: impl Fn(&Context, EntityId<E>) -> bool + 'static,
+    ) -> Self {
+        self.constraints.push(Constraint {
+            compute_only: P::initialization_kind() == PropertyInitializationKind::Derived,
+            cost,
+            test: Box::new(test),
+        });
+        self
+    }
+
+    /// Keeps the entities whose value of `P` equals `value`.
+    pub fn matching<P: Property<E>>(self, value: P) -> Self {
+        self.push::<P>(0, move |context, entity_id| {
+            context.try_get_property::<E, P>(entity_id).is_ok_and(|actual| actual == value)
+        })
+    }
+
+    /// Keeps the entities whose value of each property in `values` equals the one given, as if
+    /// [`Query::matching`] were called with each value.
+    pub fn matching_all<PL: PropertyList<E>>(self, values: PL) -> Self {
+        values.add_to_query(self)
+    }
+
+    /// Keeps the entities that have a value of `P` other than `value`. Entities without a value of
+    /// `P` are dropped.
+    pub fn not_matching<P: Property<E>>(self, value: P) -> Self {
+        self.push::<P>(0, move |context, entity_id| {
+            context.try_get_property::<E, P>(entity_id).is_ok_and(|actual| actual != value)
+        })
+    }
+
+    /// Keeps the entities whose canonical value of `P` lies in `range`.
+    pub fn range<P>(self, range: impl RangeBounds<P::CanonicalValue> + 'static) -> Self
+    where
+        P: Property<E>,
+        P::CanonicalValue: PartialOrd,
+    {
+        self.push::<P>(1, move |context, entity_id| {
+            context
+                .try_get_property::<E, P>(entity_id)
+                .is_ok_and(|value| range.contains(&value.make_canonical()))
+        })
+    }
+
+    /// Keeps the entities whose value of `P` satisfies `predicate`. (`where` is a keyword, hence
+    /// the name.)
+    pub fn filter<P: Property<E>>(self, predicate: impl Fn(&P) -> bool + 'static) -> Self {
+        self.push::<P>(2, move |context, entity_id| {
+            context.try_get_property::<E, P>(entity_id).is_ok_and(|value| predicate(&value))
+        })
+    }
+
+    /// Returns the ids of the entities that satisfy every constraint, in id order.
+    pub fn ids(mut self) -> Vec<EntityId<E>> {
+        // The sort is stable, so constraints of equal cost are checked in the order they were added.
+        self.constraints
+            .sort_by_key(|constraint| (constraint.compute_only, constraint.cost));
+        let context = self.context;
+        context
+            .entity_store
+            .live_entity_ids::<E>()
+            .filter(|entity_id| {
+                self.constraints
+                    .iter()
+                    .all(|constraint| (constraint.test)(context, *entity_id))
+            })
+            .collect()
+    }
+
+    /// Returns the number of entities that satisfy every constraint.
+    pub fn count(self) -> usize {
+        self.ids().len()
+    }
+
+    /// Chooses `n` distinct entities that satisfy every constraint uniformly at random, or every
+    /// such entity if there are no more than `n`. The entities are returned in the order they
+    /// were chosen.
+    pub fn sample<R: Rng + ?Sized>(self, rng: &mut R, n: usize) -> Vec<EntityId<E>> {
+        let mut ids = self.ids();
+        let (chosen, _) = ids.partial_shuffle(rng, n);
+        chosen.to_vec()
+    }
+}
+
+impl Context {
+    /// Starts a [`Query`] over the entities of type `E`. Without constraints, it selects every
+    /// live entity.
+    pub fn query<E: Entity>(&self) -> Query<'_, E> {
+        Query::new(self)
+    }
+
+    /// Runs a query for the entities of type `E` matching every value in `values` and maps each
+    /// one to `f` of its id.
+    pub fn query_map<E: Entity, PL: PropertyList<E>, T>(
+        &self,
+        values: PL,
+        f: impl Fn(EntityId<E>) -> T,
+    ) -> EntityKeyedSparseMap<E, T> {
+        let mut map = EntityKeyedSparseMap::new();
+        for entity_id in self.query::<E>().matching_all(values).ids() {
+            map.insert(entity_id, f(entity_id));
+        }
+        map
+    }
+
+    /// Returns the ids of the entities of type `E` that have a value of `P` other than `value`, in
+    /// id order. Entities without a value of `P` are not included.
+    pub fn query_not<E: Entity, P: Property<E>>(&self, value: P) -> Vec<EntityId<E>> {
+        self.query::<E>().not_matching(value).ids()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, collections::HashSet};
+
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::{
+        Context, define_entity, define_property,
+        entity::{EntityId, property::PropertyInitializationKind},
+        impl_property_with_options,
+    };
+
+    define_entity!(Person);
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct Age(u8);
+    impl_property_with_options!(
+        Age,
+        Person,
+        is_required = true,
+        canonical_value = u8,
+        make_canonical = |age: Age| age.0,
+        make_uncanonical = Age
+    );
+
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+
+    define_property!(struct Weight(u16), Person);
+
+    thread_local! {
+        /// The number of times `AgeGroup` has been computed.
+        static AGE_GROUP_COMPUTATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    enum AgeGroup {
+        Child,
+        Adult,
+        Senior,
+    }
+    impl_property_with_options!(
+        AgeGroup,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            AGE_GROUP_COMPUTATIONS.set(AGE_GROUP_COMPUTATIONS.get() + 1);
+            let age: Age = context.get_property(entity_id);
+            match age.0 {
+                0..18 => AgeGroup::Child,
+                18..65 => AgeGroup::Adult,
+                _ => AgeGroup::Senior,
+            }
+        }
+    );
+
+    fn population() -> (Context, Vec<PersonId>) {
+        let mut context = Context::new();
+        let people = [
+            (8, InfectionStatus::Infected),
+            (15, InfectionStatus::Susceptible),
+            (30, InfectionStatus::Infected),
+            (42, InfectionStatus::Recovered),
+            (67, InfectionStatus::Infected),
+        ]
+        .into_iter()
+        .map(|(age, status)| context.add_entity((Age(age), status)))
+        .collect();
+        (context, people)
+    }
+
+    #[test]
+    fn matching_and_range() {
+        let (context, people) = population();
+        let infected_adults = context
+            .query::<Person>()
+            .matching(InfectionStatus::Infected)
+            .range::<Age>(18..65)
+            .ids();
+        assert_eq!(infected_adults, [people[2]]);
+
+        let infected = context.query::<Person>().matching(InfectionStatus::Infected);
+        assert_eq!(infected.range::<Age>(18..).count(), 2);
+    }
+
+    #[test]
+    fn not_matching_selects_the_complement() {
+        let (context, people) = population();
+        let not_recovered = context.query_not::<Person, _>(InfectionStatus::Recovered);
+        assert_eq!(not_recovered, [people[0], people[1], people[2], people[4]]);
+
+        let not_infected_children = context
+            .query::<Person>()
+            .not_matching(InfectionStatus::Infected)
+            .range::<Age>(..18)
+            .ids();
+        assert_eq!(not_infected_children, [people[1]]);
+
+        // "Unset" is not "not equal".
+        context.set_property(people[0], Weight(30));
+        assert_eq!(context.query_not::<Person, _>(Weight(80)), [people[0]]);
+    }
+
+    #[test]
+    fn matching_a_derived_property() {
+        let (context, people) = population();
+        assert_eq!(
+            context.query::<Person>().matching(AgeGroup::Adult).ids(),
+            [people[2], people[3]]
+        );
+
+        // The derived constraint is checked last, only for the entities that are infected.
+        AGE_GROUP_COMPUTATIONS.set(0);
+        let infected_children = context
+            .query::<Person>()
+            .matching(AgeGroup::Child)
+            .matching(InfectionStatus::Infected)
+            .ids();
+        assert_eq!(infected_children, [people[0]]);
+        assert_eq!(AGE_GROUP_COMPUTATIONS.get(), 3);
+    }
+
+    #[test]
+    fn filter_and_unset_values() {
+        let (context, people) = population();
+        context.set_property(people[1], Weight(50));
+        context.set_property(people[3], Weight(80));
+
+        let heavy = context
+            .query::<Person>()
+            .filter::<Weight>(|weight| weight.0 > 60)
+            .ids();
+        assert_eq!(heavy, [people[3]]);
+        // Entities without a weight satisfy no constraint on it.
+        assert_eq!(context.query::<Person>().filter::<Weight>(|_| true).count(), 2);
+        assert_eq!(context.query::<Person>().count(), 5);
+        assert_eq!(
+            context.query::<Person>().matching(Age(99)).ids(),
+            Vec::<EntityId<Person>>::new()
+        );
+    }
+
+    #[test]
+    fn query_map_of_infected_ages() {
+        let (context, people) = population();
+        let ages = context.query_map(InfectionStatus::Infected, |person| {
+            context.get_property::<_, Age>(person).0
+        });
+        assert_eq!(
+            ages.iter().map(|(person, age)| (person, *age)).collect::<Vec<_>>(),
+            [(people[0], 8), (people[2], 30), (people[4], 67)]
+        );
+
+        let ages = context.query_map((InfectionStatus::Infected, Age(30)), |person| {
+            context.get_property::<_, Age>(person).0
+        });
+        assert_eq!(ages.len(), 1);
+        assert_eq!(ages.get(people[2]), Some(&30));
+    }
+
+    #[test]
+    fn sample_of_matching_entities() {
+        let (context, people) = population();
+        let mut rng = StdRng::seed_from_u64(42);
+        let infected = [people[0], people[2], people[4]];
+
+        let mut chosen = HashSet::new();
+        for _ in 0..100 {
+            let sample = context
+                .query::<Person>()
+                .matching(InfectionStatus::Infected)
+                .sample(&mut rng, 2);
+            assert_eq!(sample.len(), 2);
+            assert_ne!(sample[0], sample[1]);
+            assert!(sample.iter().all(|person| infected.contains(person)));
+            chosen.extend(sample);
+        }
+        assert_eq!(chosen.len(), 3);
+
+        // Asking for more than match returns every match.
+        let mut sample = context.query::<Person>().range::<Age>(..18).sample(&mut rng, 10);
+        sample.sort();
+        assert_eq!(sample, [people[0], people[1]]);
+        assert!(context.query::<Person>().matching(Age(99)).sample(&mut rng, 3).is_empty());
+    }
+}
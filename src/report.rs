@@ -0,0 +1,812 @@
+/*!
+
+Summaries of property values across a population, for reporting.
+
+```rust,ignore
+let counts = context.tabulate::<Person, InfectionStatus>();
+println!("{} people are infected", counts.get(&InfectionStatus::Infected).unwrap_or(&0));
+
+// Properties with a numeric canonical value can be grouped into ranges.
+for (bin, count) in context.histogram::<Person, Age>(10) {
+    println!("{bin}: {count}");
+}
+```
+
+Only live entities are counted. Entities without a value for an explicit property are skipped.
+
+*/
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display, Formatter},
+    hash::Hash,
+    iter::Sum,
+};
+
+use crate::{
+    Context,
+    entity::{
+        Entity, EntityId,
+        property::{Property, PropertyInitializationKind},
+    },
+};
+
+/// The live entities of type `E` split into groups by their canonical value `K` of a property,
+/// built by [`Context::partition`]. The groups are in the order of their first member, and each
+/// owns its ids, in id order, so the groups can be handed out separately, e.g. to different
+/// threads.
+pub struct Partition<E: Entity, K> {
+    pub(crate) groups: Vec<(K, Vec<EntityId<E>>)>,
+    /// The position of each key's group in `groups`.
+    positions: HashMap<K, usize>,
+}
+
+impl<E: Entity, K: Eq + Hash> Partition<E, K> {
+    /// The number of groups.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// The ids in the group of `key`, or `None` if no entity has that value.
+    pub fn get(&self, key: &K) -> Option<&[EntityId<E>]> {
+        let position = *self.positions.get(key)?;
+        Some(&self.groups[position].1)
+    }
+
+    /// Iterates over the groups and their keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &[EntityId<E>])> {
+        self.groups.iter().map(|(key, ids)| (key, ids.as_slice()))
+    }
+}
+
+impl<E: Entity, K> IntoIterator for Partition<E, K> {
+    type Item = (K, Vec<EntityId<E>>);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.groups.into_iter()
+    }
+}
+
+/// A numeric type that [`Context::histogram`] can group into bins. Bin `i` of width `w` is the
+/// range `i * w .. (i + 1) * w`, cut off at the ends of the type's range.
+pub trait Binnable: Copy + PartialOrd {
+    const ZERO: Self;
+
+    /// The index of the bin of width `bin_width` that contains `self`.
+    fn bin_index(self, bin_width: Self) -> i64;
+
+    /// The value `index * bin_width` where the bin with the given index starts, or `None` if the
+    /// type can't represent it.
+    fn bin_start(index: i64, bin_width: Self) -> Option<Self>;
+}
+
+/// One bin of a [`Context::histogram`]: the values from `start` up to but not including `end`,
+/// or, if `end` is `None`, up to and including the largest value of the type, where the bin is
+/// cut off because the value that would end it can't be represented.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bin<V> {
+    pub start: V,
+    pub end: Option<V>,
+}
+
+impl<V: Binnable> Bin<V> {
+    /// Whether `value` falls in this bin.
+    pub fn contains(&self, value: V) -> bool {
+        self.start <= value && self.end.is_none_or(|end| value < end)
+    }
+}
+
+impl<V: Debug> Display for Bin<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.end {
+            Some(end) => write!(f, "{:?}..{:?}", self.start, end),
+            None => write!(f, "{:?}..", self.start),
+        }
+    }
+}
+
+macro_rules! impl_binnable_int {
+    ($($ty:ty),*) => {$(
+        impl Binnable for $ty {
+            const ZERO: Self = 0;
+
+            fn bin_index(self, bin_width: Self) -> i64 {
+                (self as i128).div_euclid(bin_width as i128) as i64
+            }
+
+            fn bin_start(index: i64, bin_width: Self) -> Option<Self> {
+                Self::try_from(index as i128 * bin_width as i128).ok()
+            }
+        }
+    )*};
+}
+impl_binnable_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+macro_rules! impl_binnable_float {
+    ($($ty:ty),*) => {$(
+        impl Binnable for $ty {
+            const ZERO: Self = 0.0;
+
+            fn bin_index(self, bin_width: Self) -> i64 {
+                (self / bin_width).floor() as i64
+            }
+
+            fn bin_start(index: i64, bin_width: Self) -> Option<Self> {
+                Some(index as Self * bin_width)
+            }
+        }
+    )*};
+}
+impl_binnable_float!(f32, f64);
+
+impl Context {
+    /// Counts the entities of type `E` with each value of property `P`. Derived values are
+    /// computed for each entity, entities that have never set a constant property count
+    /// towards its default, and default function values are computed and stored as needed.
+    pub fn tabulate<E: Entity, P: Property<E>>(&self) -> HashMap<P::CanonicalValue, usize>
+    where
+        P::CanonicalValue: Eq + Hash,
+    {
+        let mut counts = HashMap::new();
+        self.for_each_value::<E, P>(|value, count| *counts.entry(value).or_insert(0) += count);
+        counts
+    }
+
+    /// Counts the entities of type `E` with each combination of values of properties `P1` and
+    /// `P2`, e.g. a contingency table of infection status against vaccination. Values are read
+    /// as by [`Context::try_get_property`], so derived values are computed and constant
+    /// properties that were never set count as their default. Entities without a value for
+    /// either property are skipped.
+    pub fn crosstab<E: Entity, P1: Property<E>, P2: Property<E>>(
+        &self,
+    ) -> HashMap<(P1::CanonicalValue, P2::CanonicalValue), usize>
+    where
+        P1::CanonicalValue: Eq + Hash,
+        P2::CanonicalValue: Eq + Hash,
+    {
+        let mut counts = HashMap::new();
+        for entity_id in self.entity_store.live_entity_ids::<E>() {
+            let (Ok(value1), Ok(value2)) = (
+                self.try_get_property::<E, P1>(entity_id),
+                self.try_get_property::<E, P2>(entity_id),
+            ) else {
+                continue;
+            };
+            *counts
+                .entry((value1.make_canonical(), value2.make_canonical()))
+                .or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Partitions the entities of type `E` by their value of property `P`, like
+    /// [`Context::tabulate`] but returning the ids in each group rather than counting them. Each
+    /// group lists its ids in id order. Entities without a value are in no group.
+    pub fn group_by<E: Entity, P: Property<E>>(
+        &self,
+    ) -> HashMap<P::CanonicalValue, Vec<EntityId<E>>>
+    where
+        P::CanonicalValue: Eq + Hash,
+    {
+        let mut groups: HashMap<_, Vec<_>> = HashMap::new();
+        for entity_id in self.entity_store.live_entity_ids::<E>() {
+            if let Ok(value) = self.try_get_property::<E, P>(entity_id) {
+                groups.entry(value.make_canonical()).or_default().push(entity_id);
+            }
+        }
+        groups
+    }
+
+    /// Splits the entities of type `E` into a [`Partition`] by their value of property `P`, in a
+    /// single pass over the population. Like [`Context::group_by`], but the groups keep a stable
+    /// order and can be iterated, or consumed, one at a time. Entities without a value are in no
+    /// group.
+    pub fn partition<E: Entity, P: Property<E>>(&self) -> Partition<E, P::CanonicalValue>
+    where
+        P::CanonicalValue: Eq + Hash,
+    {
+        let mut partition = Partition {
+            groups: Vec::new(),
+            positions: HashMap::new(),
+        };
+        for entity_id in self.entity_store.live_entity_ids::<E>() {
+            let Ok(value) = self.try_get_property::<E, P>(entity_id) else {
+                continue;
+            };
+            let value = value.make_canonical();
+            let position = match partition.positions.get(&value) {
+                Some(position) => *position,
+                None => {
+                    partition.groups.push((value.clone(), Vec::new()));
+                    partition.positions.insert(value, partition.groups.len() - 1);
+                    partition.groups.len() - 1
+                }
+            };
+            partition.groups[position].1.push(entity_id);
+        }
+        partition
+    }
+
+    /// Counts the entities of type `E` whose canonical value of property `P` falls in each bin
+    /// of width `bin_width`, like [`Context::tabulate`]. The bins are returned in order, from the
+    /// one containing the smallest value to the one containing the largest, including any empty
+    /// bins in between. Returns an empty `Vec` if no entity has a value. A bin that would extend
+    /// past either end of the type's range is cut off there: the first bin starts no lower than
+    /// the smallest value, and the last has no `end` if it reaches the largest value of the type.
+    ///
+    /// Panics if `bin_width` isn't positive.
+    pub fn histogram<E: Entity, P: Property<E>>(
+        &self,
+        bin_width: P::CanonicalValue,
+    ) -> Vec<(Bin<P::CanonicalValue>, usize)>
+    where
+        P::CanonicalValue: Binnable,
+    {
+        assert!(
+            bin_width > P::CanonicalValue::ZERO,
+            "the bin width of a histogram must be positive"
+        );
+
+        let mut bin_counts: HashMap<i64, usize> = HashMap::new();
+        let mut smallest: Option<P::CanonicalValue> = None;
+        self.for_each_value::<E, P>(|value, count| {
+            *bin_counts.entry(value.bin_index(bin_width)).or_insert(0) += count;
+            if smallest.is_none_or(|smallest| value < smallest) {
+                smallest = Some(value);
+            }
+        });
+        let (Some(&first), Some(&last), Some(smallest)) =
+            (bin_counts.keys().min(), bin_counts.keys().max(), smallest)
+        else {
+            return Vec::new();
+        };
+
+        (first..=last)
+            .map(|index| {
+                // Only the first bin can start below the type's range, and only the last can end
+                // above it.
+                let start = P::CanonicalValue::bin_start(index, bin_width).unwrap_or(smallest);
+                let end = P::CanonicalValue::bin_start(index + 1, bin_width);
+                (Bin { start, end }, bin_counts.get(&index).copied().unwrap_or(0))
+            })
+            .collect()
+    }
+
+    /// The sum of the canonical values of property `P` over the entities of type `E`, with the
+    /// same values as [`Context::tabulate`].
+    pub fn sum<E: Entity, P: Property<E>>(&self) -> P::CanonicalValue
+    where
+        P::CanonicalValue: Sum,
+    {
+        // Sum as the values stream by, starting each group from the running total, rather than
+        // collecting one value per entity.
+        let mut total: Option<P::CanonicalValue> = None;
+        self.for_each_value::<E, P>(|value, count| {
+            total = Some(total.take().into_iter().chain(std::iter::repeat_n(value, count)).sum());
+        });
+        total.unwrap_or_else(|| std::iter::empty().sum())
+    }
+
+    /// The mean of the canonical values of property `P` over the entities of type `E`, with the
+    /// same values as [`Context::tabulate`]. Returns `NaN` if no entity has a value.
+    pub fn mean<E: Entity, P: Property<E>>(&self) -> f64
+    where
+        P::CanonicalValue: Into<f64>,
+    {
+        let mut total = 0.0;
+        let mut total_count = 0;
+        self.for_each_value::<E, P>(|value, count| {
+            total += value.into() * count as f64;
+            total_count += count;
+        });
+        total / total_count as f64
+    }
+
+    /// The smallest canonical value of property `P` over the entities of type `E`, with the same
+    /// values as [`Context::tabulate`]. Returns `None` if no entity has a value.
+    pub fn min<E: Entity, P: Property<E>>(&self) -> Option<P::CanonicalValue>
+    where
+        P::CanonicalValue: Ord,
+    {
+        let mut min: Option<P::CanonicalValue> = None;
+        self.for_each_value::<E, P>(|value, _| {
+            if min.as_ref().is_none_or(|min| value < *min) {
+                min = Some(value);
+            }
+        });
+        min
+    }
+
+    /// The largest canonical value of property `P` over the entities of type `E`, with the same
+    /// values as [`Context::tabulate`]. Returns `None` if no entity has a value.
+    pub fn max<E: Entity, P: Property<E>>(&self) -> Option<P::CanonicalValue>
+    where
+        P::CanonicalValue: Ord,
+    {
+        let mut max: Option<P::CanonicalValue> = None;
+        self.for_each_value::<E, P>(|value, _| {
+            if max.as_ref().is_none_or(|max| value > *max) {
+                max = Some(value);
+            }
+        });
+        max
+    }
+
+    /// The `q`-quantile of the canonical values of property `P` over the entities of type `E`,
+    /// with the same values as [`Context::tabulate`], e.g. the median for `q = 0.5`. Returns
+    /// `None` if no entity has a value.
+    ///
+    /// Values are only ordered, not averaged, so nothing is interpolated: this is the
+    /// nearest-rank quantile, the smallest value that at least a fraction `q` of the values are
+    /// less than or equal to. Of `n` values in order, that is the one at rank `ceil(q * n)`,
+    /// counting from 1, or the smallest value for `q = 0`. The median of an even number of
+    /// values is the lower of the middle two.
+    ///
+    /// Panics if `q` isn't in `[0, 1]`.
+    pub fn quantile<E: Entity, P: Property<E>>(&self, q: f64) -> Option<P::CanonicalValue>
+    where
+        P::CanonicalValue: Ord,
+    {
+        assert!((0.0..=1.0).contains(&q), "the quantile {q} is not in [0, 1]");
+
+        let mut value_counts: Vec<(P::CanonicalValue, usize)> = Vec::new();
+        self.for_each_value::<E, P>(|value, count| value_counts.push((value, count)));
+        let total: usize = value_counts.iter().map(|(_, count)| count).sum();
+        if total == 0 {
+            return None;
+        }
+        value_counts.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let rank = ((q * total as f64).ceil() as usize).max(1);
+        let mut seen = 0;
+        for (value, count) in value_counts {
+            seen += count;
+            if seen >= rank {
+                return Some(value);
+            }
+        }
+        unreachable!("the rank is at most the number of values")
+    }
+
+    /// The fraction of the live entities of type `E` that have a value of property `P`, e.g. the
+    /// share of people with a recorded vaccination date. Only explicit properties can be unset,
+    /// so the coverage of any other property is 1. Returns `NaN` if there are no live entities.
+    pub fn coverage<E: Entity, P: Property<E>>(&self) -> f64 {
+        let population = self.entity_store.get_live_entity_count::<E>();
+        let set_count = match P::initialization_kind() {
+            // Removing an entity clears its values, so every stored value belongs to a live entity.
+            PropertyInitializationKind::Explicit => self
+                .property_store
+                .try_get::<E, P>()
+                .map_or(0, |property_value_store| property_value_store.count_set()),
+            _ => population,
+        };
+        set_count as f64 / population as f64
+    }
+
+    /// Calls `f(value, count)` with the canonical values of property `P` for the live entities
+    /// of type `E`. The counts for a value across calls add up to the number of entities with
+    /// that value.
+    fn for_each_value<E: Entity, P: Property<E>>(
+        &self,
+        mut f: impl FnMut(P::CanonicalValue, usize),
+    ) {
+        match P::initialization_kind() {
+            PropertyInitializationKind::Derived | PropertyInitializationKind::DefaultFn => {
+                for entity_id in self.entity_store.live_entity_ids::<E>() {
+                    f(self.get_property::<E, P>(entity_id).make_canonical(), 1);
+                }
+            }
+            PropertyInitializationKind::Explicit | PropertyInitializationKind::Constant => {
+                // Read the slots of the live entities only, so that nothing left in the slot of a
+                // removed entity is counted.
+                let Some(property_value_store) = self.property_store.try_get::<E, P>() else {
+                    let population = self.entity_store.get_live_entity_count::<E>();
+                    if P::initialization_kind() == PropertyInitializationKind::Constant
+                        && population > 0
+                    {
+                        f(P::default_const().make_canonical(), population);
+                    }
+                    return;
+                };
+                for entity_id in self.entity_store.live_entity_ids::<E>() {
+                    // An unset constant property reads as its default.
+                    if let Some(value) = property_value_store.get(entity_id) {
+                        f(value.make_canonical(), 1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Bin, Binnable};
+    use crate::{
+        Context, define_entity, define_property, entity::property::PropertyInitializationKind,
+        impl_property_with_options,
+    };
+
+    define_entity!(Person);
+
+    // The canonical value is the bare `u8`, so ages can be binned.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct Age(u8);
+    impl_property_with_options!(
+        Age,
+        Person,
+        is_required = true,
+        canonical_value = u8,
+        make_canonical = |age: Age| age.0,
+        make_uncanonical = Age
+    );
+
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+
+    define_property!(
+        struct Vaccinated(bool),
+        Person,
+        default_const = Vaccinated(false)
+    );
+
+    define_property!(struct Weight(u16), Person);
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct IsAdult(bool);
+    impl_property_with_options!(
+        IsAdult,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            IsAdult(age.0 >= 18)
+        }
+    );
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct AgeInMonths(u32);
+    impl_property_with_options!(
+        AgeInMonths,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            AgeInMonths(12 * age.0 as u32)
+        },
+        canonical_value = u32,
+        make_canonical = |age: AgeInMonths| age.0,
+        make_uncanonical = AgeInMonths
+    );
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct DailyContacts(u32);
+    impl_property_with_options!(
+        DailyContacts,
+        Person,
+        default_const = DailyContacts(3),
+        canonical_value = u32,
+        make_canonical = |contacts: DailyContacts| contacts.0,
+        make_uncanonical = DailyContacts
+    );
+
+    fn population() -> Context {
+        let mut context = Context::new();
+        for (age, status) in [
+            (8, None),
+            (15, Some(InfectionStatus::Infected)),
+            (30, None),
+            (42, Some(InfectionStatus::Recovered)),
+            (67, Some(InfectionStatus::Infected)),
+        ] {
+            let person = context.add_entity((Age(age),));
+            if let Some(status) = status {
+                context.set_property(person, status);
+            }
+        }
+        context
+    }
+
+    #[test]
+    fn tabulate_constant_property() {
+        let context = population();
+        assert_eq!(
+            context.tabulate::<Person, InfectionStatus>(),
+            HashMap::from([
+                (InfectionStatus::Susceptible, 2),
+                (InfectionStatus::Infected, 2),
+                (InfectionStatus::Recovered, 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn tabulate_explicit_property_skips_unset() {
+        let context = population();
+        let person = PersonId::new(0);
+        context.set_property(person, Weight(30));
+        assert_eq!(context.tabulate::<Person, Weight>(), HashMap::from([(Weight(30), 1)]));
+    }
+
+    #[test]
+    fn tabulate_derived_property() {
+        let context = population();
+        assert_eq!(
+            context.tabulate::<Person, IsAdult>(),
+            HashMap::from([(IsAdult(false), 2), (IsAdult(true), 3)])
+        );
+    }
+
+    #[test]
+    fn group_by_lists_members() {
+        let mut context = population();
+        let people: Vec<PersonId> = (0..5).map(PersonId::new).collect();
+        assert_eq!(
+            context.group_by::<Person, InfectionStatus>(),
+            HashMap::from([
+                (InfectionStatus::Susceptible, vec![people[0], people[2]]),
+                (InfectionStatus::Infected, vec![people[1], people[4]]),
+                (InfectionStatus::Recovered, vec![people[3]]),
+            ])
+        );
+
+        assert!(context.remove_entity::<Person>(people[4]));
+        context.set_property(people[0], Weight(30));
+        assert_eq!(
+            context.group_by::<Person, Weight>(),
+            HashMap::from([(Weight(30), vec![people[0]])])
+        );
+        assert_eq!(
+            context.group_by::<Person, InfectionStatus>()[&InfectionStatus::Infected],
+            [people[1]]
+        );
+    }
+
+    #[test]
+    fn partition_by_infection_status() {
+        let mut context = population();
+        assert!(context.remove_entity(PersonId::new(2)));
+        let partition = context.partition::<Person, InfectionStatus>();
+
+        let sizes: Vec<(InfectionStatus, usize)> = partition
+            .iter()
+            .map(|(status, people)| (*status, people.len()))
+            .collect();
+        assert_eq!(
+            sizes,
+            [
+                (InfectionStatus::Susceptible, 1),
+                (InfectionStatus::Infected, 2),
+                (InfectionStatus::Recovered, 1),
+            ]
+        );
+        assert_eq!(
+            partition.get(&InfectionStatus::Infected),
+            Some([PersonId::new(1), PersonId::new(4)].as_slice())
+        );
+        assert_eq!(context.partition::<Person, Weight>().len(), 0);
+
+        let sizes: usize = partition.into_iter().map(|(_, people)| people.len()).sum();
+        assert_eq!(sizes, context.entity_store.get_live_entity_count::<Person>());
+    }
+
+    #[test]
+    fn min_and_max_ages() {
+        let mut context = population();
+        assert_eq!(context.min::<Person, Age>(), Some(8));
+        assert_eq!(context.max::<Person, Age>(), Some(67));
+        // Derived values are computed for each person.
+        assert_eq!(context.min::<Person, AgeInMonths>(), Some(96));
+        assert_eq!(context.max::<Person, AgeInMonths>(), Some(804));
+
+        assert!(context.remove_entity::<Person>(PersonId::new(0)));
+        assert!(context.remove_entity::<Person>(PersonId::new(4)));
+        assert_eq!(context.min::<Person, Age>(), Some(15));
+        assert_eq!(context.max::<Person, Age>(), Some(42));
+
+        assert_eq!(Context::new().min::<Person, Age>(), None);
+    }
+
+    #[test]
+    fn quantiles_of_ages() {
+        let mut context = population();
+        assert_eq!(context.quantile::<Person, Age>(0.5), Some(30));
+        assert_eq!(context.quantile::<Person, Age>(0.0), Some(8));
+        assert_eq!(context.quantile::<Person, Age>(0.2), Some(8));
+        assert_eq!(context.quantile::<Person, Age>(0.21), Some(15));
+        assert_eq!(context.quantile::<Person, Age>(0.95), Some(67));
+        assert_eq!(context.quantile::<Person, Age>(1.0), Some(67));
+
+        // The median of an even number of values is the lower middle one.
+        context.add_entity((Age(50),));
+        assert_eq!(context.quantile::<Person, Age>(0.5), Some(30));
+        assert_eq!(Context::new().quantile::<Person, Age>(0.5), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "the quantile 1.5 is not in [0, 1]")]
+    fn quantile_out_of_range() {
+        population().quantile::<Person, Age>(1.5);
+    }
+
+    #[test]
+    fn coverage_of_a_partially_set_property() {
+        let mut context = population();
+        assert_eq!(context.coverage::<Person, Weight>(), 0.0);
+        for index in [0, 3] {
+            context.set_property(PersonId::new(index), Weight(70));
+        }
+        assert_eq!(context.property_store.get::<Person, Weight>().count_set(), 2);
+        assert_eq!(context.coverage::<Person, Weight>(), 0.4);
+
+        assert!(context.remove_entity::<Person>(PersonId::new(3)));
+        assert_eq!(context.coverage::<Person, Weight>(), 0.25);
+        assert_eq!(context.coverage::<Person, InfectionStatus>(), 1.0);
+        assert!(Context::new().coverage::<Person, Weight>().is_nan());
+    }
+
+    #[test]
+    fn tabulate_skips_removed_entities() {
+        let mut context = population();
+        let removed = PersonId::new(1);
+        assert!(context.remove_entity::<Person>(removed));
+        assert_eq!(
+            context.tabulate::<Person, InfectionStatus>(),
+            HashMap::from([
+                (InfectionStatus::Susceptible, 2),
+                (InfectionStatus::Infected, 1),
+                (InfectionStatus::Recovered, 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn tabulate_after_removing_entities_below_a_set_value() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..3).map(|age| context.add_entity((Age(age),))).collect();
+        assert!(context.remove_entity(people[0]));
+        assert!(context.remove_entity(people[1]));
+        context.set_property(people[2], InfectionStatus::Infected);
+
+        assert_eq!(
+            context.tabulate::<Person, InfectionStatus>(),
+            HashMap::from([(InfectionStatus::Infected, 1)])
+        );
+    }
+
+    #[test]
+    fn crosstab_infection_status_by_vaccination() {
+        let context = population();
+        context.set_property(PersonId::new(1), Vaccinated(true));
+        context.set_property(PersonId::new(3), Vaccinated(true));
+
+        let counts = context.crosstab::<Person, InfectionStatus, Vaccinated>();
+        assert_eq!(
+            counts,
+            HashMap::from([
+                ((InfectionStatus::Susceptible, Vaccinated(false)), 2),
+                ((InfectionStatus::Infected, Vaccinated(true)), 1),
+                ((InfectionStatus::Infected, Vaccinated(false)), 1),
+                ((InfectionStatus::Recovered, Vaccinated(true)), 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn crosstab_skips_unset_values() {
+        let context = population();
+        context.set_property(PersonId::new(4), Weight(80));
+        assert_eq!(
+            context.crosstab::<Person, IsAdult, Weight>(),
+            HashMap::from([((IsAdult(true), Weight(80)), 1)])
+        );
+    }
+
+    #[test]
+    fn sum_and_mean_of_explicit_property() {
+        let mut context = population();
+        assert_eq!(context.sum::<Person, Age>(), 8 + 15 + 30 + 42 + 67);
+        assert_eq!(context.mean::<Person, Age>(), 162.0 / 5.0);
+
+        assert!(context.remove_entity(PersonId::new(4)));
+        assert_eq!(context.sum::<Person, Age>(), 8 + 15 + 30 + 42);
+        assert_eq!(context.mean::<Person, Age>(), 23.75);
+    }
+
+    #[test]
+    fn sum_of_derived_property() {
+        let context = population();
+        assert_eq!(context.sum::<Person, AgeInMonths>(), 12 * (8 + 15 + 30 + 42 + 67));
+    }
+
+    #[test]
+    fn sum_of_constant_property_defaults() {
+        let mut context = Context::new();
+        for age in 0..10_000u32 {
+            context.add_entity((Age((age % 100) as u8),));
+        }
+        // No value was set, so the whole population shares the default.
+        assert_eq!(context.sum::<Person, DailyContacts>(), 3 * 10_000);
+
+        context.set_property(PersonId::new(7), DailyContacts(10));
+        assert_eq!(context.sum::<Person, DailyContacts>(), 3 * 9_999 + 10);
+    }
+
+    #[test]
+    fn mean_of_empty_population_is_nan() {
+        let context = Context::new();
+        assert_eq!(context.sum::<Person, Age>(), 0);
+        assert!(context.mean::<Person, Age>().is_nan());
+    }
+
+    #[test]
+    fn histogram_bins_ages_by_decade() {
+        let context = population();
+        assert_eq!(
+            context.histogram::<Person, Age>(10),
+            [(0, 1), (10, 1), (20, 0), (30, 1), (40, 1), (50, 0), (60, 1)]
+                .map(|(start, count)| (Bin { start, end: Some(start + 10) }, count))
+        );
+    }
+
+    #[test]
+    fn histogram_cuts_off_the_last_bin_at_the_largest_value() {
+        let mut context = Context::new();
+        for age in [3, 249, 250, u8::MAX] {
+            context.add_entity((Age(age),));
+        }
+        let histogram = context.histogram::<Person, Age>(50);
+        assert_eq!(
+            histogram,
+            [
+                (Bin { start: 0, end: Some(50) }, 1),
+                (Bin { start: 50, end: Some(100) }, 0),
+                (Bin { start: 100, end: Some(150) }, 0),
+                (Bin { start: 150, end: Some(200) }, 0),
+                (Bin { start: 200, end: Some(250) }, 1),
+                (Bin { start: 250, end: None }, 2),
+            ]
+        );
+        let (last, _) = histogram[5];
+        assert!(last.contains(u8::MAX) && !last.contains(249));
+        assert_eq!(last.to_string(), "250..");
+        assert_eq!(histogram[0].0.to_string(), "0..50");
+        assert_eq!(u8::bin_start(26, 10), None);
+        assert_eq!(i8::bin_start(-13, 10), None);
+    }
+
+    #[test]
+    fn histogram_of_empty_population() {
+        let context = Context::new();
+        assert!(context.histogram::<Person, Age>(10).is_empty());
+    }
+
+    #[test]
+    fn histogram_of_floats() {
+        assert_eq!(2.5f64.bin_index(1.0), 2);
+        assert_eq!((-0.5f64).bin_index(1.0), -1);
+        assert_eq!(f64::bin_start(-1, 0.5), Some(-0.5));
+    }
+
+    #[test]
+    #[should_panic(expected = "the bin width of a histogram must be positive")]
+    fn histogram_with_zero_bin_width() {
+        let context = population();
+        let _ = context.histogram::<Person, Age>(0);
+    }
+}
@@ -0,0 +1,202 @@
+/*!
+
+Random samples of a population.
+
+```rust,ignore
+// Pick someone to schedule the next contact for.
+let person: Option<PersonId> = context.random_entity(&mut rng);
+
+// Pick 10 people to import infections into, favoring the more susceptible.
+let imported = context.sample_weighted::<Person, Susceptibility, _>(&mut rng, 10);
+```
+
+Only live entities are sampled. Entities without a value for an explicit property are never
+chosen.
+
+*/
+
+use rand::Rng;
+
+use crate::{
+    Context,
+    entity::{Entity, EntityId, property::Property},
+};
+
+impl Context {
+    /// Chooses a live entity of type `E` uniformly at random, or returns `None` if there are none.
+    ///
+    /// While at most half of the ids belong to removed entities, this draws ids until it finds a
+    /// live one, which takes fewer than two draws on average. Otherwise it draws a position among
+    /// the live entities and walks to it.
+    pub fn random_entity<E: Entity, R: Rng + ?Sized>(&self, rng: &mut R) -> Option<EntityId<E>> {
+        let entity_count = self.entity_store.get_entity_count::<E>();
+        let live_count = self.entity_store.get_live_entity_count::<E>();
+        if live_count == 0 {
+            return None;
+        }
+        if 2 * live_count >= entity_count {
+            loop {
+                let entity_id = EntityId::new(rng.random_range(0..entity_count));
+                if self.entity_store.is_live(entity_id) {
+                    return Some(entity_id);
+                }
+            }
+        }
+        self.entity_store
+            .live_entity_ids::<E>()
+            .nth(rng.random_range(0..live_count))
+    }
+
+    /// Chooses `n` distinct entities of type `E` at random, each with probability proportional to
+    /// its value of `P`, or every eligible entity if there are no more than `n`. The entities are
+    /// returned in the order they were chosen.
+    ///
+    /// Entities with a weight of zero are never chosen, unless every weight is zero, in which case
+    /// there is no preference and the sample is uniform.
+    ///
+    /// Sampling without replacement changes the weights after every draw, so rather than
+    /// repeatedly searching a cumulative weight array, this gives each entity the random key
+    /// `u^(1/w)` and takes the `n` largest (Efraimidis and Spirakis, 2006), which is equivalent.
+    ///
+    /// Panics if a weight is negative or NaN.
+    pub fn sample_weighted<E, P, R>(&self, rng: &mut R, n: usize) -> Vec<EntityId<E>>
+    where
+        E: Entity,
+        P: Property<E>,
+        P::CanonicalValue: Into<f64>,
+        R: Rng + ?Sized,
+    {
+        let weighted: Vec<(EntityId<E>, f64)> = self
+            .entity_store
+            .live_entity_ids::<E>()
+            .filter_map(|entity_id| {
+                let value = self.try_get_property::<E, P>(entity_id).ok()?;
+                Some((entity_id, value.make_canonical().into()))
+            })
+            .collect();
+        assert!(
+            weighted.iter().all(|(_, weight)| *weight >= 0.0),
+            "sampling weights must be non-negative"
+        );
+        let all_zero = weighted.iter().all(|(_, weight)| *weight == 0.0);
+
+        // Comparing `ln(u) / w` orders the keys the same way as `u^(1/w)` without underflowing.
+        let mut keyed: Vec<(f64, EntityId<E>)> = weighted
+            .into_iter()
+            .filter_map(|(entity_id, weight)| {
+                let weight = if all_zero { 1.0 } else { weight };
+                if weight == 0.0 {
+                    return None;
+                }
+                let u: f64 = rng.random();
+                Some((u.ln() / weight, entity_id))
+            })
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        keyed.truncate(n);
+        keyed.into_iter().map(|(_, entity_id)| entity_id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::{Context, define_entity, impl_property_with_options};
+
+    define_entity!(Person);
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct Susceptibility(u8);
+    impl_property_with_options!(
+        Susceptibility,
+        Person,
+        canonical_value = u8,
+        make_canonical = |susceptibility: Susceptibility| susceptibility.0,
+        make_uncanonical = Susceptibility
+    );
+
+    #[test]
+    fn sampling_favors_high_weights() {
+        let mut context = Context::new();
+        let low: Vec<PersonId> = (0..9).map(|_| context.add_entity(Susceptibility(1))).collect();
+        let high = context.add_entity(Susceptibility(9));
+        let immune = context.add_entity(Susceptibility(0));
+        // Without a susceptibility, a person is never chosen.
+        let unknown: PersonId = context.add_entity(());
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let draws = 10_000;
+        let mut high_count = 0;
+        for _ in 0..draws {
+            let sample = context.sample_weighted::<Person, Susceptibility, _>(&mut rng, 1);
+            assert_eq!(sample.len(), 1);
+            assert_ne!(sample[0], immune);
+            assert_ne!(sample[0], unknown);
+            if sample[0] == high {
+                high_count += 1;
+            }
+        }
+        // The expected share of `high` is 9 / 18.
+        assert!((4_500..5_500).contains(&high_count), "{high_count}");
+
+        let mut everyone = context.sample_weighted::<Person, Susceptibility, _>(&mut rng, 20);
+        assert_eq!(everyone.len(), 10);
+        everyone.sort();
+        let mut expected = low.clone();
+        expected.push(high);
+        assert_eq!(everyone, expected);
+    }
+
+    #[test]
+    fn all_zero_weights_sample_uniformly() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..4).map(|_| context.add_entity(Susceptibility(0))).collect();
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut sample = context.sample_weighted::<Person, Susceptibility, _>(&mut rng, 4);
+        sample.sort();
+        assert_eq!(sample, people);
+        assert_eq!(
+            context.sample_weighted::<Person, Susceptibility, _>(&mut rng, 2).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn random_entity_skips_removed() {
+        let mut context = Context::new();
+        let mut rng = StdRng::seed_from_u64(42);
+        assert_eq!(context.random_entity::<Person, _>(&mut rng), None);
+
+        let people: Vec<PersonId> = (0..10).map(|_| context.add_entity(())).collect();
+        let draws: Vec<PersonId> = (0..5)
+            .map(|_| context.random_entity(&mut rng).unwrap())
+            .collect();
+        let mut rng = StdRng::seed_from_u64(42);
+        let repeated: Vec<PersonId> = (0..5)
+            .map(|_| context.random_entity(&mut rng).unwrap())
+            .collect();
+        assert_eq!(draws, repeated);
+
+        // Both with a few removed entities and with most of them removed.
+        for removed in [&people[..3], &people[3..8]] {
+            for person in removed {
+                assert!(context.remove_entity(*person));
+            }
+            let mut seen = HashSet::new();
+            for _ in 0..1_000 {
+                let person: PersonId = context.random_entity(&mut rng).unwrap();
+                assert!(context.entity_store.is_live(person));
+                seen.insert(person);
+            }
+            assert_eq!(seen.len(), context.entity_store.get_live_entity_count::<Person>());
+        }
+
+        for person in &people[8..] {
+            assert!(context.remove_entity(*person));
+        }
+        assert_eq!(context.random_entity::<Person, _>(&mut rng), None);
+    }
+}
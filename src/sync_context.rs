@@ -0,0 +1,428 @@
+/*!
+
+A copy of property values that can be shared across threads, read by many threads at once and
+written by one at a time.
+
+```rust,ignore
+let mut view = SyncContext::new()
+    .with_property::<Person, Age>(&context)
+    .with_property::<Person, InfectionStatus>(&context);
+std::thread::scope(|scope| {
+    scope.spawn(|| view.filter_entities::<Person, Age>(|age| age.0 >= 65));
+    scope.spawn(|| view.set_property(person, InfectionStatus::Infected));
+});
+view.write_to(&mut context);
+```
+
+A `Context` can't be shared across threads, since reading it may write to it: see the
+[`Context`] documentation. A [`SyncContext`] instead holds a copy of the columns it was given,
+taken on the calling thread, behind an `RwLock`, and is `Send + Sync`. Any number of threads can
+read it at once, while a write waits for the readers to finish and holds off the others until
+it's done. It doesn't follow later changes to the `Context`; build a new one after writing to
+take them in. Values set in a `SyncContext` are held there until [`SyncContext::write_to`] sets
+them in a `Context`, which is where they are validated and where change events are emitted.
+
+Each column holds the value every live entity reads as when it's copied: derived and default
+function values are computed, and an unset constant property holds its default.
+
+*/
+
+use std::{
+    any::{Any, TypeId},
+    collections::{BTreeSet, HashMap},
+    sync::RwLock,
+};
+
+use crate::{
+    Context, InvalidPropertyValueError, PropertyNotSetError,
+    entity::{
+        Entity, EntityId,
+        property::{Property, PropertyInitializationKind},
+    },
+};
+
+/// A copy of some property columns of a [`Context`], which can be read from many threads at once
+/// and written from one at a time. See the [module documentation](self).
+#[derive(Default)]
+pub struct SyncContext {
+    /// The copied columns, keyed by the `TypeId` of their property.
+    columns: RwLock<HashMap<TypeId, Column>>,
+}
+
+/// The values of one property, and the entities whose value was set in the `SyncContext`.
+struct Column {
+    /// The `Vec<Option<P>>` of the values of the property `P`, indexed by entity.
+    values: Box<dyn Any + Send + Sync>,
+    /// The indices of the entities whose value was set since the column was copied or last
+    /// written to a `Context`.
+    written: BTreeSet<usize>,
+    /// Sets the written values in a `Context`.
+    write_to: fn(&Column, &mut Context),
+}
+
+impl SyncContext {
+    /// Creates a `SyncContext` without any columns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copies the value of property `P` of every entity of type `E` out of `context`, replacing
+    /// any earlier copy along with the values set in it.
+    pub fn with_property<E, P>(mut self, context: &Context) -> Self
+    where
+        E: Entity,
+        P: Property<E> + Send + Sync,
+    {
+        let column = Column {
+            values: Box::new(context.copy_values::<E, P>()),
+            written: BTreeSet::new(),
+            write_to: write_column::<E, P>,
+        };
+        self.columns.get_mut().unwrap().insert(P::type_id(), column);
+        self
+    }
+
+    /// Returns the value of property `P` for `entity_id`.
+    ///
+    /// Panics if the entity has no value, or if `P` wasn't copied.
+    pub fn get_property<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>) -> P {
+        self.try_get_property(entity_id)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Returns the value of property `P` for `entity_id`, or an error if the entity has no value,
+    /// e.g. because it was removed.
+    ///
+    /// Panics if `P` wasn't copied.
+    pub fn try_get_property<E: Entity, P: Property<E>>(
+        &self,
+        entity_id: EntityId<E>,
+    ) -> Result<P, PropertyNotSetError> {
+        self.read_column::<E, P, _>(|values| values.get(entity_id.0).cloned().flatten())
+            .ok_or(PropertyNotSetError {
+                property_name: P::name(),
+                entity_name: E::name(),
+                index: entity_id.0,
+            })
+    }
+
+    /// Returns the ids, in order, of the entities of type `E` whose value of property `P`
+    /// satisfies `predicate`. Entities without a value are skipped.
+    ///
+    /// Panics if `P` wasn't copied.
+    pub fn filter_entities<E: Entity, P: Property<E>>(
+        &self,
+        predicate: impl Fn(&P) -> bool,
+    ) -> Vec<EntityId<E>> {
+        self.read_column::<E, P, _>(|values| {
+            values
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| value.as_ref().is_some_and(&predicate))
+                .map(|(index, _)| EntityId::new(index))
+                .collect()
+        })
+    }
+
+    /// Sets the value of property `P` for `entity_id` in the `SyncContext`, waiting for any
+    /// readers or other writer to finish. The value is set in a `Context` by
+    /// [`SyncContext::write_to`].
+    ///
+    /// Panics if `P` is a derived property, if the entity didn't exist when `P` was copied, or if
+    /// `P` wasn't copied.
+    pub fn set_property<E: Entity, P: Property<E>>(&self, entity_id: EntityId<E>, property_value: P) {
+        self.try_set_property(entity_id, property_value)
+            .unwrap_or_else(|error| panic!("{error}"));
+    }
+
+    /// Sets the value of property `P` for `entity_id` like [`SyncContext::set_property`], or
+    /// returns an error, leaving the value unchanged, if `P` is a derived property or the entity
+    /// didn't exist when `P` was copied. [`Property::validate`] runs when the value is written to
+    /// a `Context`, since it may read the `Context`.
+    ///
+    /// Panics if `P` wasn't copied.
+    pub fn try_set_property<E: Entity, P: Property<E>>(
+        &self,
+        entity_id: EntityId<E>,
+        property_value: P,
+    ) -> Result<(), InvalidPropertyValueError> {
+        if P::is_derived() {
+            return Err(InvalidPropertyValueError {
+                property_name: P::name(),
+                message: "the property is derived, so its values can't be set".to_string(),
+            });
+        }
+        let mut columns = self.columns.write().unwrap();
+        let column = columns
+            .get_mut(&P::type_id())
+            .unwrap_or_else(|| not_copied::<E, P>());
+        let values = column.values.downcast_mut::<Vec<Option<P>>>().unwrap();
+        let Some(slot) = values.get_mut(entity_id.0) else {
+            return Err(InvalidPropertyValueError {
+                property_name: P::name(),
+                message: format!("{entity_id} does not exist"),
+            });
+        };
+        *slot = Some(property_value);
+        column.written.insert(entity_id.0);
+        Ok(())
+    }
+
+    /// Sets the values set in this `SyncContext` since it was built, or since the last call, in
+    /// `context`, in property order and then entity order, as [`Context::set_property`] would.
+    ///
+    /// Panics, like [`Context::set_property`], if [`Property::validate`] rejects a value or an
+    /// entity was removed from `context` in the meantime.
+    pub fn write_to(&mut self, context: &mut Context) {
+        for column in self.columns.get_mut().unwrap().values_mut() {
+            (column.write_to)(column, context);
+            column.written.clear();
+        }
+    }
+
+    /// Calls `f` with the values of property `P` while holding the read lock.
+    fn read_column<E: Entity, P: Property<E>, R>(&self, f: impl FnOnce(&[Option<P>]) -> R) -> R {
+        let columns = self.columns.read().unwrap();
+        let column = columns
+            .get(&P::type_id())
+            .unwrap_or_else(|| not_copied::<E, P>());
+        f(column.values.downcast_ref::<Vec<Option<P>>>().unwrap())
+    }
+}
+
+fn not_copied<E: Entity, P: Property<E>>() -> ! {
+    panic!("property {} wasn't copied into the SyncContext", P::name())
+}
+
+/// Sets the written values of the column of property `P` in `context`.
+fn write_column<E: Entity, P: Property<E>>(column: &Column, context: &mut Context) {
+    let values = column.values.downcast_ref::<Vec<Option<P>>>().unwrap();
+    for &index in &column.written {
+        let value = values[index].clone().unwrap();
+        context.set_property(EntityId::<E>::new(index), value);
+    }
+}
+
+impl Context {
+    /// Copies the value of property `P` that each entity of type `E` reads as, or `None` for a
+    /// removed entity or an unset explicit property, indexed by entity. Derived and default
+    /// function values are computed, and default function values are stored as they are.
+    pub(crate) fn copy_values<E: Entity, P: Property<E>>(&self) -> Vec<Option<P>> {
+        let entity_count = self.get_entity_count::<E>();
+        match P::initialization_kind() {
+            PropertyInitializationKind::Explicit | PropertyInitializationKind::Constant => {
+                // The slots of removed entities are empty, and so are the slots of entities that
+                // never set a constant property, which read as the default.
+                let mut values = self.property_store.get::<E, P>().to_values();
+                values.resize(entity_count, None);
+                if P::initialization_kind() == PropertyInitializationKind::Constant {
+                    for (index, value) in values.iter_mut().enumerate() {
+                        if value.is_none() && self.entity_store.is_live(EntityId::<E>::new(index)) {
+                            *value = Some(P::default_const());
+                        }
+                    }
+                }
+                values
+            }
+            PropertyInitializationKind::Derived | PropertyInitializationKind::DefaultFn => {
+                let mut values = vec![None; entity_count];
+                for entity_id in self.entity_store.live_entity_ids::<E>() {
+                    values[entity_id.0] = self.try_get_property::<E, P>(entity_id).ok();
+                }
+                values
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Barrier, mpsc};
+
+    use super::*;
+    use crate::{define_entity, define_property, impl_property_with_options};
+
+    define_entity!(Person);
+
+    define_property!(struct Age(u8), Person, is_required = true);
+    define_property!(
+        enum InfectionStatus {
+            Susceptible,
+            Infected,
+            Recovered,
+        },
+        Person,
+        default_const = InfectionStatus::Susceptible
+    );
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, crate::serde::Serialize, crate::serde::Deserialize)]
+    struct IsAdult(bool);
+    impl_property_with_options!(
+        IsAdult,
+        Person,
+        initialization_kind = PropertyInitializationKind::Derived,
+        compute_derived_fn = |context: &Context, entity_id| {
+            let age: Age = context.get_property(entity_id);
+            IsAdult(age.0 >= 18)
+        }
+    );
+
+    fn view(context: &Context) -> SyncContext {
+        SyncContext::new()
+            .with_property::<Person, Age>(context)
+            .with_property::<Person, InfectionStatus>(context)
+            .with_property::<Person, IsAdult>(context)
+    }
+
+    #[test]
+    fn sync_context_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SyncContext>();
+    }
+
+    #[test]
+    fn view_holds_the_values_read_from_the_context() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..40).map(|age| context.add_entity((Age(age),))).collect();
+        context.set_property(people[3], InfectionStatus::Infected);
+        assert!(context.remove_entity(people[5]));
+        let view = view(&context);
+
+        assert_eq!(view.get_property::<_, Age>(people[20]), Age(20));
+        assert_eq!(view.get_property::<_, InfectionStatus>(people[4]), InfectionStatus::Susceptible);
+        assert_eq!(view.get_property::<_, IsAdult>(people[30]), IsAdult(true));
+        let error = view.try_get_property::<_, InfectionStatus>(people[5]).unwrap_err();
+        assert_eq!(error.index, 5);
+        assert_eq!(
+            view.filter_entities::<Person, InfectionStatus>(|status| *status != InfectionStatus::Susceptible),
+            [people[3]]
+        );
+
+        // Later writes to the context aren't seen.
+        context.set_property(people[4], InfectionStatus::Recovered);
+        assert_eq!(view.get_property::<_, InfectionStatus>(people[4]), InfectionStatus::Susceptible);
+    }
+
+    #[test]
+    fn readers_share_a_view_between_writes() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..100).map(|age| context.add_entity((Age(age),))).collect();
+
+        for round in 0..3 {
+            // The main thread writes, then pauses while the readers share the new view.
+            for person in people.iter().skip(round).step_by(10) {
+                context.set_property(*person, InfectionStatus::Infected);
+            }
+            let view = view(&context);
+            let expected: Vec<PersonId> = context
+                .query::<Person>()
+                .matching(InfectionStatus::Infected)
+                .ids();
+
+            let barrier = Barrier::new(4);
+            let (sender, receiver) = mpsc::channel();
+            std::thread::scope(|scope| {
+                for _ in 0..4 {
+                    let sender = sender.clone();
+                    let (view, barrier) = (&view, &barrier);
+                    scope.spawn(move || {
+                        // Every reader reads at the same time.
+                        barrier.wait();
+                        let infected = view.filter_entities::<Person, InfectionStatus>(|status| {
+                            *status == InfectionStatus::Infected
+                        });
+                        let adults = infected
+                            .iter()
+                            .filter(|person| view.get_property::<_, IsAdult>(**person).0)
+                            .count();
+                        sender.send((infected, adults)).unwrap();
+                    });
+                }
+            });
+            drop(sender);
+
+            let results: Vec<(Vec<PersonId>, usize)> = receiver.iter().collect();
+            assert_eq!(results.len(), 4);
+            for (infected, adults) in results {
+                assert_eq!(infected, expected);
+                assert_eq!(adults, expected.iter().filter(|person| person.0 >= 18).count());
+            }
+        }
+    }
+
+    #[test]
+    fn writers_take_turns_with_readers() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..100).map(|age| context.add_entity((Age(age),))).collect();
+        let mut view = view(&context);
+
+        let barrier = Barrier::new(8);
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let (view, barrier, people) = (&view, &barrier, &people);
+                scope.spawn(move || {
+                    barrier.wait();
+                    for person in people.iter().skip(thread).step_by(4) {
+                        view.set_property(*person, InfectionStatus::Infected);
+                    }
+                });
+            }
+            for _ in 0..4 {
+                let (view, barrier) = (&view, &barrier);
+                scope.spawn(move || {
+                    barrier.wait();
+                    // Writes are never undone, so each read sees at least as many as the last.
+                    let mut seen = 0;
+                    for _ in 0..10 {
+                        let infected = view.filter_entities::<Person, InfectionStatus>(|status| {
+                            *status == InfectionStatus::Infected
+                        });
+                        assert!(infected.len() >= seen);
+                        seen = infected.len();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(
+            view.filter_entities::<Person, InfectionStatus>(|status| *status == InfectionStatus::Infected),
+            people
+        );
+        // The context doesn't change until the writes are written to it.
+        assert_eq!(context.get_property::<_, InfectionStatus>(people[7]), InfectionStatus::Susceptible);
+        view.write_to(&mut context);
+        assert_eq!(
+            context.query::<Person>().matching(InfectionStatus::Infected).ids(),
+            people
+        );
+
+        // Writes are only written once.
+        context.set_property(people[7], InfectionStatus::Recovered);
+        view.write_to(&mut context);
+        assert_eq!(context.get_property::<_, InfectionStatus>(people[7]), InfectionStatus::Recovered);
+    }
+
+    #[test]
+    fn setting_a_derived_property_or_a_missing_entity_is_an_error() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(30),));
+        let view = view(&context);
+
+        let error = view.try_set_property(person, IsAdult(false)).unwrap_err();
+        assert_eq!(error.property_name, "IsAdult");
+        let error = view
+            .try_set_property(PersonId::new(1), InfectionStatus::Infected)
+            .unwrap_err();
+        assert_eq!(error.message, "Person#1 does not exist");
+        assert_eq!(view.get_property::<_, IsAdult>(person), IsAdult(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "property Age wasn't copied into the SyncContext")]
+    fn reading_a_property_that_was_not_copied() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(3),));
+        let view = SyncContext::new().with_property::<Person, InfectionStatus>(&context);
+        view.get_property::<_, Age>(person);
+    }
+}
@@ -20,38 +20,103 @@ Functionality:
 For any functionality of `Vec<T>` that `ValueVec<T>` doesn't provide,
 you can use `into_vec` to convert to a `Vec<T>` at zero cost.
 
+Growth:
+
+By default a `ValueVec` grows like a `Vec`, doubling its capacity when it runs out of room. A
+`ValueVec` created with [`ValueVec::with_growth`] can instead grow by a fixed number of elements
+or to exactly the length required. See [`GrowthStrategy`].
+
+Optional values:
+
+A `ValueVec<Option<T>>` pays for the `Option` tag in every slot unless `T` has a niche to hide
+it in. [`OptionValueVec`] instead keeps a bitset of the set slots next to a dense vector of
+values, which halves the memory of optional `u8`s. A property stores its values in one if its
+`value_storage` option names an `OptionValueVec` of its type.
+
 Soundness:
 
 We require `T` to be `Copy` to avoid subtle soundness issues. However, this requirement
 could be replaced with a requirement that prevents re-entrance into methods of
 `ValueVec<T>` (directly or indirectly) from the `Drop` or `Clone` implementations of `T`.
+For values that aren't `Copy`, e.g. `String`s, use a [`ClonedValueVec`], which keeps its
+values in a `RefCell` instead, so that re-entrance panics rather than aliasing the storage.
 
 */
 
-use std::{cell::UnsafeCell, fmt::Debug};
+use std::{
+    cell::{RefCell, UnsafeCell},
+    fmt::Debug,
+};
+
+/// How a [`ValueVec`] grows its capacity when an insertion doesn't fit.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GrowthStrategy {
+    /// Grow like a `Vec`, at least doubling the capacity. Insertions are amortized O(1).
+    #[default]
+    Doubling,
+    /// Grow the capacity in multiples of this many elements. (Zero is treated as one.)
+    Fixed(usize),
+    /// Grow the capacity to exactly the required length. This never over-allocates, but
+    /// growing one element at a time reallocates on every insertion, so it is best used
+    /// together with `with_capacity` or `reserve` when the final length is known.
+    ExactToLen,
+}
+
+impl GrowthStrategy {
+    /// Ensures `vec` has room for `additional` more elements, growing it according to this strategy.
+    fn reserve<V>(self, vec: &mut Vec<V>, additional: usize) {
+        let required = vec.len() + additional;
+        if required <= vec.capacity() {
+            return;
+        }
+        match self {
+            GrowthStrategy::Doubling => vec.reserve(additional),
+            GrowthStrategy::Fixed(increment) => {
+                let increment = increment.max(1);
+                let shortfall = required - vec.capacity();
+                let new_capacity = vec.capacity() + shortfall.div_ceil(increment) * increment;
+                vec.reserve_exact(new_capacity - vec.len());
+            }
+            GrowthStrategy::ExactToLen => vec.reserve_exact(additional),
+        }
+    }
+}
 
 /**
 A by-value, `ref`-less vector with interior mutability. Values of type `V` can be moved into and out of the vector. We require `V` to be `Copy` to avoid subtle soundness issues.
 */
 pub struct ValueVec<V: Copy> {
     data: UnsafeCell<Vec<V>>,
+    growth: GrowthStrategy,
 }
 
 impl<V: Copy> ValueVec<V> {
     /// Creates an empty `ValueVec`.
     pub fn new() -> Self {
-        Self {
-            data: UnsafeCell::new(Vec::new()),
-        }
+        Self::with_growth(GrowthStrategy::Doubling)
     }
 
     /// Creates with capacity.
     pub fn with_capacity(cap: usize) -> Self {
         Self {
             data: UnsafeCell::new(Vec::with_capacity(cap)),
+            growth: GrowthStrategy::Doubling,
         }
     }
 
+    /// Creates an empty `ValueVec` that grows according to `growth`.
+    pub fn with_growth(growth: GrowthStrategy) -> Self {
+        Self {
+            data: UnsafeCell::new(Vec::new()),
+            growth,
+        }
+    }
+
+    /// The strategy this `ValueVec` uses to grow its capacity.
+    pub fn growth(&self) -> GrowthStrategy {
+        self.growth
+    }
+
     /// Current number of elements.
     #[inline]
     pub fn len(&self) -> usize {
@@ -74,7 +139,8 @@ impl<V: Copy> ValueVec<V> {
 
     /// Ensures capacity for at least `additional` more elements.
     pub fn reserve(&self, additional: usize) {
-        self.with_vec(|v| v.reserve(additional));
+        let growth = self.growth;
+        self.with_vec(|v| growth.reserve(v, additional));
     }
 
     /// Shrinks the capacity as much as possible.
@@ -84,7 +150,11 @@ impl<V: Copy> ValueVec<V> {
 
     /// Pushes a value (by move) onto the end.
     pub fn push(&self, value: V) {
-        self.with_vec(|v| v.push(value));
+        let growth = self.growth;
+        self.with_vec(|v| {
+            growth.reserve(v, 1);
+            v.push(value);
+        });
     }
 
     /// Pops and **returns** the last element (by move), or `None` if empty.
@@ -126,7 +196,9 @@ impl<V: Copy> ValueVec<V> {
 
     /// Inserts `value` at position `index`, shifting elements to the right. Panics if `index` is out of bounds.
     pub fn insert(&self, index: usize, value: V) {
+        let growth = self.growth;
         self.with_vec(|v| {
+            growth.reserve(v, 1);
             v.insert(index, value);
         })
     }
@@ -161,23 +233,34 @@ impl<V: Copy> ValueVec<V> {
     where
         I: IntoIterator<Item = V>,
     {
-        self.with_vec(|v| v.extend(iter));
+        match self.growth {
+            GrowthStrategy::Doubling => self.with_vec(|v| v.extend(iter)),
+            growth => {
+                // Collect first so that the vector grows once, by the number of new elements.
+                let values: Vec<V> = iter.into_iter().collect();
+                self.with_vec(|v| {
+                    growth.reserve(v, values.len());
+                    v.extend(values);
+                });
+            }
+        }
     }
 
     pub fn resize_with<F>(&self, new_len: usize, f: F)
     where
         F: FnMut() -> V,
     {
-        self.with_vec(|v| v.resize_with(new_len, f));
+        let growth = self.growth;
+        self.with_vec(|v| {
+            growth.reserve(v, new_len.saturating_sub(v.len()));
+            v.resize_with(new_len, f);
+        });
     }
 
     /// Returns a **snapshot** `Vec<V>` by cloning all elements.
     ///
     /// Use `From<ValueVec<V>> for Vec<V>` for a zero-cost conversion if you don't want to clone.
-    pub fn to_vec(&self) -> Vec<V>
-    where
-        V: Clone,
-    {
+    pub fn to_vec(&self) -> Vec<V> {
         unsafe { (&*self.data.get()).clone() }
     }
 
@@ -215,6 +298,445 @@ impl<V: Copy> ValueVec<V> {
     }
 }
 
+impl<T: Copy> ValueVec<Option<T>> {
+    /// Iterates over the occupied slots in index order, yielding each index with a clone of its
+    /// value and skipping `None`s.
+    ///
+    /// Consistent with the rest of `ValueVec`, values are yielded by value rather than by
+    /// reference. Each slot is read when the iterator reaches it, so the iterator stays valid
+    /// if the vector is mutated in the meantime.
+    pub fn iter_set(&self) -> impl Iterator<Item = (usize, T)> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index).flatten().map(|value| (index, value)))
+    }
+
+    /// Returns the number of occupied slots, without cloning any values.
+    pub fn count_set(&self) -> usize {
+        self.with_vec(|v| v.iter().filter(|slot| slot.is_some()).count())
+    }
+}
+
+/**
+A vector of optional values that keeps a packed bitset of which slots are set apart from a dense
+vector of the values themselves. A slot takes `size_of::<T>()` bytes plus one bit, where a
+`ValueVec<Option<T>>` slot takes `size_of::<Option<T>>()` bytes, which is twice as much for a
+type without a niche like `u8`. Empty slots hold `T::default()`.
+
+Like a `ValueVec`, it gives out values, never references.
+*/
+#[derive(Clone, Default)]
+pub struct OptionValueVec<T: Copy + Default> {
+    /// Bit `i % 64` of word `i / 64` is set if slot `i` is. Bits past `len` are always clear.
+    presence: ValueVec<u64>,
+    values: ValueVec<T>,
+}
+
+impl<T: Copy + Default> OptionValueVec<T> {
+    /// Creates an empty `OptionValueVec`.
+    pub fn new() -> Self {
+        Self::with_growth(GrowthStrategy::Doubling)
+    }
+
+    /// Creates with room for `cap` slots.
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            presence: ValueVec::with_capacity(cap.div_ceil(64)),
+            values: ValueVec::with_capacity(cap),
+        }
+    }
+
+    /// Creates an empty `OptionValueVec` whose bitset and values grow according to `growth`.
+    pub fn with_growth(growth: GrowthStrategy) -> Self {
+        Self {
+            presence: ValueVec::with_growth(growth),
+            values: ValueVec::with_growth(growth),
+        }
+    }
+
+    /// Current number of slots, set or not.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if the vector has no slots.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns whether the slot at `index` is set. Returns `false` if `index` is out of bounds.
+    pub fn is_set(&self, index: usize) -> bool {
+        index < self.len() && self.presence.at(index / 64) & (1 << (index % 64)) != 0
+    }
+
+    /// Returns the value in the slot at `index`, or `None` if it isn't set or `index` is out of
+    /// bounds.
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.is_set(index).then(|| self.values.at(index))
+    }
+
+    /// Appends a slot holding `value`.
+    pub fn push(&self, value: Option<T>) {
+        let index = self.len();
+        if index.is_multiple_of(64) {
+            self.presence.push(0);
+        }
+        self.values.push(T::default());
+        self.set(index, value);
+    }
+
+    /// Sets the slot at `index` to `value`, emptying it if `value` is `None`. Panics if `index`
+    /// is out of bounds.
+    pub fn set(&self, index: usize, value: Option<T>) {
+        assert!(index < self.len(), "index {index} is out of bounds for length {}", self.len());
+        let (word, mask) = (index / 64, 1 << (index % 64));
+        let bits = self.presence.at(word);
+        match value {
+            Some(value) => {
+                self.values.set(index, value);
+                self.presence.set(word, bits | mask);
+            }
+            None => {
+                self.values.set(index, T::default());
+                self.presence.set(word, bits & !mask);
+            }
+        }
+    }
+
+    /// Sets the slot at `index` to `value`, returning its old value. Panics if `index` is out of
+    /// bounds.
+    pub fn replace(&self, index: usize, value: Option<T>) -> Option<T> {
+        let old = self.get(index);
+        self.set(index, value);
+        old
+    }
+
+    /// Grows the vector to `new_len` with empty slots, or truncates it to `new_len`.
+    pub fn resize(&self, new_len: usize) {
+        self.values.resize_with(new_len, T::default);
+        self.presence.resize_with(new_len.div_ceil(64), || 0);
+        // Clear the bits of truncated slots that share the last word with kept ones.
+        if !new_len.is_multiple_of(64) {
+            let word = new_len / 64;
+            self.presence.set(word, self.presence.at(word) & ((1 << (new_len % 64)) - 1));
+        }
+    }
+
+    /// Removes all slots.
+    pub fn clear(&self) {
+        self.presence.clear();
+        self.values.clear();
+    }
+
+    /// Iterates over the set slots in index order, yielding each index with a clone of its value.
+    /// As with [`ValueVec::iter_set`], each slot is read when the iterator reaches it.
+    pub fn iter_set(&self) -> impl Iterator<Item = (usize, T)> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index).map(|value| (index, value)))
+    }
+
+    /// Returns the number of set slots, counted from the bitset.
+    pub fn count_set(&self) -> usize {
+        (0..self.presence.len())
+            .map(|word| self.presence.at(word).count_ones() as usize)
+            .sum()
+    }
+
+    /// The heap memory allocated for the bitset and the values.
+    pub fn heap_bytes(&self) -> usize {
+        self.presence.capacity() * size_of::<u64>() + self.values.capacity() * size_of::<T>()
+    }
+}
+
+/**
+A vector of optional values that aren't `Copy`, e.g. `String`s, with the same by-value interface
+as a `ValueVec<Option<T>>`. The values are kept in a `RefCell`, so a `Clone` or `Drop`
+implementation that re-enters the vector panics instead of aliasing the storage.
+*/
+#[derive(Clone)]
+pub struct ClonedValueVec<T: Clone> {
+    data: RefCell<Vec<Option<T>>>,
+    growth: GrowthStrategy,
+}
+
+impl<T: Clone> ClonedValueVec<T> {
+    /// Creates an empty `ClonedValueVec`.
+    pub fn new() -> Self {
+        Self::with_growth(GrowthStrategy::Doubling)
+    }
+}
+
+impl<T: Clone> Default for ClonedValueVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Debug> Debug for ClonedValueVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.data.borrow().fmt(f)
+    }
+}
+
+/// The interface a `PropertyValueStore` uses to keep one optional value per entity. Like a
+/// `ValueVec`, every method takes `&self` and moves values in and out rather than giving out
+/// references.
+pub trait OptionSlots<T>: Clone {
+    fn with_capacity(cap: usize) -> Self;
+    fn with_growth(growth: GrowthStrategy) -> Self;
+    /// The number of slots, set or not.
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The number of slots the vector can hold without reallocating.
+    fn capacity(&self) -> usize;
+    /// The heap memory allocated for the slots, not counting memory owned by the values.
+    fn heap_bytes(&self) -> usize;
+    fn reserve(&self, additional: usize);
+    /// The value in the slot at `index`, or `None` if it is empty or `index` is out of bounds.
+    fn get(&self, index: usize) -> Option<T>;
+    /// Sets the slot at `index`. Panics if `index` is out of bounds.
+    fn set(&self, index: usize, value: Option<T>);
+    fn push(&self, value: Option<T>);
+    fn resize_with(&self, new_len: usize, f: impl FnMut() -> Option<T>);
+    fn extend(&self, values: impl IntoIterator<Item = Option<T>>);
+    fn clear(&self);
+    fn shrink_to_fit(&self);
+    /// The number of slots holding a value.
+    fn count_set(&self) -> usize;
+    /// A copy of every slot in index order.
+    fn to_vec(&self) -> Vec<Option<T>>;
+}
+
+impl<T: Copy> OptionSlots<T> for ValueVec<Option<T>> {
+    fn with_capacity(cap: usize) -> Self {
+        ValueVec::with_capacity(cap)
+    }
+
+    fn with_growth(growth: GrowthStrategy) -> Self {
+        ValueVec::with_growth(growth)
+    }
+
+    fn len(&self) -> usize {
+        ValueVec::len(self)
+    }
+
+    fn capacity(&self) -> usize {
+        ValueVec::capacity(self)
+    }
+
+    fn heap_bytes(&self) -> usize {
+        ValueVec::capacity(self) * size_of::<Option<T>>()
+    }
+
+    fn reserve(&self, additional: usize) {
+        ValueVec::reserve(self, additional);
+    }
+
+    fn get(&self, index: usize) -> Option<T> {
+        ValueVec::get(self, index).flatten()
+    }
+
+    fn set(&self, index: usize, value: Option<T>) {
+        ValueVec::set(self, index, value);
+    }
+
+    fn push(&self, value: Option<T>) {
+        ValueVec::push(self, value);
+    }
+
+    fn resize_with(&self, new_len: usize, f: impl FnMut() -> Option<T>) {
+        ValueVec::resize_with(self, new_len, f);
+    }
+
+    fn extend(&self, values: impl IntoIterator<Item = Option<T>>) {
+        ValueVec::extend(self, values);
+    }
+
+    fn clear(&self) {
+        ValueVec::clear(self);
+    }
+
+    fn shrink_to_fit(&self) {
+        ValueVec::shrink_to_fit(self);
+    }
+
+    fn count_set(&self) -> usize {
+        ValueVec::count_set(self)
+    }
+
+    fn to_vec(&self) -> Vec<Option<T>> {
+        ValueVec::to_vec(self)
+    }
+}
+
+impl<T: Clone> OptionSlots<T> for ClonedValueVec<T> {
+    fn with_capacity(cap: usize) -> Self {
+        Self {
+            data: RefCell::new(Vec::with_capacity(cap)),
+            growth: GrowthStrategy::Doubling,
+        }
+    }
+
+    fn with_growth(growth: GrowthStrategy) -> Self {
+        Self {
+            data: RefCell::new(Vec::new()),
+            growth,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.data.borrow().len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.borrow().capacity()
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.capacity() * size_of::<Option<T>>()
+    }
+
+    fn reserve(&self, additional: usize) {
+        self.growth.reserve(&mut self.data.borrow_mut(), additional);
+    }
+
+    fn get(&self, index: usize) -> Option<T> {
+        self.data.borrow().get(index).cloned().flatten()
+    }
+
+    fn set(&self, index: usize, value: Option<T>) {
+        // The old value is dropped after the borrow ends.
+        let _old = std::mem::replace(&mut self.data.borrow_mut()[index], value);
+    }
+
+    fn push(&self, value: Option<T>) {
+        let mut data = self.data.borrow_mut();
+        self.growth.reserve(&mut data, 1);
+        data.push(value);
+    }
+
+    fn resize_with(&self, new_len: usize, f: impl FnMut() -> Option<T>) {
+        let mut data = self.data.borrow_mut();
+        let additional = new_len.saturating_sub(data.len());
+        self.growth.reserve(&mut data, additional);
+        data.resize_with(new_len, f);
+    }
+
+    fn extend(&self, values: impl IntoIterator<Item = Option<T>>) {
+        // Collect first, so that the iterator runs while the vector isn't borrowed.
+        let values: Vec<Option<T>> = values.into_iter().collect();
+        let mut data = self.data.borrow_mut();
+        self.growth.reserve(&mut data, values.len());
+        data.extend(values);
+    }
+
+    fn clear(&self) {
+        let capacity = self.capacity();
+        let _old = self.data.replace(Vec::with_capacity(capacity));
+    }
+
+    fn shrink_to_fit(&self) {
+        self.data.borrow_mut().shrink_to_fit();
+    }
+
+    fn count_set(&self) -> usize {
+        self.data.borrow().iter().filter(|slot| slot.is_some()).count()
+    }
+
+    fn to_vec(&self) -> Vec<Option<T>> {
+        self.data.borrow().clone()
+    }
+}
+
+impl<T: Copy + Default> OptionSlots<T> for OptionValueVec<T> {
+    fn with_capacity(cap: usize) -> Self {
+        OptionValueVec::with_capacity(cap)
+    }
+
+    fn with_growth(growth: GrowthStrategy) -> Self {
+        OptionValueVec::with_growth(growth)
+    }
+
+    fn len(&self) -> usize {
+        OptionValueVec::len(self)
+    }
+
+    fn capacity(&self) -> usize {
+        self.values.capacity()
+    }
+
+    fn heap_bytes(&self) -> usize {
+        OptionValueVec::heap_bytes(self)
+    }
+
+    fn reserve(&self, additional: usize) {
+        let words = (self.len() + additional).div_ceil(64);
+        self.presence.reserve(words.saturating_sub(self.presence.len()));
+        self.values.reserve(additional);
+    }
+
+    fn get(&self, index: usize) -> Option<T> {
+        OptionValueVec::get(self, index)
+    }
+
+    fn set(&self, index: usize, value: Option<T>) {
+        OptionValueVec::set(self, index, value);
+    }
+
+    fn push(&self, value: Option<T>) {
+        OptionValueVec::push(self, value);
+    }
+
+    fn resize_with(&self, new_len: usize, mut f: impl FnMut() -> Option<T>) {
+        let old_len = self.len();
+        self.resize(new_len);
+        for index in old_len..new_len {
+            self.set(index, f());
+        }
+    }
+
+    fn extend(&self, values: impl IntoIterator<Item = Option<T>>) {
+        for value in values {
+            self.push(value);
+        }
+    }
+
+    fn clear(&self) {
+        OptionValueVec::clear(self);
+    }
+
+    fn shrink_to_fit(&self) {
+        self.presence.shrink_to_fit();
+        self.values.shrink_to_fit();
+    }
+
+    fn count_set(&self) -> usize {
+        OptionValueVec::count_set(self)
+    }
+
+    fn to_vec(&self) -> Vec<Option<T>> {
+        (0..self.len()).map(|index| self.get(index)).collect()
+    }
+}
+
+impl<T: Copy + Default + Debug> Debug for OptionValueVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries((0..self.len()).map(|index| self.get(index)))
+            .finish()
+    }
+}
+
+impl<V: Copy> Clone for ValueVec<V> {
+    fn clone(&self) -> Self {
+        Self {
+            data: UnsafeCell::new(self.to_vec()),
+            growth: self.growth,
+        }
+    }
+}
+
 impl<V: Copy> Default for ValueVec<V> {
     fn default() -> Self {
         Self::new()
@@ -235,6 +757,7 @@ impl<V: Copy> From<Vec<V>> for ValueVec<V> {
     fn from(src: Vec<V>) -> Self {
         Self {
             data: UnsafeCell::new(src),
+            growth: GrowthStrategy::Doubling,
         }
     }
 }
@@ -259,7 +782,7 @@ impl<V: Copy> IntoIterator for ValueVec<V> {
 
 #[cfg(test)]
 mod tests {
-    use super::ValueVec;
+    use super::{ClonedValueVec, GrowthStrategy, OptionSlots, OptionValueVec, ValueVec};
 
     #[test]
     fn push_pop() {
@@ -275,6 +798,41 @@ mod tests {
         assert_eq!(v.pop(), None);
     }
 
+    #[test]
+    fn iter_set_skips_empty_slots() {
+        let v = ValueVec::new();
+        v.extend([None, Some(1), None, None, Some(4), None]);
+        assert_eq!(v.iter_set().collect::<Vec<_>>(), vec![(1, 1), (4, 4)]);
+    }
+
+    #[test]
+    fn growth_strategies() {
+        let doubling = ValueVec::with_growth(GrowthStrategy::Doubling);
+        let fixed = ValueVec::with_growth(GrowthStrategy::Fixed(10));
+        let exact = ValueVec::with_growth(GrowthStrategy::ExactToLen);
+        for v in [&doubling, &fixed, &exact] {
+            for i in 0..33 {
+                v.push(i);
+            }
+        }
+        assert!(doubling.capacity() >= 64);
+        assert_eq!(fixed.capacity(), 40);
+        assert_eq!(exact.capacity(), 33);
+
+        for v in [&fixed, &exact] {
+            v.resize_with(45, || 0);
+            v.extend([1, 2, 3]);
+        }
+        assert_eq!(fixed.capacity(), 50);
+        assert_eq!(exact.capacity(), 48);
+
+        // Explicit reservations follow the strategy too.
+        fixed.reserve(3);
+        assert_eq!(fixed.capacity(), 60);
+        exact.reserve(100);
+        assert_eq!(exact.capacity(), 148);
+    }
+
     #[test]
     fn get_cloned_and_replace() {
         let v = ValueVec::new();
@@ -378,4 +936,81 @@ mod tests {
         let collected_from_into: Vec<_> = vv2.into();
         assert_eq!(collected_from_iter, collected_from_into);
     }
+
+    #[test]
+    fn option_value_vec_matches_vec_of_options() {
+        let v = OptionValueVec::new();
+        let mut expected: Vec<Option<u8>> = Vec::new();
+        for i in 0..150u8 {
+            let value = (i % 3 != 0).then_some(i);
+            v.push(value);
+            expected.push(value);
+        }
+        v.set(4, None);
+        expected[4] = None;
+        assert_eq!(v.replace(9, Some(200)), None);
+        expected[9] = Some(200);
+        assert_eq!(v.replace(10, None), Some(10));
+        expected[10] = None;
+
+        let check = |v: &OptionValueVec<u8>, expected: &[Option<u8>]| {
+            assert_eq!(v.len(), expected.len());
+            assert_eq!((0..v.len()).map(|index| v.get(index)).collect::<Vec<_>>(), expected);
+            assert_eq!(v.count_set(), expected.iter().flatten().count());
+            assert_eq!(v.iter_set().count(), v.count_set());
+        };
+        check(&v, &expected);
+        assert_eq!(v.get(150), None);
+        assert!(!v.is_set(1_000));
+
+        // Truncating to the middle of a word clears the bits of the dropped slots.
+        v.resize(70);
+        expected.truncate(70);
+        check(&v, &expected);
+        v.resize(130);
+        expected.resize(130, None);
+        check(&v, &expected);
+
+        v.clear();
+        assert!(v.is_empty());
+        assert_eq!(v.count_set(), 0);
+    }
+
+    #[test]
+    fn option_value_vec_saves_memory() {
+        let len = 10_000;
+        let packed = OptionValueVec::with_capacity(len);
+        let options = ValueVec::with_capacity(len);
+        for i in 0..len {
+            let value = (i % 2 == 0).then_some(i as u8);
+            packed.push(value);
+            options.push(value);
+        }
+        assert_eq!(size_of::<Option<u8>>(), 2);
+        let option_bytes = options.capacity() * size_of::<Option<u8>>();
+        assert_eq!(option_bytes, 2 * len);
+        // One byte per value, plus one bit per slot rounded up to whole words.
+        assert_eq!(packed.heap_bytes(), len + len.div_ceil(64) * 8);
+        assert!(packed.heap_bytes() * 10 < option_bytes * 6);
+    }
+
+    #[test]
+    fn cloned_value_vec_holds_strings() {
+        let v = ClonedValueVec::with_growth(GrowthStrategy::Fixed(4));
+        v.push(Some("a".to_string()));
+        v.resize_with(3, || None);
+        v.set(2, Some("c".to_string()));
+        assert_eq!(v.capacity(), 4);
+        assert_eq!(v.get(0).as_deref(), Some("a"));
+        assert_eq!(v.get(1), None);
+        assert_eq!(v.get(5), None);
+        assert_eq!(v.count_set(), 2);
+
+        let copy = v.clone();
+        v.set(0, None);
+        assert_eq!(copy.to_vec(), [Some("a".to_string()), None, Some("c".to_string())]);
+        v.clear();
+        assert_eq!(v.len(), 0);
+        assert_eq!(copy.len(), 3);
+    }
 }